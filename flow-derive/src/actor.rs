@@ -79,11 +79,25 @@ pub fn expand(input: DeriveInput) -> TokenStream {
         impl#imp_g flow_rs::node::Actor for #ident#ty_g
             #where_g {
                 fn start(mut self: Box<Self>, ctx: flow_rs::graph::Context, resources: flow_rs::resource::ResourceCollection) -> flow_rs::rt::task::JoinHandle<anyhow::Result<()>> {
+                    let exec_observer = flow_rs::metrics::ExecObserver::current();
+                    let exec_span = flow_rs::trace::ExecSpan::current();
+                    let stop_token = flow_rs::node::StopToken::current();
+                    let pause_token = flow_rs::node::PauseToken::current();
                     flow_rs::rt::task::#spawn_func(async move {
                         self.initialize(resources).await;
                         let mut empty_n = 0;
                         loop  {
-                            self.exec(&ctx).await?;
+                            pause_token.wait().await;
+                            let exec_start = std::time::Instant::now();
+                            let exec_guard = exec_span.enter();
+                            let ret = stop_token.or_cancelled(self.exec(&ctx)).await;
+                            drop(exec_guard);
+                            match ret {
+                                Some(ret) => ret?,
+                                // the current exec is dropped, and the node is still finalized
+                                None => break,
+                            }
+                            exec_observer.observe(exec_start);
                             if #inputs_n > 0 {
                                 let mut min_empty_n = usize::MAX;
                                 #(#recv_empty_n )*
@@ -94,7 +108,7 @@ pub fn expand(input: DeriveInput) -> TokenStream {
 
                                 empty_n = min_empty_n;
                             }
-                            if self.is_allinp_closed() {
+                            if self.is_allinp_closed() || stop_token.is_stopped() {
                                 break
                             }
                         }
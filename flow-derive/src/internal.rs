@@ -46,6 +46,20 @@ pub fn submit_expand<ID: ToTokens>(input: CollectionSlice<ID>) -> TokenStream {
     }
 }
 
+pub fn dylib_export_expand() -> TokenStream {
+    quote! {
+        #[no_mangle]
+        pub extern "C" fn __megflow_abi_version() -> *const std::os::raw::c_char {
+            flow_rs::loader::dylib::ABI_VERSION.as_ptr() as *const std::os::raw::c_char
+        }
+
+        #[no_mangle]
+        pub unsafe extern "C" fn __megflow_collect(collection: *mut std::ffi::c_void) {
+            flow_rs::loader::dylib::export(collection)
+        }
+    }
+}
+
 pub struct FeatureDeclare {
     name: Ident,
     fields: Punctuated<Field, Token![,]>,
@@ -182,14 +196,14 @@ pub fn parser_expand(input: DeriveInput) -> TokenStream {
         quote! {
             type PathSet = std::collections::BTreeSet<std::path::PathBuf>;
             impl<'a> #ident {
-                fn from_impl(path: &std::path::Path, template: &str, dynamic: &templar::InnerData, mut visit: PathSet) -> anyhow::Result<(#ident, PathSet)> {
+                fn from_impl(path: &std::path::Path, template: &str, format: flow_rs::config::format::Format, dynamic: &templar::InnerData, mut visit: PathSet) -> anyhow::Result<(#ident, PathSet)> {
                     use templar::Context;
 
                     let template = flow_rs::config::parser::TEMPLAR.parse_template(template)?;
                     let context = templar::StandardContext::new();
                     context.set(dynamic.clone())?;
                     let content = template.render(&context)?;
-                    let mut config: #ident = toml::from_str(&content)?;
+                    let mut config: #ident = format.parse(&content)?;
 
                     let include: Vec<_> = config.include.iter().cloned().collect();
 
@@ -208,7 +222,8 @@ pub fn parser_expand(input: DeriveInput) -> TokenStream {
                         }
                         let sub_template = std::fs::read_to_string(&abs_path)?;
                         visit.insert(abs_path.clone());
-                        let mut ret = Self::from_impl(&abs_path, &sub_template, dynamic, visit)?;
+                        let format = flow_rs::config::format::Format::from_path(&abs_path);
+                        let mut ret = Self::from_impl(&abs_path, &sub_template, format, dynamic, visit)?;
                         let sub_config = &mut ret.0;
                         #(#list) *
                         #(#set) *
@@ -230,14 +245,22 @@ pub fn parser_expand(input: DeriveInput) -> TokenStream {
                     };
                     let dynamic_data: templar::InnerData = toml::from_str(&dynamic)?;
                     let template_content = std::fs::read_to_string(template)?;
+                    let format = flow_rs::config::format::Format::from_path(template);
 
-                    Self::from_impl(template, &template_content, &dynamic_data, set).map(|x| x.0)
+                    Self::from_impl(template, &template_content, format, &dynamic_data, set).map(|x| x.0)
                 }
 
-                fn from_str(template: &str, dynamic: Option<&str>) -> anyhow::Result<#ident> {
+                fn from_str_in(template: &str, path: Option<&std::path::Path>, dynamic: Option<&str>, format: flow_rs::config::format::Format) -> anyhow::Result<#ident> {
                     let dynamic_data: templar::InnerData = toml::from_str(dynamic.unwrap_or(""))?;
-                    let cur = std::env::current_dir()?;
-                    Self::from_impl(&cur, template, &dynamic_data, std::collections::BTreeSet::new()).map(|x| x.0)
+                    let mut set = std::collections::BTreeSet::new();
+                    let path = match path {
+                        Some(path) => {
+                            set.insert(path.to_owned());
+                            path.to_owned()
+                        }
+                        None => std::env::current_dir()?,
+                    };
+                    Self::from_impl(&path, template, format, &dynamic_data, set).map(|x| x.0)
                 }
             }
         }
@@ -250,11 +273,12 @@ pub fn parser_expand(input: DeriveInput) -> TokenStream {
                     } else {
                         None
                     };
+                    let format = flow_rs::config::format::Format::from_path(template);
                     let template = std::fs::read_to_string(template)?;
-                    Self::from_str(&template, dynamic.as_ref().map(|x| x.as_ref()))
+                    Self::from_str_as(&template, dynamic.as_ref().map(|x| x.as_ref()), format)
                 }
 
-                fn from_str(template: &str, dynamic: Option<&str>) -> anyhow::Result<Self> {
+                fn from_str_in(template: &str, _: Option<&std::path::Path>, dynamic: Option<&str>, format: flow_rs::config::format::Format) -> anyhow::Result<Self> {
                     use templar::Context;
                     use flow_rs::config::parser::TEMPLAR;
                     let dynamic_data: templar::InnerData = toml::from_str(dynamic.unwrap_or(""))?;
@@ -262,8 +286,7 @@ pub fn parser_expand(input: DeriveInput) -> TokenStream {
                     let context = templar::StandardContext::new();
                     context.set(dynamic_data)?;
                     let content = template.render(&context)?;
-                    let config = toml::from_str(&content)?;
-                    Ok(config)
+                    format.parse(&content)
                 }
             }
         }
@@ -87,6 +87,13 @@ pub fn resource_register(input: TokenStream) -> TokenStream {
     resource::registry_expand(input).into()
 }
 
+/// A proc macro used to export nodes and resources registered in a shared library, which is loaded by
+/// `flow_rs::loader::PluginType::Dylib`. `dylib_export!()`
+#[proc_macro]
+pub fn dylib_export(_: TokenStream) -> TokenStream {
+    internal::dylib_export_expand().into()
+}
+
 #[doc(hidden)]
 #[proc_macro]
 pub fn submit(input: TokenStream) -> TokenStream {
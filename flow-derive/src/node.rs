@@ -107,12 +107,28 @@ pub fn expand(input: DeriveInput) -> TokenStream {
         }
     }
 
+    fn port_field_f((_, ident, _): IterArgs) -> TokenStream {
+        quote_spanned! {ident.span()=> pub #ident: flow_rs::PortRef,}
+    }
+    fn port_ref_f((_, ident, _): IterArgs) -> TokenStream {
+        quote_spanned! {ident.span()=> #ident: flow_rs::PortRef::new(node, stringify!(#ident)),}
+    }
+
     let seti = extract_ports(&input.data, type_name::IN_T, set_f);
     let seto = extract_ports(&input.data, type_name::OUT_T, set_f);
     let set_dyni = extract_ports(&input.data, type_name::IN_T, set_dyn_f);
     let set_dyno = extract_ports(&input.data, type_name::OUT_T, set_dyn_f);
     let closeo = extract_ports(&input.data, type_name::OUT_T, close_f);
     let is_closei = extract_ports(&input.data, type_name::IN_T, is_close_f);
+    let port_fields = [type_name::IN_T, type_name::OUT_T]
+        .iter()
+        .flat_map(|ty| extract_ports(&input.data, ty, port_field_f))
+        .collect::<Vec<_>>();
+    let port_refs = [type_name::IN_T, type_name::OUT_T]
+        .iter()
+        .flat_map(|ty| extract_ports(&input.data, ty, port_ref_f))
+        .collect::<Vec<_>>();
+    let ports_ident = crate::lit::ident(format!("__{}Ports", ident));
 
     let (imp_g, ty_g, where_g) = input.generics.split_for_impl();
     quote! {
@@ -143,5 +159,21 @@ pub fn expand(input: DeriveInput) -> TokenStream {
                     is_closed
                 }
             }
+
+        #[doc(hidden)]
+        #[allow(dead_code, non_camel_case_types)]
+        pub struct #ports_ident {
+            #(#port_fields)*
+        }
+
+        impl#imp_g flow_rs::NodePorts for #ident#ty_g
+            #where_g {
+                type Ports = #ports_ident;
+                fn ports(node: &str) -> Self::Ports {
+                    #ports_ident {
+                        #(#port_refs)*
+                    }
+                }
+            }
     }
 }
@@ -58,6 +58,8 @@ async fn analyze(
 ) -> Result<Either<Image, impl Reply>, Rejection> {
     let img = img.into_bgr8();
     let id = state.id();
+    let mut span = flow_rs::trace::Span::root("ImageServer/analyze");
+    span.set_attribute("megflow.partial_id", id);
 
     let pyobject: PyObject = Python::with_gil(|py| -> PyResult<_> {
         let data = img.as_raw();
@@ -81,6 +83,7 @@ async fn analyze(
             pyobject,
             EnvelopeInfo {
                 partial_id: Some(id),
+                trace: span.context(),
                 ..Default::default()
             },
         );
@@ -124,6 +127,8 @@ impl ImageServer {
     async fn initialize(&mut self, _: ResourceCollection) {}
     async fn finalize(&mut self) {}
     async fn exec(&mut self, _: &Context) -> Result<()> {
+        // requests are traced by spans of `analyze`
+        flow_rs::trace::detach_exec();
         let state = State::new(self.ty, std::mem::take(&mut self.out));
         let mapping = state.mapping.clone();
         let inp = std::mem::take(&mut self.inp);
@@ -37,3 +37,28 @@ where
 {
     rweb::reject::custom(RejectCause::from(err))
 }
+
+/// Too many video streams are alive
+#[derive(Debug)]
+struct TooManyRequests(String);
+
+impl rweb::reject::Reject for TooManyRequests {}
+
+pub fn too_many_requests<T>(err: T) -> Rejection
+where
+    T: std::fmt::Display,
+{
+    rweb::reject::custom(TooManyRequests(err.to_string()))
+}
+
+/// Reply 429 to requests rejected by `too_many_requests`, and leave the other rejections as they are
+pub async fn recover(err: Rejection) -> Result<impl rweb::Reply, Rejection> {
+    if let Some(TooManyRequests(msg)) = err.find() {
+        Ok(rweb::reply::with_status(
+            msg.clone(),
+            hyper::StatusCode::TOO_MANY_REQUESTS,
+        ))
+    } else {
+        Err(err)
+    }
+}
@@ -128,7 +128,7 @@ impl VideoServer {
         let mut recv_msgs = FuturesUnordered::new();
         let mut recv_conns = FuturesUnordered::new();
         let mut spawn_decode = FuturesUnordered::new();
-        let listen = serve(filter.or(openapi_docs(spec)))
+        let listen = serve(filter.or(openapi_docs(spec)).recover(error::recover))
             .run(([0, 0, 0, 0], self.port))
             .fuse();
         recv_conns.push(self.inp.fetch());
@@ -164,7 +164,15 @@ impl VideoServer {
                 // spawn subgraph
                 ret = spawn_decode.select_next_some() => {
                     if let Ok((id, url, waker)) = ret {
-                        self.out.create(id, self.resources.clone().unwrap(), Default::default()).await.expect("broker has closed");
+                        match self.out.create(id, self.resources.clone().unwrap(), Default::default()).await {
+                            Ok(_) => (),
+                            Err(err) if err.is::<TooManyInstances>() => {
+                                waker.send(Err(error::too_many_requests(err))).ok();
+                                spawn_decode.push(r.recv());
+                                continue;
+                            }
+                            Err(err) => panic!("broker has closed: {:?}", err),
+                        }
                         let (_, port) = self.out.fetch().await.expect("broker has closed");
                         let url = urlencoding::decode(&url).unwrap().into_owned();
                         let url_cloned = url.clone();
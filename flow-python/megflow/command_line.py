@@ -31,17 +31,47 @@ def megflow_run():
     parser.add_argument('-m', '--module', type=str, help='module path')
     parser.add_argument('-c', '--config', type=str, help='config path')
     parser.add_argument('--dynamic', type=str, help='dynamic config path')
+    parser.add_argument('--watch', help='reload the graph when the config changes', action='store_true')
     parser.add_argument('--version', action='version', version='%(prog)s {version}'.format(version=megflow.__version__))
 
     args = parser.parse_args()
 
-    megflow.Graph(
+    graph = megflow.Graph(
         dump=args.dump, 
         plugin_path=args.plugin, 
         module_path=args.module, 
         config_path=args.config, 
         dynamic_path = args.dynamic
-    ).wait()
+    )
+    if args.watch:
+        config = args.config
+        if config is None:
+            plugin = os.path.realpath(args.plugin)
+            config = os.path.join(plugin, os.path.basename(plugin) + '.toml')
+        watch(graph, config, args.dynamic)
+    graph.wait()
+
+
+def watch(graph, config, dynamic, interval=1.0):
+    # poll the config between waiting for the graph, which returns once the graph is finished
+    def mtimes():
+        return [os.path.getmtime(path) if path and os.path.exists(path) else None for path in (config, dynamic)]
+
+    def read(path):
+        with open(path) as f:
+            return f.read()
+
+    last = mtimes()
+    while not graph.join(interval):
+        current = mtimes()
+        if current == last:
+            continue
+        last = current
+        try:
+            report = graph.reload(read(config), read(dynamic) if dynamic else None)
+            print('reloaded, restarted nodes: {}, updated graphs: {}'.format(report['restarted'], report['updated_graphs']))
+        except Exception as exc:
+            print('failed to reload:', exc)
 
 
 def run_with_plugins():
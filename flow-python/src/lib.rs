@@ -13,14 +13,19 @@ use flow_rs::loader::python::channel::*;
 use flow_rs::loader::python::envelope::envelope_register;
 use flow_rs::loader::python::utils::utils_register;
 use flow_rs::prelude::*;
+use pyo3::exceptions::PyRuntimeError;
 use pyo3::prelude::*;
+use pyo3::types::PyDict;
 use pyo3::wrap_pyfunction;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::{Arc, Once};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::time::Duration;
 
 #[pyclass(module = "megflow")]
 pub struct Graph {
+    id: u64,
     graph: Option<MainGraph>,
     handle: Option<flow_rs::rt::task::JoinHandle<Result<()>>>,
     inps: HashMap<String, PyObject>,
@@ -28,6 +33,28 @@ pub struct Graph {
 }
 
 static ONCE_INIT: Once = Once::new();
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+lazy_static::lazy_static! {
+    // graphs shut down by SIGINT or SIGTERM, with their timeouts
+    static ref RUNNING: Mutex<HashMap<u64, (ShutdownHandle, Duration)>> = Default::default();
+}
+
+fn on_terminate() {
+    let graphs: Vec<_> = RUNNING.lock().unwrap().drain().map(|(_, v)| v).collect();
+    flow_rs::rt::task::block_on(async {
+        for (handle, timeout) in graphs {
+            let report = handle.shutdown(timeout).await;
+            if !report.drained {
+                log::warn!(
+                    "graph is shut down, cancelled nodes: {:?}",
+                    report.cancelled
+                );
+            }
+        }
+    });
+    unsafe { libc::_exit(0) }
+}
 
 #[pyfunction]
 fn version() -> &'static str {
@@ -49,6 +76,29 @@ impl Graph {
                 });
             });
         }
+        RUNNING.lock().unwrap().remove(&self.id);
+    }
+
+    /// Wait at most `timeout` seconds for the graph to finish without closing its inputs, and return whether it is finished
+    fn join(&mut self, py: Python, timeout: f64) -> bool {
+        let handle = match self.handle.as_mut() {
+            Some(handle) => handle,
+            None => return true,
+        };
+        let ret = py.allow_threads(|| {
+            flow_rs::rt::task::block_on(flow_rs::rt::future::timeout(
+                Duration::from_secs_f64(timeout),
+                handle,
+            ))
+        });
+        match ret {
+            Ok(ret) => {
+                ret.unwrap();
+                self.handle = None;
+                true
+            }
+            Err(_) => false,
+        }
     }
 
     fn inputs(&self) -> Vec<&str> {
@@ -68,12 +118,122 @@ impl Graph {
     }
 
     fn close(&mut self) {
+        RUNNING.lock().unwrap().remove(&self.id);
         self.inps.clear();
         if let Some(graph) = self.graph.take() {
             graph.stop();
         }
     }
 
+    /// Close inputs, wait at most `timeout` seconds for the graph to drain, and cancel nodes failed to stop in time
+    #[args(timeout = "10.0")]
+    fn shutdown(&mut self, py: Python, timeout: f64) -> PyResult<PyObject> {
+        RUNNING.lock().unwrap().remove(&self.id);
+        self.inps.clear();
+        let graph = self
+            .graph
+            .take()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        let report = py.allow_threads(|| {
+            flow_rs::rt::task::block_on(graph.shutdown(Duration::from_secs_f64(timeout)))
+        });
+        self.handle = None;
+        let dict = PyDict::new(py);
+        dict.set_item("drained", report.drained)?;
+        dict.set_item("cancelled", report.cancelled)?;
+        Ok(dict.into())
+    }
+
+    /// Render the graph with live queue depth and throughput on edges, `fmt` is "dot" or "svg"
+    #[args(fmt = "\"dot\"")]
+    fn snapshot(&self, fmt: &str) -> PyResult<String> {
+        let graph = self
+            .graph
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        match fmt {
+            "dot" => Ok(graph.snapshot_dot()),
+            "svg" => Ok(graph.snapshot_svg()),
+            _ => Err(PyRuntimeError::new_err(format!("unknown format {}", fmt))),
+        }
+    }
+
+    fn pause(&self, name: &str) -> PyResult<()> {
+        let graph = self
+            .graph
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        graph
+            .pause(name)
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))
+    }
+
+    fn resume(&self, name: &str) -> PyResult<()> {
+        let graph = self
+            .graph
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        graph
+            .resume(name)
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))
+    }
+
+    /// Live instances of dynamic subgraphs, as dicts of key, topic, created (unix time in seconds), received and sent
+    fn dyn_instances(&self, py: Python) -> PyResult<Vec<PyObject>> {
+        let graph = self
+            .graph
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        let instances = graph
+            .dyn_instances()
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))?;
+        let mut list = vec![];
+        for instance in instances {
+            let created = instance
+                .created
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            let dict = PyDict::new(py);
+            dict.set_item("key", instance.key)?;
+            dict.set_item("topic", instance.topic)?;
+            dict.set_item("created", created.as_secs_f64())?;
+            dict.set_item("received", instance.received)?;
+            dict.set_item("sent", instance.sent)?;
+            list.push(dict.into());
+        }
+        Ok(list)
+    }
+
+    fn kill_dyn(&self, topic: &str, key: u64) -> PyResult<usize> {
+        let graph = self
+            .graph
+            .as_ref()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        graph
+            .kill_dyn(topic, key)
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))
+    }
+
+    #[args(dynamic_str = "None")]
+    fn reload(
+        &mut self,
+        py: Python,
+        config_str: String,
+        dynamic_str: Option<String>,
+    ) -> PyResult<PyObject> {
+        let graph = self
+            .graph
+            .as_mut()
+            .ok_or_else(|| PyRuntimeError::new_err("graph is closed"))?;
+        let report = graph
+            .reload(&config_str, &dynamic_str.unwrap_or_default())
+            .map_err(|e| PyRuntimeError::new_err(format!("{:#}", e)))?;
+        let dict = PyDict::new(py);
+        dict.set_item("restarted", report.restarted)?;
+        dict.set_item("updated_graphs", report.updated_graphs)?;
+        Ok(dict.into())
+    }
+
     #[new]
     #[args(
         config_path = "None",
@@ -82,7 +242,8 @@ impl Graph {
         dynamic_str = "None",
         plugin_path = "None",
         module_path = "None",
-        dump = "false"
+        dump = "false",
+        shutdown_timeout = "10.0"
     )]
     fn new(
         mut config_path: Option<PathBuf>,
@@ -92,11 +253,12 @@ impl Graph {
         plugin_path: Option<PathBuf>,
         module_path: Option<PathBuf>,
         dump: bool,
+        shutdown_timeout: f64,
     ) -> PyResult<Graph> {
         ONCE_INIT.call_once(|| {
             // workaround for https://github.com/rust-lang/rust/issues/47384
             flow_plugins::export();
-            ctrlc::set_handler(|| unsafe { libc::_exit(0) }).expect("Error setting Ctrl-C handler");
+            ctrlc::set_handler(on_terminate).expect("Error setting Ctrl-C handler");
         });
 
         // load graph
@@ -172,8 +334,17 @@ impl Graph {
 
         // run graph
         let handle = graph.start();
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        RUNNING.lock().unwrap().insert(
+            id,
+            (
+                graph.shutdown_handle(),
+                Duration::from_secs_f64(shutdown_timeout),
+            ),
+        );
 
         Ok(Graph {
+            id,
             graph: Some(graph),
             handle: Some(handle),
             inps,
@@ -0,0 +1,74 @@
+/**
+ * \file flow-rs/src/channel/expiry.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::{ChannelStorage, Sender};
+use crate::envelope::{DummyEnvelope, EnvelopeInfo, SealedEnvelope};
+use std::time::Duration;
+
+/// What receivers do with expired envelopes
+#[derive(Clone)]
+pub enum ExpiredAction {
+    /// Drop them
+    Drop,
+    /// Send them into another channel
+    Divert(ChannelStorage),
+}
+
+/// Expiry options of a channel. An envelope is expired if its deadline is passed, or it is older than `ttl`
+#[derive(Clone)]
+pub struct Expiry {
+    pub ttl: Option<Duration>,
+    pub action: ExpiredAction,
+}
+
+impl Expiry {
+    pub fn is_expired(&self, info: &EnvelopeInfo) -> bool {
+        info.is_expired() || matches!((self.ttl, info.age()), (Some(ttl), Some(age)) if age > ttl)
+    }
+}
+
+/// The expiry options owned by a receiver, with its own sender of the diverted channel
+#[derive(Clone)]
+pub(super) struct ExpiryHandler {
+    expiry: Expiry,
+    divert: Option<Sender>,
+}
+
+impl ExpiryHandler {
+    pub fn new(expiry: &Expiry) -> ExpiryHandler {
+        let divert = match &expiry.action {
+            ExpiredAction::Drop => None,
+            ExpiredAction::Divert(storage) => Some(storage.sender()),
+        };
+        ExpiryHandler {
+            expiry: expiry.clone(),
+            divert,
+        }
+    }
+
+    pub fn is_expired(&self, envelope: &SealedEnvelope) -> bool {
+        self.expiry.is_expired(envelope.info())
+    }
+
+    /// Divert the expired envelope without waiting, so a full diverted channel never blocks the receiver, and the
+    /// envelope is counted as dropped by the diverted channel instead
+    pub fn handle(&self, envelope: SealedEnvelope) {
+        if let Some(divert) = &self.divert {
+            divert.try_send_any(envelope);
+        }
+    }
+
+    /// Pass the flush event to the diverted channel, as the sender is counted by it
+    pub async fn flush(&self) {
+        if let Some(divert) = &self.divert {
+            divert.send_any(DummyEnvelope {}.seal()).await.ok();
+        }
+    }
+}
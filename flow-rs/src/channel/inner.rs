@@ -36,13 +36,15 @@ use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::usize;
 
+use super::priority::{PriorityFn, PriorityQueue, Queue};
+use crate::config::presentation::OverflowPolicy;
 use concurrent_queue::{ConcurrentQueue, PopError, PushError};
 use event_listener::{Event, EventListener};
 use futures_core::stream::Stream;
 
 pub struct Channel<T> {
     /// Inner message queue.
-    pub(super) queue: ConcurrentQueue<T>,
+    pub(super) queue: Queue<T>,
 
     /// Send operations waiting while the channel is full.
     send_ops: Event,
@@ -58,6 +60,12 @@ pub struct Channel<T> {
 
     /// The number of currently active `Receivers`s.
     pub(super) receiver_count: AtomicUsize,
+
+    /// The number of holds keeping the channel open while there are no senders or receivers.
+    pub(super) hold_count: AtomicUsize,
+
+    /// The number of send operations finding the channel full, used by `OverflowPolicy::Sample`.
+    overflow_count: AtomicUsize,
 }
 
 impl<T> Channel<T> {
@@ -84,23 +92,56 @@ pub fn bounded<T>(cap: usize) -> Arc<Channel<T>> {
     assert!(cap > 0, "capacity cannot be zero");
 
     Arc::new(Channel {
-        queue: ConcurrentQueue::bounded(cap),
+        queue: Queue::Fifo(ConcurrentQueue::bounded(cap)),
+        send_ops: Event::new(),
+        recv_ops: Event::new(),
+        stream_ops: Event::new(),
+        sender_count: AtomicUsize::new(0),
+        receiver_count: AtomicUsize::new(0),
+        hold_count: AtomicUsize::new(0),
+        overflow_count: AtomicUsize::new(0),
+    })
+}
+
+pub fn bounded_priority<T>(cap: usize, priority: PriorityFn<T>) -> Arc<Channel<T>> {
+    assert!(cap > 0, "capacity cannot be zero");
+
+    Arc::new(Channel {
+        queue: Queue::Priority(PriorityQueue::bounded(cap, priority)),
+        send_ops: Event::new(),
+        recv_ops: Event::new(),
+        stream_ops: Event::new(),
+        sender_count: AtomicUsize::new(0),
+        receiver_count: AtomicUsize::new(0),
+        hold_count: AtomicUsize::new(0),
+        overflow_count: AtomicUsize::new(0),
+    })
+}
+
+/// An empty channel with the same capacity and order as `channel`
+pub fn like<T>(channel: &Channel<T>) -> Arc<Channel<T>> {
+    Arc::new(Channel {
+        queue: channel.queue.empty_like(),
         send_ops: Event::new(),
         recv_ops: Event::new(),
         stream_ops: Event::new(),
         sender_count: AtomicUsize::new(0),
         receiver_count: AtomicUsize::new(0),
+        hold_count: AtomicUsize::new(0),
+        overflow_count: AtomicUsize::new(0),
     })
 }
 
 pub fn unbounded<T>() -> Arc<Channel<T>> {
     Arc::new(Channel {
-        queue: ConcurrentQueue::unbounded(),
+        queue: Queue::Fifo(ConcurrentQueue::unbounded()),
         send_ops: Event::new(),
         recv_ops: Event::new(),
         stream_ops: Event::new(),
         sender_count: AtomicUsize::new(0),
         receiver_count: AtomicUsize::new(0),
+        hold_count: AtomicUsize::new(0),
+        overflow_count: AtomicUsize::new(0),
     })
 }
 
@@ -168,6 +209,53 @@ impl<T> Sender<T> {
         }
     }
 
+    /// Sends a message, and handles the full channel by `policy`.
+    ///
+    /// Queued messages are only dropped if `drop_queued` is true, otherwise the message itself is dropped. The caller
+    /// must make sure that no message which must be kept is queued during the call if `drop_queued` is true.
+    pub async fn send_with(
+        &self,
+        msg: T,
+        policy: OverflowPolicy,
+        drop_queued: bool,
+    ) -> Result<Dropped, SendError<T>> {
+        let mut msg = match self.try_send(msg) {
+            Ok(()) => return Ok(Dropped::default()),
+            Err(TrySendError::Closed(msg)) => return Err(SendError(msg)),
+            Err(TrySendError::Full(msg)) => msg,
+        };
+        let dropped_self = Dropped {
+            queued: 0,
+            sending: true,
+        };
+
+        match policy {
+            OverflowPolicy::Block => self.send(msg).await.map(|_| Dropped::default()),
+            OverflowPolicy::DropNewest => Ok(dropped_self),
+            OverflowPolicy::DropOldest if !drop_queued => Ok(dropped_self),
+            OverflowPolicy::DropOldest => {
+                let mut dropped = Dropped::default();
+                loop {
+                    if self.channel.queue.pop_lowest().is_ok() {
+                        dropped.queued += 1;
+                    }
+                    match self.try_send(msg) {
+                        Ok(()) => return Ok(dropped),
+                        Err(TrySendError::Closed(msg)) => return Err(SendError(msg)),
+                        Err(TrySendError::Full(m)) => msg = m,
+                    }
+                }
+            }
+            OverflowPolicy::Sample(n) => {
+                if self.channel.overflow_count.fetch_add(1, Ordering::Relaxed) % n == 0 {
+                    self.send(msg).await.map(|_| Dropped::default())
+                } else {
+                    Ok(dropped_self)
+                }
+            }
+        }
+    }
+
     pub fn close(&self) -> bool {
         self.channel.close()
     }
@@ -204,7 +292,9 @@ impl<T> Sender<T> {
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
         // Decrement the sender count and close the channel if it drops down to zero.
-        if self.channel.sender_count.fetch_sub(1, Ordering::AcqRel) == 1 {
+        if self.channel.sender_count.fetch_sub(1, Ordering::AcqRel) == 1
+            && self.channel.hold_count.load(Ordering::Acquire) == 0
+        {
             self.channel.close();
             self.close_ops.notify(usize::MAX);
         }
@@ -335,7 +425,9 @@ impl<T> Receiver<T> {
 impl<T> Drop for Receiver<T> {
     fn drop(&mut self) {
         // Decrement the receiver count and close the channel if it drops down to zero.
-        if self.channel.receiver_count.fetch_sub(1, Ordering::AcqRel) == 1 {
+        if self.channel.receiver_count.fetch_sub(1, Ordering::AcqRel) == 1
+            && self.channel.hold_count.load(Ordering::Acquire) == 0
+        {
             self.channel.close();
             self.close_ops.notify(usize::MAX);
         }
@@ -408,6 +500,15 @@ impl<T> Stream for Receiver<T> {
     }
 }
 
+/// Messages dropped by [`Sender::send_with()`].
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Dropped {
+    /// The number of queued messages dropped to make room.
+    pub queued: usize,
+    /// Whether the message being sent is dropped.
+    pub sending: bool,
+}
+
 /// An error returned from [`Sender::send()`].
 ///
 /// Received because the channel is closed.
@@ -9,9 +9,13 @@
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
 mod error;
+mod expiry;
 mod inner;
+mod observer;
+mod priority;
 mod receiver;
 mod sender;
+mod stats;
 mod storage;
 
 pub trait ChannelBase {
@@ -20,14 +24,17 @@ pub trait ChannelBase {
 }
 
 pub use error::*;
+pub use expiry::{ExpiredAction, Expiry};
+pub use observer::Observer;
 pub use receiver::*;
 pub use sender::*;
+pub use stats::*;
 pub use storage::*;
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::prelude::DummyEnvelope;
+    use crate::prelude::{AnyEnvelope, DummyEnvelope, Envelope};
     use crate::rt;
 
     #[rt::test]
@@ -80,4 +87,215 @@ mod test {
         drop(s);
         chan.wait_tx_closed().await;
     }
+
+    #[rt::test]
+    async fn test_hold() {
+        let chan = ChannelStorage::unbound();
+        let hold = chan.hold();
+        let s = chan.sender();
+        let r = chan.receiver();
+        drop(s);
+        drop(r);
+        assert!(!chan.is_closed());
+        drop(hold);
+        assert!(chan.is_closed());
+        chan.wait_tx_closed().await;
+        chan.wait_rx_closed().await;
+    }
+
+    #[rt::test]
+    async fn test_stats() {
+        let chan = ChannelStorage::bound(1);
+        let s = chan.sender();
+        let r = chan.receiver();
+        s.send(Envelope::new(0)).await.ok();
+        let blocked = rt::task::spawn(async move {
+            s.send(Envelope::new(1)).await.ok();
+        });
+        rt::task::sleep(std::time::Duration::from_millis(10)).await;
+        assert_eq!(chan.stats().queued(), 1);
+        // the sender still waiting is counted
+        assert!(chan.stats().blocked() >= std::time::Duration::from_millis(10));
+        assert!(r.recv::<i32>().await.is_ok());
+        blocked.await;
+        assert!(r.recv::<i32>().await.is_ok());
+        assert_eq!(chan.stats().sent(), 2);
+        assert_eq!(chan.stats().received(), 2);
+        assert_eq!(chan.stats().queued(), 0);
+        assert!(chan.stats().blocked() > std::time::Duration::from_millis(0));
+    }
+
+    #[rt::test]
+    async fn test_expiry() {
+        let expired = ChannelStorage::unbound();
+        let chan = ChannelStorage::unbound().with_expiry(Expiry {
+            ttl: Some(std::time::Duration::from_millis(20)),
+            action: ExpiredAction::Divert(expired.clone()),
+        });
+        let s = chan.sender();
+        let r = chan.receiver();
+        let r_expired = expired.receiver();
+
+        let mut envelope = Envelope::new(0);
+        envelope
+            .info_mut()
+            .set_timeout(std::time::Duration::from_millis(0));
+        s.send(envelope).await.ok();
+        s.send(Envelope::new(1)).await.ok();
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 1);
+        assert_eq!(*r_expired.recv::<i32>().await.unwrap().get_ref(), 0);
+
+        s.send(Envelope::new(2)).await.ok();
+        rt::task::sleep(std::time::Duration::from_millis(30)).await;
+        s.send(Envelope::new(3)).await.ok();
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 3);
+        assert_eq!(*r_expired.recv::<i32>().await.unwrap().get_ref(), 2);
+        assert_eq!(chan.stats().expired(), 2);
+
+        // flush events are passed to the diverted channel
+        s.send_any(DummyEnvelope {}.seal()).await.ok();
+        assert!(r.recv_any().await.is_err());
+        assert!(r_expired.recv_any().await.is_err());
+        assert!(!r_expired.is_closed());
+    }
+
+    #[rt::test]
+    async fn test_expiry_full() {
+        let expired = ChannelStorage::bound(1);
+        let chan = ChannelStorage::unbound().with_expiry(Expiry {
+            ttl: None,
+            action: ExpiredAction::Divert(expired.clone()),
+        });
+        let s = chan.sender();
+        let r = chan.receiver();
+        let r_expired = expired.receiver();
+
+        for i in 0..3 {
+            let mut envelope = Envelope::new(i);
+            envelope
+                .info_mut()
+                .set_timeout(std::time::Duration::from_millis(0));
+            s.send(envelope).await.ok();
+        }
+        s.send(Envelope::new(3)).await.ok();
+        // the full diverted channel never blocks the receiver
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 3);
+        assert_eq!(chan.stats().expired(), 3);
+        assert_eq!(expired.stats().dropped(), 2);
+        assert_eq!(*r_expired.recv::<i32>().await.unwrap().get_ref(), 0);
+    }
+
+    #[rt::test]
+    async fn test_policy() {
+        use crate::config::presentation::OverflowPolicy;
+
+        let chan = ChannelStorage::bound(2).with_policy(OverflowPolicy::DropNewest);
+        let s = chan.sender();
+        let r = chan.receiver();
+        for i in 0..4 {
+            s.send(Envelope::new(i)).await.ok();
+        }
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 0);
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 1);
+        assert_eq!(chan.stats().dropped(), 2);
+        assert_eq!(chan.stats().queued(), 0);
+        // dropped envelopes are not counted as sent
+        assert_eq!(chan.swap_tx_counter(), 2);
+
+        let chan = ChannelStorage::bound(2).with_policy(OverflowPolicy::DropOldest);
+        let s = chan.sender();
+        let r = chan.receiver();
+        for i in 0..4 {
+            s.send(Envelope::new(i)).await.ok();
+        }
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 2);
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 3);
+        assert_eq!(chan.stats().dropped(), 2);
+        assert_eq!(chan.stats().queued(), 0);
+
+        // flush events are never dropped
+        s.send(Envelope::new(4)).await.ok();
+        s.send_any(DummyEnvelope {}.seal()).await.ok();
+        s.send(Envelope::new(5)).await.ok();
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 4);
+        assert!(r.recv_any().await.is_err());
+        assert!(r.is_empty());
+
+        let chan = ChannelStorage::bound(1).with_policy(OverflowPolicy::Sample(2));
+        let s1 = chan.sender();
+        let s2 = chan.sender();
+        let r = chan.receiver();
+        s1.send(Envelope::new(0)).await.ok();
+        let blocked = rt::task::spawn(async move {
+            s1.send(Envelope::new(1)).await.ok();
+        });
+        rt::task::sleep(std::time::Duration::from_millis(10)).await;
+        s2.send(Envelope::new(2)).await.ok();
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 0);
+        blocked.await;
+        assert_eq!(*r.recv::<i32>().await.unwrap().get_ref(), 1);
+        assert_eq!(chan.stats().dropped(), 1);
+    }
+
+    #[rt::test]
+    async fn test_priority_drop_oldest() {
+        use crate::config::presentation::OverflowPolicy;
+
+        let chan = ChannelStorage::bound_priority(2).with_policy(OverflowPolicy::DropOldest);
+        let s = chan.sender();
+        let r = chan.receiver();
+        for (i, priority) in [1, 0, 1].iter().enumerate() {
+            let mut envelope = Envelope::new(i);
+            envelope.info_mut().priority = *priority;
+            s.send(envelope).await.ok();
+        }
+        // the envelope with the lowest priority is dropped instead of the oldest one
+        assert_eq!(*r.recv::<usize>().await.unwrap().get_ref(), 0);
+        assert_eq!(*r.recv::<usize>().await.unwrap().get_ref(), 2);
+        assert_eq!(chan.stats().dropped(), 1);
+    }
+
+    #[rt::test]
+    async fn test_replica() {
+        use crate::config::presentation::OverflowPolicy;
+
+        let chan = ChannelStorage::bound_priority(2).with_policy(OverflowPolicy::DropNewest);
+        let replica = chan.replica();
+        assert_eq!(replica.capacity(), Some(2));
+        let s = replica.sender();
+        let r = replica.receiver();
+        for (i, priority) in [0, 1, 2].iter().enumerate() {
+            let mut envelope = Envelope::new(i);
+            envelope.info_mut().priority = *priority;
+            s.send(envelope).await.ok();
+        }
+        assert_eq!(*r.recv::<usize>().await.unwrap().get_ref(), 1);
+        assert_eq!(*r.recv::<usize>().await.unwrap().get_ref(), 0);
+        // the statistics are shared with the original channel
+        assert_eq!(chan.stats().dropped(), 1);
+        assert_eq!(chan.stats().received(), 2);
+        assert!(chan.is_empty());
+    }
+
+    #[rt::test]
+    async fn test_priority() {
+        let chan = ChannelStorage::bound_priority(8);
+        let s = chan.sender();
+        let r = chan.receiver();
+        for (i, priority) in [0, 1, 0, 2].iter().enumerate() {
+            let mut envelope = Envelope::new(i);
+            envelope.info_mut().priority = *priority;
+            s.send(envelope).await.ok();
+        }
+        s.send_any(DummyEnvelope {}.seal()).await.ok();
+        let mut envelope = Envelope::new(4);
+        envelope.info_mut().priority = 3;
+        s.send(envelope).await.ok();
+
+        for expected in [3, 1, 0, 2] {
+            assert_eq!(*r.recv::<usize>().await.unwrap().get_ref(), expected);
+        }
+        assert!(r.recv_any().await.is_err());
+        assert_eq!(*r.recv::<usize>().await.unwrap().get_ref(), 4);
+    }
 }
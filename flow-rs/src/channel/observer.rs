@@ -0,0 +1,21 @@
+/**
+ * \file flow-rs/src/channel/observer.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::envelope::SealedEnvelope;
+
+/// Observe the envelopes going through a port, e.g. `record::Recorder`
+pub trait Observer: Send + Sync {
+    /// Called before an envelope is sent, or after an envelope is received
+    fn on_envelope(&self, envelope: &SealedEnvelope);
+    /// Called when a flush event is sent or received
+    fn on_flush(&self);
+    /// Called when the port closes the channel, or finds the channel closed
+    fn on_close(&self);
+}
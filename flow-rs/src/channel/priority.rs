@@ -0,0 +1,277 @@
+/**
+ * \file flow-rs/src/channel/priority.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use concurrent_queue::{ConcurrentQueue, PopError, PushError};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Mutex;
+
+/// The priority of a message, or `None` if the message is a barrier, e.g. a flush event
+pub type PriorityFn<T> = fn(&T) -> Option<i32>;
+
+/// The queue of a channel, which is a fifo queue or a priority queue
+pub enum Queue<T> {
+    Fifo(ConcurrentQueue<T>),
+    Priority(PriorityQueue<T>),
+}
+
+impl<T> Queue<T> {
+    pub fn push(&self, value: T) -> Result<(), PushError<T>> {
+        match self {
+            Queue::Fifo(queue) => queue.push(value),
+            Queue::Priority(queue) => queue.push(value),
+        }
+    }
+
+    pub fn pop(&self) -> Result<T, PopError> {
+        match self {
+            Queue::Fifo(queue) => queue.pop(),
+            Queue::Priority(queue) => queue.pop(),
+        }
+    }
+
+    /// Pop the message to drop when the queue is full, which is the oldest one with the lowest priority
+    pub fn pop_lowest(&self) -> Result<T, PopError> {
+        match self {
+            Queue::Fifo(queue) => queue.pop(),
+            Queue::Priority(queue) => queue.pop_lowest(),
+        }
+    }
+
+    pub fn close(&self) -> bool {
+        match self {
+            Queue::Fifo(queue) => queue.close(),
+            Queue::Priority(queue) => queue.close(),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        match self {
+            Queue::Fifo(queue) => queue.is_closed(),
+            Queue::Priority(queue) => queue.inner.lock().unwrap().closed,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        match self {
+            Queue::Fifo(queue) => queue.is_full(),
+            Queue::Priority(queue) => queue.len() >= queue.cap,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Queue::Fifo(queue) => queue.len(),
+            Queue::Priority(queue) => queue.len(),
+        }
+    }
+
+    pub fn capacity(&self) -> Option<usize> {
+        match self {
+            Queue::Fifo(queue) => queue.capacity(),
+            Queue::Priority(queue) => Some(queue.cap),
+        }
+    }
+
+    /// An empty queue with the same capacity and order
+    pub fn empty_like(&self) -> Queue<T> {
+        match self {
+            Queue::Fifo(queue) => match queue.capacity() {
+                Some(cap) => Queue::Fifo(ConcurrentQueue::bounded(cap)),
+                None => Queue::Fifo(ConcurrentQueue::unbounded()),
+            },
+            Queue::Priority(queue) => {
+                Queue::Priority(PriorityQueue::bounded(queue.cap, queue.priority))
+            }
+        }
+    }
+}
+
+struct Entry<T> {
+    // messages are only reordered between two barriers
+    epoch: u64,
+    priority: Option<i32>,
+    seq: u64,
+    value: T,
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Entry<T> {}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for Entry<T> {
+    // the greatest entry is popped first
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .epoch
+            .cmp(&self.epoch)
+            .then(self.priority.cmp(&other.priority))
+            .then(other.seq.cmp(&self.seq))
+    }
+}
+
+struct Inner<T> {
+    heap: BinaryHeap<Entry<T>>,
+    epoch: u64,
+    seq: u64,
+    closed: bool,
+}
+
+/// A bounded queue, where messages with higher priority are popped first, and messages with the same
+/// priority are popped in order. Barriers are never overtaken, and never overtake others.
+pub struct PriorityQueue<T> {
+    inner: Mutex<Inner<T>>,
+    cap: usize,
+    priority: PriorityFn<T>,
+}
+
+impl<T> PriorityQueue<T> {
+    pub fn bounded(cap: usize, priority: PriorityFn<T>) -> PriorityQueue<T> {
+        PriorityQueue {
+            inner: Mutex::new(Inner {
+                heap: BinaryHeap::with_capacity(cap),
+                epoch: 0,
+                seq: 0,
+                closed: false,
+            }),
+            cap,
+            priority,
+        }
+    }
+
+    fn push(&self, value: T) -> Result<(), PushError<T>> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.closed {
+            return Err(PushError::Closed(value));
+        }
+        if inner.heap.len() >= self.cap {
+            return Err(PushError::Full(value));
+        }
+        let priority = (self.priority)(&value);
+        let entry = Entry {
+            epoch: inner.epoch,
+            priority,
+            seq: inner.seq,
+            value,
+        };
+        inner.seq += 1;
+        if priority.is_none() {
+            inner.epoch += 1;
+        }
+        inner.heap.push(entry);
+        Ok(())
+    }
+
+    fn pop(&self) -> Result<T, PopError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.heap.pop() {
+            Some(entry) => Ok(entry.value),
+            None if inner.closed => Err(PopError::Closed),
+            None => Err(PopError::Empty),
+        }
+    }
+
+    // barriers are never dropped
+    fn pop_lowest(&self) -> Result<T, PopError> {
+        let mut inner = self.inner.lock().unwrap();
+        let mut entries = std::mem::take(&mut inner.heap).into_vec();
+        let lowest = entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.priority.is_some())
+            .min_by_key(|(_, entry)| (entry.priority, entry.seq))
+            .map(|(i, _)| i);
+        let ret = lowest.map(|i| entries.swap_remove(i).value);
+        inner.heap = entries.into();
+        match ret {
+            Some(value) => Ok(value),
+            None if inner.closed => Err(PopError::Closed),
+            None => Err(PopError::Empty),
+        }
+    }
+
+    fn close(&self) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        !std::mem::replace(&mut inner.closed, true)
+    }
+
+    fn len(&self) -> usize {
+        self.inner.lock().unwrap().heap.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_priority() {
+        let queue = Queue::Priority(PriorityQueue::bounded(8, |x: &i32| {
+            if *x < 0 {
+                None
+            } else {
+                Some(x / 10)
+            }
+        }));
+        for x in [1, 12, 2, 21, -1, 30, 3] {
+            queue.push(x).ok();
+        }
+        let mut popped = vec![];
+        while let Ok(x) = queue.pop() {
+            popped.push(x);
+        }
+        assert_eq!(popped, vec![21, 12, 1, 2, -1, 30, 3]);
+
+        for x in 0..8 {
+            queue.push(x).ok();
+        }
+        assert!(queue.is_full());
+        assert!(matches!(queue.push(8), Err(PushError::Full(8))));
+        assert!(queue.close());
+        assert!(!queue.close());
+        assert!(matches!(queue.push(8), Err(PushError::Closed(8))));
+        assert!(matches!(queue.pop(), Ok(0)));
+    }
+
+    #[test]
+    fn test_pop_lowest() {
+        let queue = Queue::Priority(PriorityQueue::bounded(8, |x: &i32| {
+            if *x < 0 {
+                None
+            } else {
+                Some(x / 10)
+            }
+        }));
+        for x in [12, 1, -1, 2, 21] {
+            queue.push(x).ok();
+        }
+        assert!(matches!(queue.pop_lowest(), Ok(1)));
+        assert!(matches!(queue.pop_lowest(), Ok(2)));
+        assert!(matches!(queue.pop_lowest(), Ok(12)));
+        assert!(matches!(queue.pop_lowest(), Ok(21)));
+        // barriers are never dropped
+        assert!(matches!(queue.pop_lowest(), Err(PopError::Empty)));
+        assert!(matches!(queue.pop(), Ok(-1)));
+    }
+}
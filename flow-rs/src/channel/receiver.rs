@@ -14,9 +14,10 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::expiry::ExpiryHandler;
 use super::inner::Receiver as RecvImpl;
-use super::{BatchRecvError, RecvError};
-use crate::envelope::{DummyEnvelope, Envelope, SealedEnvelope};
+use super::{BatchRecvError, ChannelStats, Observer, RecvError};
+use crate::envelope::{AnyEnvelope, DummyEnvelope, Envelope, SealedEnvelope};
 
 use super::ChannelBase;
 
@@ -27,6 +28,10 @@ pub struct Receiver {
     g_epoch: Arc<AtomicUsize>,
     is_closed: AtomicBool,
     counter: Arc<AtomicUsize>,
+    stats: Arc<ChannelStats>,
+    expiry: Option<ExpiryHandler>,
+    flushes: Arc<AtomicUsize>,
+    observer: Option<Arc<dyn Observer>>,
 }
 
 impl Clone for Receiver {
@@ -38,6 +43,10 @@ impl Clone for Receiver {
             g_epoch: self.g_epoch.clone(),
             is_closed: AtomicBool::new(self.is_closed.load(Ordering::Relaxed)),
             counter: self.counter.clone(),
+            stats: self.stats.clone(),
+            expiry: self.expiry.clone(),
+            flushes: self.flushes.clone(),
+            observer: self.observer.clone(),
         }
     }
 }
@@ -60,6 +69,7 @@ impl Receiver {
         imp: RecvImpl<SealedEnvelope>,
         epoch: Arc<AtomicUsize>,
         counter: Arc<AtomicUsize>,
+        stats: Arc<ChannelStats>,
     ) -> Self {
         let m_epoch = epoch.load(Ordering::Relaxed);
         Receiver {
@@ -68,8 +78,26 @@ impl Receiver {
             g_epoch: epoch,
             is_closed: AtomicBool::new(false),
             counter,
+            stats,
+            expiry: None,
+            flushes: Default::default(),
+            observer: None,
         }
     }
+    pub(super) fn with_expiry(mut self, expiry: Option<ExpiryHandler>) -> Self {
+        self.expiry = expiry;
+        self
+    }
+    /// `flushes` is the number of flush events in the channel, see `Sender::with_policy`
+    pub(super) fn with_flushes(mut self, flushes: Arc<AtomicUsize>) -> Self {
+        self.flushes = flushes;
+        self
+    }
+    /// Observe envelopes received by this receiver
+    pub fn with_observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
     pub fn len(&self) -> usize {
         if let Some(imp) = self.imp.as_ref() {
             imp.len()
@@ -112,25 +140,59 @@ impl Receiver {
         })
     }
     /// Receives a any envelope from the channel, see document of `recv` for more detail
+    ///
+    /// If the channel has expiry options, expired envelopes are dropped or diverted instead of being returned
     pub async fn recv_any(&self) -> Result<SealedEnvelope, RecvError> {
-        let m_epoch = self.m_epoch.load(Ordering::Relaxed);
-        let g_epoch = self.g_epoch.load(Ordering::Relaxed);
-        if m_epoch < g_epoch {
-            self.m_epoch.fetch_add(1, Ordering::Relaxed);
-            return Err(RecvError {});
+        let ret = self.recv_any_impl().await;
+        if let Some(observer) = &self.observer {
+            match &ret {
+                Ok(envelope) => observer.on_envelope(envelope),
+                Err(_) if self.is_closed() => observer.on_close(),
+                Err(_) => observer.on_flush(),
+            }
         }
-        let imp = self.imp.as_ref().ok_or(RecvError {})?;
-        let envelope = imp.recv().await.map_err(|e| {
-            self.is_closed.store(true, Ordering::Relaxed);
-            e
-        })?;
-        if envelope.is::<DummyEnvelope>() {
-            self.m_epoch.fetch_add(1, Ordering::Relaxed);
-            self.g_epoch.fetch_add(1, Ordering::Relaxed);
-            Err(RecvError {})
-        } else {
+        ret
+    }
+
+    async fn recv_any_impl(&self) -> Result<SealedEnvelope, RecvError> {
+        loop {
+            let m_epoch = self.m_epoch.load(Ordering::Relaxed);
+            let g_epoch = self.g_epoch.load(Ordering::Relaxed);
+            if m_epoch < g_epoch {
+                self.m_epoch.fetch_add(1, Ordering::Relaxed);
+                self.flush_expiry().await;
+                return Err(RecvError {});
+            }
+            let imp = self.imp.as_ref().ok_or(RecvError {})?;
+            let envelope = imp.recv().await.map_err(|e| {
+                self.is_closed.store(true, Ordering::Relaxed);
+                e
+            })?;
+            if envelope.is::<DummyEnvelope>() {
+                self.flushes.fetch_sub(1, Ordering::Relaxed);
+                self.m_epoch.fetch_add(1, Ordering::Relaxed);
+                self.g_epoch.fetch_add(1, Ordering::Relaxed);
+                self.flush_expiry().await;
+                return Err(RecvError {});
+            }
             self.counter.fetch_add(1, Ordering::Relaxed);
-            Ok(envelope)
+            self.stats.on_recv();
+            match &self.expiry {
+                Some(expiry) if expiry.is_expired(&envelope) => {
+                    self.stats.on_expired();
+                    expiry.handle(envelope);
+                }
+                _ => {
+                    crate::trace::on_recv(envelope.info());
+                    return Ok(envelope);
+                }
+            }
+        }
+    }
+
+    async fn flush_expiry(&self) {
+        if let Some(expiry) = &self.expiry {
+            expiry.flush().await;
         }
     }
 
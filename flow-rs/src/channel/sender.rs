@@ -13,8 +13,9 @@ use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use super::inner::Sender as SendImpl;
-use super::SendError;
+use super::inner::{Dropped, Sender as SendImpl, TrySendError};
+use super::{ChannelStats, Observer, SendError};
+use crate::config::presentation::OverflowPolicy;
 use crate::envelope::{DummyEnvelope, Envelope, SealedEnvelope};
 
 use super::ChannelBase;
@@ -28,6 +29,10 @@ pub struct Sender {
     max_epoch: Arc<AtomicUsize>,
     record: SenderRecord,
     counter: Arc<AtomicUsize>,
+    stats: Arc<ChannelStats>,
+    policy: OverflowPolicy,
+    flushes: Arc<AtomicUsize>,
+    observer: Option<Arc<dyn Observer>>,
 }
 
 impl Clone for Sender {
@@ -40,6 +45,10 @@ impl Clone for Sender {
             max_epoch: self.max_epoch.clone(),
             record: self.record.clone(),
             counter: self.counter.clone(),
+            stats: self.stats.clone(),
+            policy: self.policy,
+            flushes: self.flushes.clone(),
+            observer: self.observer.clone(),
         }
     }
 }
@@ -63,6 +72,7 @@ impl Sender {
         max_epoch: Arc<AtomicUsize>,
         record: SenderRecord,
         counter: Arc<AtomicUsize>,
+        stats: Arc<ChannelStats>,
     ) -> Self {
         let epoch = max_epoch.load(Ordering::Relaxed);
         Sender {
@@ -71,8 +81,23 @@ impl Sender {
             max_epoch,
             record,
             counter,
+            stats,
+            policy: Default::default(),
+            flushes: Default::default(),
+            observer: None,
         }
     }
+    /// `flushes` is the number of flush events in the channel, which are never dropped
+    pub(super) fn with_policy(mut self, policy: OverflowPolicy, flushes: Arc<AtomicUsize>) -> Self {
+        self.policy = policy;
+        self.flushes = flushes;
+        self
+    }
+    /// Observe envelopes sent by this sender
+    pub fn with_observer(mut self, observer: Arc<dyn Observer>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
 
     #[doc(hidden)]
     pub fn empty_n(&self) -> usize {
@@ -92,6 +117,9 @@ impl Sender {
     /// The remaining envelopes can still be received.
     pub fn close(&self) {
         if let Some(imp) = self.imp.as_ref() {
+            if let Some(observer) = &self.observer {
+                observer.on_close();
+            }
             imp.close();
         }
     }
@@ -120,8 +148,15 @@ impl Sender {
         })
     }
     /// Sends a any envelope into the channel. see document of `send` for more detail
-    pub async fn send_any(&self, msg: SealedEnvelope) -> Result<(), SendError<SealedEnvelope>> {
+    pub async fn send_any(&self, mut msg: SealedEnvelope) -> Result<(), SendError<SealedEnvelope>> {
         if let Some(imp) = self.imp.as_ref() {
+            if let Some(observer) = &self.observer {
+                if msg.is::<DummyEnvelope>() {
+                    observer.on_flush();
+                } else {
+                    observer.on_envelope(&msg);
+                }
+            }
             if msg.is::<DummyEnvelope>() {
                 let mut record = self.record.lock().await;
                 let epoch = self.epoch.load(Ordering::Relaxed);
@@ -135,16 +170,62 @@ impl Sender {
 
                 if *count == 0 {
                     record.remove(&epoch);
-                    imp.send(msg).await
+                    self.flushes.fetch_add(1, Ordering::Relaxed);
+                    let ret = imp.send(msg).await;
+                    if ret.is_err() {
+                        self.flushes.fetch_sub(1, Ordering::Relaxed);
+                    }
+                    ret
                 } else {
                     Ok(())
                 }
             } else {
-                self.counter.fetch_add(1, Ordering::Relaxed);
-                imp.send(msg).await
+                crate::trace::on_send(&mut msg);
+                let ret = match imp.try_send(msg) {
+                    Ok(()) => Ok(Dropped::default()),
+                    Err(TrySendError::Closed(msg)) => Err(super::inner::SendError(msg)),
+                    Err(TrySendError::Full(msg)) => {
+                        // counted as blocked until the envelope is queued or dropped
+                        let _blocked = self.stats.on_blocked();
+                        // flush events are queued with the record locked, so none of them is queued between
+                        // checking `flushes` and dropping queued envelopes
+                        let _record = match self.policy {
+                            OverflowPolicy::DropOldest => Some(self.record.lock().await),
+                            _ => None,
+                        };
+                        let drop_queued = self.flushes.load(Ordering::Relaxed) == 0;
+                        imp.send_with(msg, self.policy, drop_queued).await
+                    }
+                };
+                ret.map(|dropped| {
+                    if !dropped.sending {
+                        self.counter.fetch_add(1, Ordering::Relaxed);
+                        self.stats.on_send();
+                    }
+                    self.stats.on_dropped(dropped);
+                })
             }
         } else {
             Ok(())
         }
     }
+    /// Sends a any envelope into the channel without waiting, the envelope is dropped if the channel is full or
+    /// closed. Flush events must be sent by `send_any`, as they are never dropped
+    pub(super) fn try_send_any(&self, mut msg: SealedEnvelope) {
+        if let Some(imp) = self.imp.as_ref() {
+            if let Some(observer) = &self.observer {
+                observer.on_envelope(&msg);
+            }
+            crate::trace::on_send(&mut msg);
+            if imp.try_send(msg).is_ok() {
+                self.counter.fetch_add(1, Ordering::Relaxed);
+                self.stats.on_send();
+            } else {
+                self.stats.on_dropped(Dropped {
+                    queued: 0,
+                    sending: true,
+                });
+            }
+        }
+    }
 }
@@ -0,0 +1,134 @@
+/**
+ * \file flow-rs/src/channel/stats.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::inner::Dropped;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+lazy_static::lazy_static! {
+    static ref EPOCH: Instant = Instant::now();
+}
+
+/// Monotonic statistics of a channel, which may be shared by the channels of the same connection
+#[derive(Default)]
+pub struct ChannelStats {
+    sent: AtomicU64,
+    received: AtomicU64,
+    queued: AtomicI64,
+    blocked: AtomicU64,
+    // the number of senders waiting on the full channel, and the sum of when they started waiting
+    blocking: AtomicU64,
+    blocking_since: AtomicU64,
+    expired: AtomicU64,
+    dropped: AtomicU64,
+    // nanoseconds since `EPOCH` plus one, zero if nothing is sent
+    last_sent: AtomicU64,
+}
+
+impl ChannelStats {
+    pub(super) fn on_send(&self) {
+        self.sent.fetch_add(1, Ordering::Relaxed);
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        let now = EPOCH.elapsed().as_nanos() as u64 + 1;
+        self.last_sent.fetch_max(now, Ordering::Relaxed);
+    }
+
+    pub(super) fn on_recv(&self) {
+        self.received.fetch_add(1, Ordering::Relaxed);
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Start waiting on the full channel, which is counted in `blocked` until the guard is dropped
+    pub(super) fn on_blocked(&self) -> BlockedGuard<'_> {
+        let since = EPOCH.elapsed().as_nanos() as u64;
+        self.blocking_since.fetch_add(since, Ordering::Relaxed);
+        self.blocking.fetch_add(1, Ordering::Relaxed);
+        BlockedGuard { stats: self, since }
+    }
+
+    pub(super) fn on_expired(&self) {
+        self.expired.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn on_dropped(&self, dropped: Dropped) {
+        let n = dropped.queued as u64 + dropped.sending as u64;
+        if n > 0 {
+            self.dropped.fetch_add(n, Ordering::Relaxed);
+            self.queued
+                .fetch_sub(dropped.queued as i64, Ordering::Relaxed);
+        }
+    }
+
+    /// Count an envelope which is dropped before it is sent, e.g. into a closed channel
+    pub(crate) fn on_discarded(&self) {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The number of envelopes sent into the channel
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// The number of envelopes received from the channel
+    pub fn received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    /// The number of envelopes waiting in the channel
+    pub fn queued(&self) -> u64 {
+        std::cmp::max(self.queued.load(Ordering::Relaxed), 0) as u64
+    }
+
+    /// The total time senders spent waiting on the full channel, including the ones still waiting
+    pub fn blocked(&self) -> Duration {
+        let now = EPOCH.elapsed().as_nanos() as u64;
+        let waiting = (self.blocking.load(Ordering::Relaxed) * now)
+            .saturating_sub(self.blocking_since.load(Ordering::Relaxed));
+        Duration::from_nanos(self.blocked.load(Ordering::Relaxed) + waiting)
+    }
+
+    /// The number of expired envelopes dropped or diverted by receivers
+    pub fn expired(&self) -> u64 {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    /// The number of envelopes dropped by the overflow policy of the full channel
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// When the last envelope is sent into the channel
+    pub fn last_sent(&self) -> Option<Instant> {
+        match self.last_sent.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(*EPOCH + Duration::from_nanos(nanos - 1)),
+        }
+    }
+}
+
+/// A sender waiting on the full channel
+pub(super) struct BlockedGuard<'a> {
+    stats: &'a ChannelStats,
+    since: u64,
+}
+
+impl Drop for BlockedGuard<'_> {
+    fn drop(&mut self) {
+        let now = EPOCH.elapsed().as_nanos() as u64;
+        let stats = self.stats;
+        stats
+            .blocked
+            .fetch_add(now.saturating_sub(self.since), Ordering::Relaxed);
+        stats.blocking.fetch_sub(1, Ordering::Relaxed);
+        stats
+            .blocking_since
+            .fetch_sub(self.since, Ordering::Relaxed);
+    }
+}
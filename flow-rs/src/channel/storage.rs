@@ -8,8 +8,10 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
-use super::{inner, ChannelBase, Receiver, Sender, SenderRecord};
-use crate::envelope::SealedEnvelope;
+use super::expiry::ExpiryHandler;
+use super::{inner, ChannelBase, ChannelStats, Expiry, Receiver, Sender, SenderRecord};
+use crate::config::presentation::OverflowPolicy;
+use crate::envelope::{AnyEnvelope, DummyEnvelope, SealedEnvelope};
 use crate::rt::sync::Mutex;
 use event_listener::Event;
 use std::collections::HashMap;
@@ -27,6 +29,10 @@ pub struct ChannelStorage {
     tx_counter: Arc<AtomicUsize>,
     rx_close_ops: Arc<Event>,
     tx_close_ops: Arc<Event>,
+    stats: Arc<ChannelStats>,
+    expiry: Option<Arc<Expiry>>,
+    policy: OverflowPolicy,
+    flushes: Arc<AtomicUsize>,
 }
 
 impl ChannelBase for ChannelStorage {
@@ -49,6 +55,24 @@ impl ChannelStorage {
             tx_counter: Arc::new(AtomicUsize::new(0)),
             rx_close_ops: Arc::new(Event::new()),
             tx_close_ops: Arc::new(Event::new()),
+            stats: Default::default(),
+            expiry: None,
+            policy: Default::default(),
+            flushes: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    /// A bounded channel, where envelopes with higher priority are received first.
+    /// Envelopes with the same priority are received in order, and flush events are never reordered.
+    pub fn bound_priority(cap: usize) -> ChannelStorage {
+        ChannelStorage {
+            storage: inner::bounded_priority(cap, |envelope: &SealedEnvelope| {
+                if envelope.is::<DummyEnvelope>() {
+                    None
+                } else {
+                    Some(envelope.info().priority)
+                }
+            }),
+            ..Self::bound(cap)
         }
     }
     pub fn unbound() -> ChannelStorage {
@@ -61,6 +85,21 @@ impl ChannelStorage {
             rx_close_ops: Arc::new(Event::new()),
             rx_counter: Arc::new(AtomicUsize::new(0)),
             tx_counter: Arc::new(AtomicUsize::new(0)),
+            stats: Default::default(),
+            expiry: None,
+            policy: Default::default(),
+            flushes: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+    /// A new channel with the same capacity, order, overflow policy and expiry, which shares the statistics
+    /// with this one. Envelopes relayed from this channel into the replica are counted once more.
+    pub fn replica(&self) -> ChannelStorage {
+        ChannelStorage {
+            storage: inner::like(&self.storage),
+            stats: self.stats.clone(),
+            expiry: self.expiry.clone(),
+            policy: self.policy,
+            ..Self::unbound()
         }
     }
     pub fn sender(&self) -> Sender {
@@ -77,7 +116,9 @@ impl ChannelStorage {
             self.sender_epoch.clone(),
             self.sender_record.clone(),
             self.tx_counter.clone(),
+            self.stats.clone(),
         )
+        .with_policy(self.policy, self.flushes.clone())
     }
 
     pub fn receiver(&self) -> Receiver {
@@ -95,15 +136,23 @@ impl ChannelStorage {
             },
             self.receiver_epoch.clone(),
             self.rx_counter.clone(),
+            self.stats.clone(),
         )
+        .with_expiry(self.expiry.as_deref().map(ExpiryHandler::new))
+        .with_flushes(self.flushes.clone())
     }
 
     pub fn len(&self) -> usize {
         self.storage.queue.len()
     }
 
+    /// The capacity of the channel, or `None` if it is unbounded
+    pub fn capacity(&self) -> Option<usize> {
+        self.storage.queue.capacity()
+    }
+
     pub fn is_almost_full(&self) -> bool {
-        match self.storage.queue.capacity() {
+        match self.capacity() {
             Some(capacity) => (0.9 * capacity as f64) <= self.len() as f64,
             None => false,
         }
@@ -117,6 +166,12 @@ impl ChannelStorage {
         self.storage.close();
     }
 
+    /// Keep the channel open even if all senders or receivers are dropped, until the hold is dropped.
+    pub(crate) fn hold(&self) -> ChannelHold {
+        self.storage.hold_count.fetch_add(1, Ordering::Relaxed);
+        ChannelHold(self.clone())
+    }
+
     pub fn sender_count(&self) -> usize {
         self.storage.sender_count.load(Ordering::Relaxed)
     }
@@ -125,6 +180,28 @@ impl ChannelStorage {
         self.storage.receiver_count.load(Ordering::Relaxed)
     }
 
+    /// Share the statistics with other channels, must be called before any port is created
+    pub fn with_stats(mut self, stats: Arc<ChannelStats>) -> ChannelStorage {
+        self.stats = stats;
+        self
+    }
+
+    /// Set expiry options of receivers, must be called before any receiver is created
+    pub fn with_expiry(mut self, expiry: Expiry) -> ChannelStorage {
+        self.expiry = Some(Arc::new(expiry));
+        self
+    }
+
+    /// Set what senders do when the channel is full, must be called before any sender is created
+    pub fn with_policy(mut self, policy: OverflowPolicy) -> ChannelStorage {
+        self.policy = policy;
+        self
+    }
+
+    pub fn stats(&self) -> &ChannelStats {
+        &self.stats
+    }
+
     pub fn swap_tx_counter(&self) -> usize {
         self.tx_counter.swap(0, Ordering::Relaxed)
     }
@@ -167,3 +244,21 @@ impl ChannelStorage {
         }
     }
 }
+
+pub(crate) struct ChannelHold(ChannelStorage);
+
+impl Drop for ChannelHold {
+    fn drop(&mut self) {
+        let storage = &self.0.storage;
+        if storage.hold_count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if storage.sender_count.load(Ordering::Acquire) == 0 {
+                storage.close();
+                self.0.tx_close_ops.notify(usize::MAX);
+            }
+            if storage.receiver_count.load(Ordering::Acquire) == 0 {
+                storage.close();
+                self.0.rx_close_ops.notify(usize::MAX);
+            }
+        }
+    }
+}
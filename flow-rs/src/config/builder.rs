@@ -0,0 +1,210 @@
+/**
+ * \file flow-rs/src/config/builder.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::presentation;
+use crate::graph::MainGraph;
+use anyhow::Result;
+use std::fmt;
+use toml::value::Table;
+
+/// A port `node:port` of a node in a graph, or `node:port:tag` of a dict port
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PortRef(String);
+
+impl PortRef {
+    pub fn new(node: &str, port: &str) -> PortRef {
+        PortRef(format!("{}:{}", node, port))
+    }
+
+    /// Select the channel `tag` of a dict port
+    pub fn tag(self, tag: u64) -> PortRef {
+        PortRef(format!("{}:{}", self.0, tag))
+    }
+}
+
+impl fmt::Display for PortRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for PortRef {
+    fn from(port: &str) -> Self {
+        PortRef(port.to_owned())
+    }
+}
+
+impl From<String> for PortRef {
+    fn from(port: String) -> Self {
+        PortRef(port)
+    }
+}
+
+impl From<&PortRef> for PortRef {
+    fn from(port: &PortRef) -> Self {
+        port.clone()
+    }
+}
+
+/// Ports of a node type, which is implemented by `#[derive(Node)]` for ports declared by `#[inputs]` and `#[outputs]`
+///
+/// # Examples
+///
+/// ```ignore
+/// let a = ScaleOpr::ports("a");
+/// GraphBuilder::new("main").node("a", "ScaleOpr", Table::new()).input("inp", a.inp, 4);
+/// ```
+pub trait NodePorts {
+    /// A struct with a `PortRef` field for each port
+    type Ports;
+    /// Ports of the node named `node`
+    fn ports(node: &str) -> Self::Ports;
+}
+
+fn conn<I, P>(cap: usize, ports: I) -> presentation::Connection
+where
+    I: IntoIterator<Item = P>,
+    P: Into<PortRef>,
+{
+    presentation::Connection {
+        cap,
+        ports: ports.into_iter().map(|port| port.into().0).collect(),
+        ttl_ms: None,
+        expired: None,
+        idle_timeout_ms: None,
+        max_instances: None,
+        admission: Default::default(),
+        pool_size: None,
+        policy: Default::default(),
+        priority: false,
+    }
+}
+
+/// Build a graph in rust instead of toml, the graph is checked when it is loaded by `Builder::build`
+#[derive(Clone, Debug)]
+pub struct GraphBuilder {
+    graph: presentation::Graph,
+}
+
+impl GraphBuilder {
+    pub fn new(name: &str) -> GraphBuilder {
+        GraphBuilder {
+            graph: presentation::Graph {
+                name: name.to_owned(),
+                resources: vec![],
+                nodes: vec![],
+                inputs: vec![],
+                outputs: vec![],
+                connections: vec![],
+            },
+        }
+    }
+
+    pub fn resource(mut self, name: &str, ty: &str, args: Table) -> GraphBuilder {
+        self.graph.resources.push(presentation::Entity {
+            name: name.to_owned(),
+            ty: ty.to_owned(),
+            args,
+        });
+        self
+    }
+
+    /// Declare a node of the registered node type or the graph `ty`
+    pub fn node(mut self, name: &str, ty: &str, args: Table) -> GraphBuilder {
+        self.graph.nodes.push(presentation::Node {
+            entity: presentation::Entity {
+                name: name.to_owned(),
+                ty: ty.to_owned(),
+                args,
+            },
+            res: vec![],
+            cloned: None,
+            balance: Default::default(),
+            restart: Default::default(),
+            max_retries: None,
+            backoff: None,
+        });
+        self
+    }
+
+    /// Declare an input of the graph, which is connected to the input port `to`
+    pub fn input(mut self, name: &str, to: impl Into<PortRef>, cap: usize) -> GraphBuilder {
+        self.graph.inputs.push(presentation::NamedConn {
+            name: name.to_owned(),
+            conn: conn(cap, [to.into()]),
+        });
+        self
+    }
+
+    /// Declare an output of the graph, which is connected to the output port `from`
+    pub fn output(mut self, name: &str, from: impl Into<PortRef>, cap: usize) -> GraphBuilder {
+        self.graph.outputs.push(presentation::NamedConn {
+            name: name.to_owned(),
+            conn: conn(cap, [from.into()]),
+        });
+        self
+    }
+
+    /// Connect the output port `from` to the input port `to`
+    pub fn connect(
+        mut self,
+        from: impl Into<PortRef>,
+        to: impl Into<PortRef>,
+        cap: usize,
+    ) -> GraphBuilder {
+        self.graph
+            .connections
+            .push(conn(cap, [from.into(), to.into()]));
+        self
+    }
+
+    /// Connect all `ports` with one connection, e.g. many outputs to an input
+    pub fn connect_all<I, P>(mut self, ports: I, cap: usize) -> GraphBuilder
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PortRef>,
+    {
+        self.graph.connections.push(conn(cap, ports));
+        self
+    }
+
+    /// Build the graph as the main graph, which is a shortcut of `Builder::default().graph(self).build()`
+    pub fn build(self) -> Result<MainGraph> {
+        crate::Builder::default().graph(self).build()
+    }
+
+    pub(crate) fn into_config(self) -> presentation::Graph {
+        self.graph
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_graph_builder() {
+        let graph = GraphBuilder::new("main")
+            .node("a", "A", Table::new())
+            .node("b", "B", Table::new())
+            .input("inp", "a:inp", 4)
+            .connect("a:out", PortRef::new("b", "inp"), 2)
+            .connect_all(["a:outs", "b:inps:1"], 8)
+            .output("out", PortRef::new("b", "out").tag(2), 4)
+            .into_config();
+        assert_eq!(graph.name, "main");
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.inputs[0].conn.ports, vec!["a:inp"]);
+        assert_eq!(graph.connections[0].ports, vec!["a:out", "b:inp"]);
+        assert_eq!(graph.connections[0].cap, 2);
+        assert_eq!(graph.connections[1].ports, vec!["a:outs", "b:inps:1"]);
+        assert_eq!(graph.outputs[0].conn.ports, vec!["b:out:2"]);
+    }
+}
@@ -0,0 +1,56 @@
+/**
+ * \file flow-rs/src/config/format.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+/// The format of a config file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::Toml
+    }
+}
+
+impl Format {
+    /// Guess the format by the extension of `path`, which is toml if the extension is unknown
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Format::Json,
+            Some("yaml") | Some("yml") => Format::Yaml,
+            _ => Format::Toml,
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(self, content: &str) -> Result<T> {
+        Ok(match self {
+            Format::Toml => toml::from_str(content)?,
+            Format::Json => serde_json::from_str(content)?,
+            Format::Yaml => serde_yaml::from_str(content)?,
+        })
+    }
+
+    pub fn emit<T: Serialize>(self, value: &T) -> Result<String> {
+        Ok(match self {
+            // toml requires values to be emitted before tables, which is handled by `toml::Value`
+            Format::Toml => toml::to_string_pretty(&toml::Value::try_from(value)?)?,
+            Format::Json => serde_json::to_string_pretty(value)?,
+            Format::Yaml => serde_yaml::to_string(value)?,
+        })
+    }
+}
@@ -9,27 +9,145 @@
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
 use super::interlayer::*;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use std::collections::{BTreeSet, HashMap};
-use std::fs::File;
-use std::io::Write;
-use std::path::PathBuf;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// Dump the graph into the path in `MEGFLOW_DUMP`, whose extension selects the format.
+///
+/// `.dot` and `.svg` are rendered in rust, and others are rendered as png by the `dot` binary, or as svg next to
+/// the path if `dot` is not available.
 pub fn dump(config: &Config) -> Result<()> {
     if let Ok(path) = std::env::var("MEGFLOW_DUMP") {
-        let dot = dump_dot(config);
         let path = PathBuf::from(path);
-        let mut f = File::create(&path)?;
-        write!(f, "{}", dot)?;
-        let output = Command::new("dot").arg("-Tpng").arg(&path).output()?;
-        std::fs::write(&path, output.stdout)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("dot") => std::fs::write(&path, dump_dot(config))?,
+            Some("svg") => std::fs::write(&path, dump_svg(config))?,
+            _ => {
+                let dot_path = path.with_extension("dot");
+                std::fs::write(&dot_path, dump_dot(config))?;
+                if let Err(err) = render_png(&dot_path, &path) {
+                    let svg_path = path.with_extension("svg");
+                    log::warn!(
+                        "failed to render {:?} by dot: {}, dump {:?} instead",
+                        path,
+                        err,
+                        svg_path
+                    );
+                    std::fs::write(&svg_path, dump_svg(config))?;
+                }
+            }
+        }
     }
     Ok(())
 }
 
-fn dump_dot(config: &Config) -> String {
-    let mut buf = vec![];
+fn render_png(dot_path: &Path, path: &Path) -> Result<()> {
+    let output = Command::new("dot")
+        .arg("-Tpng")
+        .arg(dot_path)
+        .arg("-o")
+        .arg(path)
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Node,
+    // an input or output of the main graph
+    Io,
+    // where a connection with many senders or receivers forks
+    Point,
+}
+
+struct Vertex {
+    id: String,
+    label: String,
+    shape: Shape,
+    // index of the subgraph in `config.graphs`
+    cluster: Option<usize>,
+}
+
+struct Edge {
+    from: String,
+    to: String,
+    label: String,
+    arrow: bool,
+    color: Option<&'static str>,
+}
+
+/// The live state of a connection of the main graph, see `MainGraph::snapshot_dot`
+pub(crate) struct Live {
+    pub queued: usize,
+    pub blocked: bool,
+    // received envelopes per second
+    pub throughput: f64,
+}
+
+impl Live {
+    fn label(&self, cap: usize) -> String {
+        format!(
+            "{}{}/{} queued, {:.1}/s",
+            if self.blocked { "blocked, " } else { "" },
+            self.queued,
+            cap,
+            self.throughput
+        )
+    }
+
+    fn color(&self, cap: usize) -> Option<&'static str> {
+        if self.blocked {
+            Some("red")
+        } else if self.queued * 2 >= cap && self.queued > 0 {
+            Some("orange")
+        } else if self.throughput > 0.0 {
+            Some("darkgreen")
+        } else {
+            None
+        }
+    }
+}
+
+struct Diagram {
+    name: String,
+    clusters: Vec<(usize, String)>,
+    vertices: Vec<Vertex>,
+    edges: Vec<Edge>,
+}
+
+fn sorted<T>(map: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut items: Vec<_> = map.iter().collect();
+    items.sort_by(|a, b| a.0.cmp(b.0));
+    items
+}
+
+// ports of graph inputs and outputs are empty
+fn arrow(tx_ports: &str, rx_ports: &str) -> String {
+    [tx_ports, rx_ports]
+        .iter()
+        .filter(|ports| !ports.is_empty())
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+fn diagram(config: &Config, live: &HashMap<String, Live>) -> Diagram {
+    let mut diagram = Diagram {
+        name: config.main.clone(),
+        clusters: vec![],
+        vertices: vec![],
+        edges: vec![],
+    };
     let mut rename = HashMap::new();
     let mut flatten_ports = HashMap::new();
     let mut dc = 0;
@@ -62,36 +180,41 @@ fn dump_dot(config: &Config) -> String {
             );
         }
     }
-    buf.push(format!("digraph {} {{", config.main));
 
-    // write subgraph into buf
     for (i, graph) in config.graphs.iter().enumerate() {
         let is_subgraph = graph.name != config.main;
-        if is_subgraph {
-            buf.push(format!("subgraph cluster_{} {{", i));
-            buf.push("style=filled;color=lightgrey;node [style=filled,color=white];".to_string());
-        }
-        // write nodes into buf
-        for (name, _) in graph
-            .nodes
-            .iter()
+        let cluster = if is_subgraph {
+            diagram.clusters.push((i, graph.name.clone()));
+            Some(i)
+        } else {
+            None
+        };
+        for (name, _) in sorted(&graph.nodes)
+            .into_iter()
             .filter(|(_, config)| !config.entity.ty.iter().any(|x| is_graph(x)))
         {
             let id = mapping((graph.name.clone(), name));
-            buf.push(format!("n{} [label=\"{}\"]", id, name));
+            diagram.vertices.push(Vertex {
+                id: format!("n{}", id),
+                label: name.clone(),
+                shape: Shape::Node,
+                cluster,
+            });
         }
-        // write connections into buf
-        for (name, conn) in &graph.connections {
+        for (name, conn) in sorted(&graph.connections) {
             macro_rules! extract {
-                ($x: ident, $x_len: ident) => {
+                ($x: ident, $x_ports: ident) => {
                     let mut $x = vec![];
+                    let mut $x_ports = BTreeSet::new();
                     for p in &conn.$x {
+                        $x_ports.insert(p.port_name.clone());
                         for ty in &p.node_type {
                             let pname = format!("{}:{}", ty, p.port_name);
-                            if flatten_ports.contains_key(&pname) {
-                                let flatten_ports = flatten_ports.get(&pname).unwrap();
-                                let mut flatten_ports =
-                                    flatten_ports.iter().map(|p| mapping((p.0.clone(), &p.1))).collect();
+                            if let Some(flatten_ports) = flatten_ports.get(&pname) {
+                                let mut flatten_ports = flatten_ports
+                                    .iter()
+                                    .map(|p| mapping((p.0.clone(), &p.1)))
+                                    .collect();
                                 $x.append(&mut flatten_ports);
                             } else if config.nodes.contains_key(&p.node_name) {
                                 $x.push(mapping(("global".to_owned(), &p.node_name)));
@@ -99,59 +222,512 @@ fn dump_dot(config: &Config) -> String {
                                 $x.push(mapping((graph.name.clone(), &p.node_name)));
                             }
                         }
-
                     }
                     if $x.is_empty() {
                         if is_subgraph {
                             continue;
                         } else {
                             let id = mapping((graph.name.clone(), name));
-                            buf.push(format!(
-                                "n{} [label=\"{}\", shape=circle,fillcolor=black, style=filled, fontcolor=white]",
-                                id, name
-                            ));
+                            diagram.vertices.push(Vertex {
+                                id: format!("n{}", id),
+                                label: name.clone(),
+                                shape: Shape::Io,
+                                cluster,
+                            });
                             $x.push(id);
                         }
                     }
-                    let ($x_len, $x) = (
-                        $x.len(),
-                        $x.iter()
-                            .map(|x| format!("n{}", x))
-                            .collect::<BTreeSet<_>>()
-                            .into_iter()
-                            .collect::<Vec<_>>()
-                            .join(","),
-                    );
+                    let $x = $x
+                        .iter()
+                        .map(|x| format!("n{}", x))
+                        .collect::<BTreeSet<_>>();
+                    let $x_ports = $x_ports.into_iter().collect::<Vec<_>>().join(",");
                 };
             }
 
-            extract!(tx, tx_len);
-            extract!(rx, rx_len);
+            extract!(tx, tx_ports);
+            extract!(rx, rx_ports);
 
-            if tx_len == 1 && rx_len == 1 {
-                buf.push(format!("{} -> {}", tx, rx));
+            let live = live.get(name).filter(|_| graph.name == config.main);
+            let cap = match live {
+                Some(live) => live.label(conn.cap),
+                None => format!("cap={}", conn.cap),
+            };
+            let color = live.and_then(|live| live.color(conn.cap));
+            if tx.len() == 1 && rx.len() == 1 {
+                diagram.edges.push(Edge {
+                    from: tx.into_iter().next().unwrap(),
+                    to: rx.into_iter().next().unwrap(),
+                    label: format!("{}\n{}", arrow(&tx_ports, &rx_ports), cap),
+                    arrow: true,
+                    color,
+                });
             } else {
-                buf.push(format!("dc{} [shape=point,width=0.01,height=0.01]", dc));
-                buf.push(format!("{} -> dc{} [dir=none]", tx, dc));
-                buf.push(format!("dc{} -> {}", dc, rx));
+                let point = format!("dc{}", dc);
                 dc += 1;
+                diagram.vertices.push(Vertex {
+                    id: point.clone(),
+                    label: String::new(),
+                    shape: Shape::Point,
+                    cluster,
+                });
+                for from in tx {
+                    diagram.edges.push(Edge {
+                        from,
+                        to: point.clone(),
+                        label: format!("{}\n{}", tx_ports, cap),
+                        arrow: false,
+                        color,
+                    });
+                }
+                for to in rx {
+                    diagram.edges.push(Edge {
+                        from: point.clone(),
+                        to,
+                        label: rx_ports.clone(),
+                        arrow: true,
+                        color,
+                    });
+                }
             }
         }
-
-        if is_subgraph {
-            buf.push(format!("label = \"{}\"", graph.name));
-            buf.push("}".to_string());
-        }
     }
-    // write nodes in global into buf
-    for (name, _) in config
-        .nodes
-        .iter()
+    for (name, _) in sorted(&config.nodes)
+        .into_iter()
         .filter(|(_, config)| !config.entity.ty.iter().any(|x| is_graph(x)))
     {
         let id = mapping(("global".to_owned(), name));
-        buf.push(format!("n{} [label=\"{}\"]", id, name));
+        diagram.vertices.push(Vertex {
+            id: format!("n{}", id),
+            label: name.clone(),
+            shape: Shape::Node,
+            cluster: None,
+        });
+    }
+    diagram
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render the graph in the DOT language, edges are annotated with port names and capacities of connections
+pub fn dump_dot(config: &Config) -> String {
+    render_dot(diagram(config, &HashMap::new()))
+}
+
+/// Render the graph in the DOT language, edges of the main graph are annotated and colored by `live`
+pub(crate) fn dump_dot_live(config: &Config, live: &HashMap<String, Live>) -> String {
+    render_dot(diagram(config, live))
+}
+
+fn render_dot(diagram: Diagram) -> String {
+    let mut buf = vec![format!("digraph \"{}\" {{", escape_dot(&diagram.name))];
+    let vertex = |v: &Vertex| match v.shape {
+        Shape::Node => format!("{} [label=\"{}\"]", v.id, escape_dot(&v.label)),
+        Shape::Io => format!(
+            "{} [label=\"{}\", shape=circle,fillcolor=black, style=filled, fontcolor=white]",
+            v.id,
+            escape_dot(&v.label)
+        ),
+        Shape::Point => format!("{} [shape=point,width=0.01,height=0.01]", v.id),
+    };
+    for (i, label) in &diagram.clusters {
+        buf.push(format!("subgraph cluster_{} {{", i));
+        buf.push("style=filled;color=lightgrey;node [style=filled,color=white];".to_string());
+        for v in diagram.vertices.iter().filter(|v| v.cluster == Some(*i)) {
+            buf.push(vertex(v));
+        }
+        buf.push(format!("label = \"{}\"", escape_dot(label)));
+        buf.push("}".to_string());
+    }
+    for v in diagram.vertices.iter().filter(|v| v.cluster.is_none()) {
+        buf.push(vertex(v));
+    }
+    for e in &diagram.edges {
+        let dir = if e.arrow { "" } else { ", dir=none" };
+        let color = match e.color {
+            Some(color) => format!(", color={0}, fontcolor={0}, penwidth=2", color),
+            None => String::new(),
+        };
+        buf.push(format!(
+            "{} -> {} [label=\"{}\", fontsize=10{}{}]",
+            e.from,
+            e.to,
+            escape_dot(&e.label),
+            dir,
+            color
+        ));
     }
     buf.push("}".to_string());
     buf.join("\n")
 }
+
+const CHAR_W: f64 = 7.5;
+const LINE_H: f64 = 14.0;
+const NODE_H: f64 = 36.0;
+const GAP_X: f64 = 140.0;
+const GAP_Y: f64 = 40.0;
+const MARGIN: f64 = 20.0;
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn text(buf: &mut String, x: f64, y: f64, size: u32, fill: &str, content: &str) {
+    let lines: Vec<_> = content.lines().collect();
+    let top = y - (lines.len() as f64 - 1.0) * LINE_H / 2.0;
+    for (i, line) in lines.iter().enumerate() {
+        writeln!(
+            buf,
+            r#"<text x="{:.1}" y="{:.1}" font-size="{}" fill="{}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+            x,
+            top + i as f64 * LINE_H,
+            size,
+            fill,
+            escape_xml(line)
+        )
+        .unwrap();
+    }
+}
+
+/// Render the graph as svg without graphviz, vertices are placed in layers from left to right
+pub fn dump_svg(config: &Config) -> String {
+    render_svg(diagram(config, &HashMap::new()))
+}
+
+/// Render the graph as svg, edges of the main graph are annotated and colored by `live`
+pub(crate) fn dump_svg_live(config: &Config, live: &HashMap<String, Live>) -> String {
+    render_svg(diagram(config, live))
+}
+
+fn render_svg(diagram: Diagram) -> String {
+    let n = diagram.vertices.len();
+    let index: HashMap<_, _> = diagram
+        .vertices
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v.id.as_str(), i))
+        .collect();
+    // edges to vertices missing in the diagram are skipped
+    let (lines, edges): (Vec<_>, Vec<_>) = diagram
+        .edges
+        .iter()
+        .filter_map(|e| {
+            let from = *index.get(e.from.as_str())?;
+            let to = *index.get(e.to.as_str())?;
+            Some((e, (from, to)))
+        })
+        .unzip();
+
+    // longest path layering, where cycles are cut by limiting the rank
+    let mut rank = vec![0; n];
+    for _ in 0..n {
+        let mut changed = false;
+        for &(from, to) in &edges {
+            if rank[to] < rank[from] + 1 && rank[from] + 1 < n {
+                rank[to] = rank[from] + 1;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    let layers = rank.iter().max().map_or(0, |max| max + 1);
+    let mut layer: Vec<Vec<usize>> = vec![vec![]; layers];
+    for (i, r) in rank.iter().enumerate() {
+        layer[*r].push(i);
+    }
+    // order vertices by the barycenter of their predecessors to reduce crossings
+    let mut order = vec![0.0; n];
+    for vertices in &mut layer {
+        for v in vertices.iter() {
+            let preds: Vec<_> = edges
+                .iter()
+                .filter(|(from, to)| *to == *v && rank[*from] < rank[*v])
+                .map(|(from, _)| order[*from])
+                .collect();
+            if !preds.is_empty() {
+                order[*v] = preds.iter().sum::<f64>() / preds.len() as f64;
+            }
+        }
+        vertices.sort_by(|a, b| order[*a].partial_cmp(&order[*b]).unwrap());
+        for (i, v) in vertices.iter().enumerate() {
+            order[*v] = i as f64;
+        }
+    }
+
+    let width = |v: &Vertex| match v.shape {
+        Shape::Point => 6.0,
+        Shape::Io => NODE_H.max(v.label.len() as f64 * CHAR_W + 16.0),
+        Shape::Node => v.label.len() as f64 * CHAR_W + 24.0,
+    };
+    let layer_w: Vec<f64> = layer
+        .iter()
+        .map(|vertices| {
+            vertices
+                .iter()
+                .map(|v| width(&diagram.vertices[*v]))
+                .fold(0.0, f64::max)
+        })
+        .collect();
+    let mut pos = vec![(0.0, 0.0); n];
+    let mut x = MARGIN;
+    let mut height: f64 = 0.0;
+    for (vertices, w) in layer.iter().zip(layer_w.iter()) {
+        for (i, v) in vertices.iter().enumerate() {
+            let y = MARGIN + NODE_H / 2.0 + i as f64 * (NODE_H + GAP_Y);
+            pos[*v] = (x + w / 2.0, y);
+            height = height.max(y + NODE_H / 2.0 + MARGIN);
+        }
+        x += w + GAP_X;
+    }
+    let total_w = x - GAP_X + MARGIN;
+
+    let mut buf = String::new();
+    writeln!(
+        buf,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {:.0} {:.0}" font-family="sans-serif">"#,
+        total_w, height, total_w, height
+    )
+    .unwrap();
+    writeln!(buf, "<title>{}</title>", escape_xml(&diagram.name)).unwrap();
+    buf.push_str(r#"<defs><marker id="arrow" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse"><path d="M 0 0 L 10 5 L 0 10 z" fill="black"/></marker></defs>"#);
+    buf.push('\n');
+
+    for (e, &(from, to)) in lines.iter().zip(edges.iter()) {
+        let (fx, fy) = pos[from];
+        let (tx, ty) = pos[to];
+        let (fw, tw) = (width(&diagram.vertices[from]), width(&diagram.vertices[to]));
+        let (x1, x2) = if tx >= fx {
+            (fx + fw / 2.0, tx - tw / 2.0)
+        } else {
+            (fx - fw / 2.0, tx + tw / 2.0)
+        };
+        let marker = if e.arrow {
+            r#" marker-end="url(#arrow)""#
+        } else {
+            ""
+        };
+        writeln!(
+            buf,
+            r#"<line x1="{:.1}" y1="{:.1}" x2="{:.1}" y2="{:.1}" stroke="{}" stroke-width="{}"{}/>"#,
+            x1,
+            fy,
+            x2,
+            ty,
+            e.color.unwrap_or("black"),
+            if e.color.is_some() { 2 } else { 1 },
+            marker
+        )
+        .unwrap();
+        text(
+            &mut buf,
+            (x1 + x2) / 2.0,
+            (fy + ty) / 2.0 - 10.0,
+            10,
+            e.color.unwrap_or("dimgray"),
+            &e.label,
+        );
+    }
+
+    for (v, &(x, y)) in diagram.vertices.iter().zip(pos.iter()) {
+        let w = width(v);
+        match v.shape {
+            Shape::Point => {
+                writeln!(buf, r#"<circle cx="{:.1}" cy="{:.1}" r="3"/>"#, x, y).unwrap();
+            }
+            Shape::Io => {
+                writeln!(
+                    buf,
+                    r#"<ellipse cx="{:.1}" cy="{:.1}" rx="{:.1}" ry="{:.1}" fill="black"/>"#,
+                    x,
+                    y,
+                    w / 2.0,
+                    NODE_H / 2.0
+                )
+                .unwrap();
+                text(&mut buf, x, y, 12, "white", &v.label);
+            }
+            Shape::Node => {
+                let fill = if v.cluster.is_some() {
+                    "lightgrey"
+                } else {
+                    "white"
+                };
+                writeln!(
+                    buf,
+                    r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="6" fill="{}" stroke="black"/>"#,
+                    x - w / 2.0,
+                    y - NODE_H / 2.0,
+                    w,
+                    NODE_H,
+                    fill
+                )
+                .unwrap();
+                text(&mut buf, x, y, 12, "black", &v.label);
+            }
+        }
+        if let Some(i) = v.cluster {
+            let graph = &diagram.clusters.iter().find(|c| c.0 == i).unwrap().1;
+            writeln!(
+                buf,
+                r#"<text x="{:.1}" y="{:.1}" font-size="9" fill="gray" text-anchor="middle">{}</text>"#,
+                x,
+                y + NODE_H / 2.0 + 10.0,
+                escape_xml(graph)
+            )
+            .unwrap();
+        }
+    }
+    buf.push_str("</svg>\n");
+    buf
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn port(node: &str, port: &str) -> Port {
+        Port {
+            node_type: vec!["T".to_owned()],
+            node_name: node.to_owned(),
+            port_name: port.to_owned(),
+            port_type: PortTy::Unit,
+            port_tag: None,
+        }
+    }
+
+    fn node(name: &str) -> Node {
+        Node {
+            entity: Entity {
+                name: name.to_owned(),
+                ty: vec!["T".to_owned()],
+                args: Default::default(),
+            },
+            res: vec![],
+            cloned: None,
+            balance: Default::default(),
+            inputs: vec!["inp".to_owned()],
+            outputs: vec!["out".to_owned()],
+            is_dyn: false,
+            is_shared: false,
+            restart: Default::default(),
+        }
+    }
+
+    fn conn(cap: usize, tx: Vec<Port>, rx: Vec<Port>) -> Connection {
+        Connection {
+            cap,
+            tx,
+            rx,
+            ttl: None,
+            expired: None,
+            idle_timeout: None,
+            max_instances: None,
+            admission: Default::default(),
+            pool_size: None,
+            policy: Default::default(),
+            priority: false,
+        }
+    }
+
+    fn config() -> Config {
+        let mut connections = HashMap::new();
+        connections.insert("inp".to_owned(), conn(4, vec![], vec![port("a", "inp")]));
+        connections.insert(
+            "__0__".to_owned(),
+            conn(
+                8,
+                vec![port("a", "out")],
+                vec![port("b", "inp"), port("c", "inp")],
+            ),
+        );
+        let nodes = ["a", "b", "c"]
+            .iter()
+            .map(|name| (name.to_string(), node(name)))
+            .collect();
+        Config {
+            resources: Default::default(),
+            nodes: Default::default(),
+            graphs: vec![Graph {
+                name: "main".to_owned(),
+                resources: Default::default(),
+                nodes,
+                inputs: vec!["inp".to_owned()],
+                outputs: vec![],
+                connections,
+                is_shared: false,
+                global_res: vec![],
+            }],
+            main: "main".to_owned(),
+            metrics: None,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = dump_dot(&config());
+        assert!(dot.starts_with("digraph \"main\" {"));
+        assert!(dot.contains("-> n0 [label=\"inp\\ncap=4\", fontsize=10]"));
+        assert!(dot.contains("-> dc0 [label=\"out\\ncap=8\", fontsize=10, dir=none]"));
+        assert!(dot.contains("dc0 -> n2 [label=\"inp\", fontsize=10]"));
+        assert_eq!(dot, dump_dot(&config()));
+    }
+
+    #[test]
+    fn test_live() {
+        let mut live = HashMap::new();
+        live.insert(
+            "__0__".to_owned(),
+            Live {
+                queued: 8,
+                blocked: true,
+                throughput: 2.5,
+            },
+        );
+        live.insert(
+            "inp".to_owned(),
+            Live {
+                queued: 0,
+                blocked: false,
+                throughput: 0.0,
+            },
+        );
+        let dot = dump_dot_live(&config(), &live);
+        assert!(dot.contains(
+            "-> dc0 [label=\"out\\nblocked, 8/8 queued, 2.5/s\", fontsize=10, dir=none, color=red, fontcolor=red, penwidth=2]"
+        ));
+        assert!(dot.contains("-> n0 [label=\"inp\\n0/4 queued, 0.0/s\", fontsize=10]"));
+        let svg = dump_svg_live(&config(), &live);
+        assert_eq!(svg.matches(r#"stroke="red""#).count(), 3);
+    }
+
+    #[test]
+    fn test_svg() {
+        let svg = dump_svg(&config());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect").count(), 3);
+        assert_eq!(svg.matches("<ellipse").count(), 1);
+        assert_eq!(svg.matches("<line").count(), 4);
+        assert!(svg.contains(">cap=8</text>"));
+    }
+
+    #[test]
+    fn test_svg_unknown_node() {
+        let mut config = config();
+        let conn = config.graphs[0].connections.get_mut("__0__").unwrap();
+        conn.rx.push(port("x", "inp"));
+        // the edge to the unknown node is skipped
+        let svg = dump_svg(&config);
+        assert_eq!(svg.matches("<line").count(), 4);
+    }
+}
@@ -116,6 +116,14 @@ pub fn bcast(
                     cap: src.cap,
                     rx: vec![p],
                     tx: vec![bcast_p("[out]")],
+                    ttl: src.ttl,
+                    expired: src.expired.clone(),
+                    idle_timeout: src.idle_timeout,
+                    max_instances: src.max_instances,
+                    admission: src.admission,
+                    pool_size: src.pool_size,
+                    policy: src.policy,
+                    priority: src.priority,
                 },
             );
         }
@@ -129,11 +137,13 @@ pub fn bcast(
                     args: Default::default(),
                 },
                 cloned: Some(1),
+                balance: Default::default(),
                 res: vec![],
                 is_dyn: false,
                 inputs: vec!["inp".to_owned()],
                 outputs: vec!["[out]".to_owned()],
                 is_shared: false,
+                restart: Default::default(),
             },
         );
     }
@@ -155,6 +165,14 @@ pub fn dyn_inp_trans(
                 let mut tmp_conn = presentation::Connection {
                     cap: src.conn.cap,
                     ports: vec![],
+                    ttl_ms: None,
+                    expired: None,
+                    idle_timeout_ms: None,
+                    max_instances: None,
+                    admission: Default::default(),
+                    pool_size: None,
+                    policy: Default::default(),
+                    priority: false,
                 };
                 let tmp_node = interlayer::Node {
                     entity: interlayer::Entity {
@@ -164,10 +182,12 @@ pub fn dyn_inp_trans(
                     },
                     res: Default::default(),
                     cloned: Some(1),
+                    balance: Default::default(),
                     inputs: vec!["inp".to_owned()],
                     outputs: vec!["dyn@out".to_owned()],
                     is_dyn: false,
                     is_shared: false,
+                    restart: Default::default(),
                 };
                 tmp_conn.ports.push(p.to_owned());
                 nodes.insert(tmp_node.entity.name.clone(), tmp_node);
@@ -195,6 +215,14 @@ pub fn dyn_out_trans(
                 let mut tmp_conn = presentation::Connection {
                     cap: src.conn.cap,
                     ports: vec![],
+                    ttl_ms: None,
+                    expired: None,
+                    idle_timeout_ms: None,
+                    max_instances: None,
+                    admission: Default::default(),
+                    pool_size: None,
+                    policy: Default::default(),
+                    priority: false,
                 };
                 let tmp_node = interlayer::Node {
                     entity: interlayer::Entity {
@@ -204,10 +232,12 @@ pub fn dyn_out_trans(
                     },
                     res: Default::default(),
                     cloned: Some(1),
+                    balance: Default::default(),
                     inputs: vec!["dyn@inp".to_owned()],
                     outputs: vec!["out".to_owned()],
                     is_dyn: false,
                     is_shared: false,
+                    restart: Default::default(),
                 };
                 tmp_conn.ports.push(p.to_owned());
                 nodes.insert(tmp_node.entity.name.clone(), tmp_node);
@@ -10,9 +10,10 @@
  */
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::time::Duration;
 use toml::value::Table;
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PortTy {
     Unit,
     List,
@@ -20,7 +21,7 @@ pub enum PortTy {
     Dyn,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Port {
     pub node_type: Vec<String>,
     pub node_name: String,
@@ -29,32 +30,49 @@ pub struct Port {
     pub port_tag: Option<u64>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Connection {
     pub cap: usize,
     pub tx: Vec<Port>,
     pub rx: Vec<Port>,
+    pub ttl: Option<Duration>,
+    pub expired: Option<String>,
+    pub idle_timeout: Option<Duration>,
+    pub max_instances: Option<usize>,
+    pub admission: super::presentation::AdmissionPolicy,
+    pub pool_size: Option<usize>,
+    pub policy: super::presentation::OverflowPolicy,
+    pub priority: bool,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Entity {
     pub name: String,
     pub ty: Vec<String>,
     pub args: Table,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Restart {
+    pub policy: super::presentation::RestartPolicy,
+    pub max_retries: Option<u32>,
+    pub backoff: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
 pub struct Node {
     pub entity: Entity,
     pub res: Vec<String>,
     pub cloned: Option<usize>,
+    pub balance: super::presentation::BalancePolicy,
     pub inputs: Vec<String>,
     pub outputs: Vec<String>,
     pub is_dyn: bool,
     pub is_shared: bool,
+    pub restart: Restart,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Graph {
     pub name: String,
     pub resources: HashMap<String, super::presentation::Entity>,
@@ -66,12 +84,14 @@ pub struct Graph {
     pub global_res: Vec<String>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Config {
     pub resources: HashMap<String, super::presentation::Entity>,
     pub nodes: HashMap<String, Node>,
     pub graphs: Vec<Graph>,
     pub main: String,
+    pub metrics: Option<super::presentation::Metrics>,
+    pub trace: Option<super::presentation::Trace>,
 }
 
 impl Port {
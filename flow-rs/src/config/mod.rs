@@ -8,6 +8,8 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
+pub mod builder;
+pub mod format;
 pub mod graphviz;
 mod insert;
 pub mod interlayer;
@@ -15,10 +17,13 @@ pub mod table;
 
 pub mod parser;
 pub mod presentation;
+pub mod resolve;
+pub mod validate;
 
 use crate::node::{inputs, outputs};
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
+use std::time::Duration;
 
 pub(crate) struct PortUtility {
     pub(crate) ty: interlayer::PortTy,
@@ -51,6 +56,18 @@ fn translate_node(
     let ty = p.entity.ty.split('|').next().unwrap().trim();
     let inputs = inputs(local_key, ty)?.into_iter().collect();
     let outputs = outputs(local_key, ty)?.into_iter().collect();
+    if is_shared && p.restart != presentation::RestartPolicy::Never {
+        return Err(anyhow!(
+            "restart policy is not supported by shared node {}",
+            p.entity.name
+        ));
+    }
+    if is_shared && p.balance != presentation::BalancePolicy::SharedQueue {
+        return Err(anyhow!(
+            "balance policy is not supported by shared node {}",
+            p.entity.name
+        ));
+    }
     Ok(interlayer::Node {
         entity: interlayer::Entity {
             name: p.entity.name,
@@ -64,10 +81,16 @@ fn translate_node(
         },
         res: p.res,
         cloned: p.cloned,
+        balance: p.balance,
         inputs,
         outputs,
         is_dyn: false,
         is_shared,
+        restart: interlayer::Restart {
+            policy: p.restart,
+            max_retries: p.max_retries,
+            backoff: p.backoff.unwrap_or(0),
+        },
     })
 }
 
@@ -136,7 +159,7 @@ fn translate_conn(
         for p in &tx {
             let node = nodes
                 .get_mut(&p.node_name)
-                .expect("dyn share subgraph is not supported ");
+                .ok_or_else(|| anyhow!("dyn share subgraph is not supported"))?;
             node.is_dyn = true;
         }
     }
@@ -148,12 +171,34 @@ fn translate_conn(
         for p in &rx {
             let node = nodes
                 .get_mut(&p.node_name)
-                .expect("dyn share subgraph is not supported ");
+                .ok_or_else(|| anyhow!("dyn share subgraph is not supported"))?;
             node.is_dyn = true;
         }
     }
 
-    Ok(interlayer::Connection { cap: p.cap, rx, tx })
+    if let Some(expired) = &p.expired {
+        interlayer::Port::parse(expired)?;
+    }
+    if p.policy == presentation::OverflowPolicy::Sample(0) {
+        return Err(anyhow!("sample rate of connection is zero"));
+    }
+    if p.max_instances == Some(0) {
+        return Err(anyhow!("max instances of connection is zero"));
+    }
+
+    Ok(interlayer::Connection {
+        cap: p.cap,
+        rx,
+        tx,
+        ttl: p.ttl_ms.map(Duration::from_millis),
+        expired: p.expired,
+        idle_timeout: p.idle_timeout_ms.map(Duration::from_millis),
+        max_instances: p.max_instances,
+        admission: p.admission,
+        pool_size: p.pool_size,
+        policy: p.policy,
+        priority: p.priority,
+    })
 }
 
 pub fn translate_graph(
@@ -236,6 +281,8 @@ pub fn translate_config(local_key: u64, p: presentation::Config) -> Result<inter
         resources,
         nodes,
         main: p.main,
+        metrics: p.metrics,
+        trace: p.trace,
     };
     insert::global_res(&mut cfg);
 
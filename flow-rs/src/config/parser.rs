@@ -8,6 +8,7 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
+use super::format::Format;
 use anyhow::Result;
 use serde::Deserialize;
 use std::path::Path;
@@ -65,8 +66,22 @@ lazy_static::lazy_static! {
 }
 
 pub trait Parser<'a>: Deserialize<'a> {
+    /// Parse a template file in the format guessed by the extension, see `Format::from_path`
     fn from_file(template: &Path, dynamic: Option<&Path>) -> Result<Self>;
-    fn from_str(template: &str, dynamic: Option<&str>) -> Result<Self>;
+    /// Parse a template in `format`, whose relative includes are resolved against `path` the template is read from,
+    /// or the current directory if it is `None`, and the dynamic config is always toml
+    fn from_str_in(
+        template: &str,
+        path: Option<&Path>,
+        dynamic: Option<&str>,
+        format: Format,
+    ) -> Result<Self>;
+    fn from_str_as(template: &str, dynamic: Option<&str>, format: Format) -> Result<Self> {
+        Self::from_str_in(template, None, dynamic, format)
+    }
+    fn from_str(template: &str, dynamic: Option<&str>) -> Result<Self> {
+        Self::from_str_as(template, dynamic, Format::Toml)
+    }
 }
 
 #[cfg(test)]
@@ -18,6 +18,25 @@ use toml::value::Table;
 pub struct Connection {
     pub cap: usize,
     pub ports: Vec<String>,
+    /// Envelopes older than `ttl_ms` are expired when received
+    pub ttl_ms: Option<u64>,
+    /// An input port `node:port` in the same graph, where expired envelopes are diverted to, or they are dropped
+    pub expired: Option<String>,
+    /// Dynamic subgraphs created through the dyn connection are closed, when they receive no envelope within `idle_timeout_ms`
+    pub idle_timeout_ms: Option<u64>,
+    /// At most `max_instances` dynamic subgraphs created through the dyn connection are alive
+    pub max_instances: Option<usize>,
+    /// What creators do when `max_instances` dynamic subgraphs are alive, "wait" by default
+    #[serde(default)]
+    pub admission: AdmissionPolicy,
+    /// Keep `pool_size` dynamic subgraphs built ahead of time for the dyn connection, to start new ones faster
+    pub pool_size: Option<usize>,
+    /// What senders do when the connection is full, "block" by default
+    #[serde(default)]
+    pub policy: OverflowPolicy,
+    /// Envelopes with higher priority are received first, see `EnvelopeInfo::priority`
+    #[serde(default)]
+    pub priority: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -27,7 +46,7 @@ pub struct NamedConn {
     pub conn: Connection,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct Entity {
     pub name: String,
     pub ty: String,
@@ -35,6 +54,79 @@ pub struct Entity {
     pub args: Table,
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Restart the node if it returns an error, or exits while any of its inputs is still open
+    Always,
+    /// Restart the node only if it returns an error, panics abort the process and are never restarted
+    OnFailure,
+    /// Stop the whole graph once the node fails
+    Never,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::Never
+    }
+}
+
+/// How envelopes are distributed among instances of a node with `cloned > 1`
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BalancePolicy {
+    /// All instances receive from the same queue
+    SharedQueue,
+    /// Each instance has its own queue, which envelopes are dispatched into in turn
+    RoundRobin,
+    /// Each instance has its own queue, and envelopes with the same `from_addr` are dispatched into the same one
+    ConsistentHash,
+}
+
+impl Default for BalancePolicy {
+    fn default() -> Self {
+        BalancePolicy::SharedQueue
+    }
+}
+
+/// What senders do when a bounded connection is full
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverflowPolicy {
+    /// Wait until there is room
+    Block,
+    /// Drop the oldest queued envelope to make room, which has the lowest priority on a priority connection
+    DropOldest,
+    /// Drop the envelope being sent
+    DropNewest,
+    /// Only one of every n envelopes waits until there is room, and the others are dropped
+    Sample(usize),
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        OverflowPolicy::Block
+    }
+}
+
+/// What creators of dynamic subgraphs do when there are too many alive instances
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AdmissionPolicy {
+    /// Wait until an instance is finished
+    Wait,
+    /// Fail with `TooManyInstances`
+    Reject,
+    /// Close the least recently used instance
+    Evict,
+}
+
+impl Default for AdmissionPolicy {
+    fn default() -> Self {
+        AdmissionPolicy::Wait
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Node {
     #[serde(flatten)]
@@ -42,6 +134,13 @@ pub struct Node {
     #[serde(default)]
     pub res: Vec<String>,
     pub cloned: Option<usize>,
+    #[serde(default)]
+    pub balance: BalancePolicy,
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    pub max_retries: Option<u32>,
+    /// The delay before the first restart in milliseconds, doubled on every retry
+    pub backoff: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Parser)]
@@ -60,6 +159,33 @@ pub struct Graph {
     pub connections: Vec<Connection>,
 }
 
+fn default_metrics_path() -> String {
+    "metrics".to_owned()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Metrics {
+    pub port: u16,
+    #[serde(default = "default_metrics_path")]
+    pub path: String,
+}
+
+fn default_service_name() -> String {
+    "megflow".to_owned()
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Trace {
+    /// Append spans to the file, one OTLP-JSON export request per line
+    pub path: Option<PathBuf>,
+    /// Post spans to an OTLP/HTTP collector, e.g. `127.0.0.1:4318`, the path defaults to `/v1/traces`
+    pub endpoint: Option<String>,
+    #[serde(default = "default_service_name")]
+    pub service_name: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Parser)]
 #[serde(deny_unknown_fields)]
 pub struct Config {
@@ -71,4 +197,6 @@ pub struct Config {
     pub nodes: Vec<Node>,
     pub graphs: Vec<Graph>,
     pub main: String,
+    pub metrics: Option<Metrics>,
+    pub trace: Option<Trace>,
 }
@@ -0,0 +1,214 @@
+/**
+ * \file flow-rs/src/config/resolve.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::format::Format;
+use super::{graphviz, interlayer, presentation};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// A config whose includes, templates and connections expanded by the loader are resolved, see `Builder::resolve`.
+///
+/// It is loaded into the same graph as the original config.
+pub struct Resolved {
+    config: presentation::Config,
+    dot: String,
+    svg: String,
+}
+
+impl Resolved {
+    pub fn emit(&self, format: Format) -> Result<String> {
+        format.emit(&self.config)
+    }
+
+    /// Write the config into `path` in the format guessed by the extension
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let content = self.emit(Format::from_path(path.as_ref()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&self.config)?)
+    }
+
+    /// The graph in the DOT language, which is what `MEGFLOW_DUMP=<path>.dot` writes
+    pub fn to_dot(&self) -> &str {
+        &self.dot
+    }
+
+    /// The graph rendered as svg without graphviz, which is what `MEGFLOW_DUMP=<path>.svg` writes
+    pub fn to_svg(&self) -> &str {
+        &self.svg
+    }
+}
+
+fn port(p: &interlayer::Port) -> String {
+    // the inverse of `super::MAPPING`
+    let name = p.port_name.as_str();
+    let name = name.strip_prefix("dyn@").unwrap_or(name);
+    let name = name.trim_start_matches(&['[', '{'][..]);
+    let name = name.trim_end_matches(&[']', '}'][..]);
+    match p.port_tag {
+        Some(tag) => format!("{}:{}:{}", p.node_name, name, tag),
+        None => format!("{}:{}", p.node_name, name),
+    }
+}
+
+fn conn(c: &interlayer::Connection) -> presentation::Connection {
+    presentation::Connection {
+        cap: c.cap,
+        ports: c.tx.iter().chain(c.rx.iter()).map(port).collect(),
+        ttl_ms: c.ttl.map(|ttl| ttl.as_millis() as u64),
+        expired: c.expired.clone(),
+        idle_timeout_ms: c.idle_timeout.map(|timeout| timeout.as_millis() as u64),
+        max_instances: c.max_instances,
+        admission: c.admission,
+        pool_size: c.pool_size,
+        policy: c.policy,
+        priority: c.priority,
+    }
+}
+
+fn node(n: &interlayer::Node) -> presentation::Node {
+    presentation::Node {
+        entity: presentation::Entity {
+            name: n.entity.name.clone(),
+            ty: n.entity.ty.join("|"),
+            args: n.entity.args.clone(),
+        },
+        res: n.res.clone(),
+        cloned: n.cloned,
+        balance: n.balance,
+        restart: n.restart.policy,
+        max_retries: n.restart.max_retries,
+        backoff: Some(n.restart.backoff).filter(|backoff| *backoff > 0),
+    }
+}
+
+fn sorted<T, U>(map: &HashMap<String, T>, f: fn(&T) -> U) -> Vec<U> {
+    let mut keys: Vec<_> = map.keys().collect();
+    keys.sort();
+    keys.into_iter().map(|k| f(&map[k])).collect()
+}
+
+fn named_conns(graph: &interlayer::Graph, names: &[String]) -> Vec<presentation::NamedConn> {
+    names
+        .iter()
+        .map(|name| presentation::NamedConn {
+            name: name.clone(),
+            conn: conn(&graph.connections[name]),
+        })
+        .collect()
+}
+
+fn graph(g: &interlayer::Graph) -> presentation::Graph {
+    let mut names: Vec<_> = g
+        .connections
+        .keys()
+        .filter(|name| !g.inputs.contains(name) && !g.outputs.contains(name))
+        .collect();
+    names.sort();
+    presentation::Graph {
+        name: g.name.clone(),
+        resources: sorted(&g.resources, Clone::clone),
+        nodes: sorted(&g.nodes, node),
+        inputs: named_conns(g, &g.inputs),
+        outputs: named_conns(g, &g.outputs),
+        connections: names
+            .into_iter()
+            .map(|name| conn(&g.connections[name]))
+            .collect(),
+    }
+}
+
+pub fn resolve(cfg: &interlayer::Config) -> Resolved {
+    Resolved {
+        config: presentation::Config {
+            include: vec![],
+            resources: sorted(&cfg.resources, Clone::clone),
+            nodes: sorted(&cfg.nodes, node),
+            graphs: cfg.graphs.iter().map(graph).collect(),
+            main: cfg.main.clone(),
+            metrics: cfg.metrics.clone(),
+            trace: cfg.trace.clone(),
+        },
+        dot: graphviz::dump_dot(cfg),
+        svg: graphviz::dump_svg(cfg),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_port() {
+        let p = |name: &str, ty, tag| interlayer::Port {
+            node_type: vec!["A".to_owned()],
+            node_name: "a".to_owned(),
+            port_name: name.to_owned(),
+            port_type: ty,
+            port_tag: tag,
+        };
+        assert_eq!(port(&p("inp", interlayer::PortTy::Unit, None)), "a:inp");
+        assert_eq!(port(&p("[out]", interlayer::PortTy::List, None)), "a:out");
+        assert_eq!(
+            port(&p("{out}", interlayer::PortTy::Dict, Some(2))),
+            "a:out:2"
+        );
+        assert_eq!(port(&p("dyn@out", interlayer::PortTy::Dyn, None)), "a:out");
+    }
+
+    #[test]
+    fn test_format() -> Result<()> {
+        let resolved = Resolved {
+            config: presentation::Config {
+                include: vec![],
+                resources: vec![],
+                nodes: vec![],
+                graphs: vec![presentation::Graph {
+                    name: "main".to_owned(),
+                    resources: vec![],
+                    nodes: vec![],
+                    inputs: vec![],
+                    outputs: vec![],
+                    connections: vec![presentation::Connection {
+                        cap: 4,
+                        ports: vec!["a:out".to_owned(), "b:inp".to_owned()],
+                        ttl_ms: Some(10),
+                        expired: None,
+                        idle_timeout_ms: None,
+                        max_instances: None,
+                        admission: Default::default(),
+                        pool_size: None,
+                        policy: presentation::OverflowPolicy::Sample(2),
+                        priority: false,
+                    }],
+                }],
+                main: "main".to_owned(),
+                metrics: None,
+                trace: None,
+            },
+            dot: String::new(),
+            svg: String::new(),
+        };
+        for format in [Format::Toml, Format::Json, Format::Yaml] {
+            let content = resolved.emit(format)?;
+            let config: presentation::Config = format.parse(&content)?;
+            assert_eq!(config.main, "main");
+            let conn = &config.graphs[0].connections[0];
+            assert_eq!(conn.ports, vec!["a:out", "b:inp"]);
+            assert_eq!(conn.ttl_ms, Some(10));
+            assert_eq!(conn.policy, presentation::OverflowPolicy::Sample(2));
+        }
+        Ok(())
+    }
+}
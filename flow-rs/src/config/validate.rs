@@ -0,0 +1,907 @@
+/**
+ * \file flow-rs/src/config/validate.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::interlayer::{Port, PortTy};
+use super::{presentation, MAPPING};
+use anyhow::Result;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::ops::Range;
+
+/// How serious a `Diagnostic` is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// The graph can not be built
+    Error,
+    /// The graph can be built, but probably does not work as expected
+    Warning,
+}
+
+/// What a `Diagnostic` is about
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    Parse,
+    UnknownGraph,
+    UnknownNodeType,
+    UnknownNode,
+    UnknownPort,
+    UnusedConnection,
+    DanglingInput,
+    DanglingOutput,
+    ZeroCapacity,
+    DynPortMisuse,
+}
+
+/// A 1-based position in the template source
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// A problem found in a graph config by `Builder::validate`
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub kind: DiagnosticKind,
+    /// The graph where the problem is found, `None` means global scope
+    pub graph: Option<String>,
+    pub message: String,
+    /// The first place in the template source where the problem could be located
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Error => write!(f, "error")?,
+            Severity::Warning => write!(f, "warning")?,
+        }
+        if let Some(span) = self.span {
+            write!(f, "[{}:{}]", span.line, span.column)?;
+        }
+        if let Some(graph) = &self.graph {
+            write!(f, " graph[{}]", graph)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Locate diagnostics in the template source, whose places are searched in the table of the graph, node or
+/// connection they belong to, so that repeated names are located at the right place
+struct Locator<'a> {
+    source: &'a str,
+    // whether the source is a template, whose parse errors are located in the rendered text instead
+    templated: bool,
+    // tables of graphs in the source
+    graphs: HashMap<&'a str, Range<usize>>,
+}
+
+impl<'a> Locator<'a> {
+    fn new(source: &'a str, graphs: &'a [presentation::Graph]) -> Locator<'a> {
+        let mut locator = Locator {
+            source,
+            templated: ["{{", "{%", "{#"].iter().any(|x| source.contains(x)),
+            graphs: HashMap::new(),
+        };
+        let mut starts = vec![];
+        let mut from = 0;
+        // graphs are searched in order, so a node with the name of a later graph is skipped
+        for graph in graphs {
+            if let Some(offset) = locator.key_value(from..source.len(), "name", &graph.name) {
+                starts.push((&graph.name, offset));
+                from = offset + 1;
+            }
+        }
+        for (i, &(name, offset)) in starts.iter().enumerate() {
+            let next = starts.get(i + 1).map_or(source.len(), |x| x.1);
+            let range = locator
+                .table(offset)
+                .unwrap_or_else(|| locator.line_start(offset)..next);
+            locator.graphs.insert(name.as_str(), range);
+        }
+        locator
+    }
+
+    fn span_of(&self, offset: usize) -> Span {
+        let before = &self.source[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rfind('\n')
+            .map(|i| before[i + 1..].chars().count())
+            .unwrap_or_else(|| before.chars().count())
+            + 1;
+        Span { line, column }
+    }
+
+    fn line_start(&self, offset: usize) -> usize {
+        self.source[..offset].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    /// the table of `graph`, or the whole source for global scope
+    fn scope(&self, graph: Option<&str>) -> Option<Range<usize>> {
+        match graph {
+            Some(graph) => self.graphs.get(graph).cloned(),
+            None => Some(0..self.source.len()),
+        }
+    }
+
+    /// offsets of a string literal `s` in `range`, in order
+    fn strings(&self, range: Range<usize>, s: &str) -> BTreeSet<usize> {
+        let source = &self.source[range.clone()];
+        let double = format!("\"{}\"", s);
+        let single = format!("'{}'", s);
+        source
+            .match_indices(double.as_str())
+            .chain(source.match_indices(single.as_str()))
+            .map(|(i, _)| range.start + i)
+            .collect()
+    }
+
+    /// offset of the first string literal `s` in `range`
+    fn string(&self, range: Range<usize>, s: &str) -> Option<usize> {
+        let source = &self.source[range.clone()];
+        let double = source.find(&format!("\"{}\"", s));
+        let single = source.find(&format!("'{}'", s));
+        double
+            .into_iter()
+            .chain(single)
+            .min()
+            .map(|i| range.start + i)
+    }
+
+    /// offset of the string literal `value` of `key` in `range`, i.e. `key = "value"` or `"key": "value"`
+    fn key_value(&self, range: Range<usize>, key: &str, value: &str) -> Option<usize> {
+        let mut from = range.start;
+        while let Some(offset) = self.string(from..range.end, value) {
+            let before = self.source[..offset].trim_end();
+            if let Some(before) = before
+                .strip_suffix('=')
+                .or_else(|| before.strip_suffix(':'))
+            {
+                let before = before.trim_end();
+                let before = before
+                    .strip_suffix('"')
+                    .or_else(|| before.strip_suffix('\''))
+                    .unwrap_or(before);
+                if before.ends_with(key) {
+                    return Some(offset);
+                }
+            }
+            from = offset + 1;
+        }
+        None
+    }
+
+    /// the inline table enclosing `offset`
+    fn table(&self, offset: usize) -> Option<Range<usize>> {
+        let mut depth = 0;
+        let start = self.source[..offset].rfind(|c: char| {
+            match c {
+                '}' => depth += 1,
+                '{' if depth == 0 => return true,
+                '{' => depth -= 1,
+                _ => (),
+            }
+            false
+        })?;
+        let mut depth = 0;
+        let end = self.source[offset..].find(|c: char| {
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return true,
+                '}' => depth -= 1,
+                _ => (),
+            }
+            false
+        })?;
+        Some(start..offset + end + 1)
+    }
+
+    /// the table of the node `name` in `range`, which ends at the end of `range` if it is not an inline table
+    fn node(&self, range: Range<usize>, name: &str) -> Option<Range<usize>> {
+        let offset = self.key_value(range.clone(), "name", name)?;
+        Some(
+            self.table(offset)
+                .filter(|table| range.contains(&table.start))
+                .unwrap_or(offset..range.end),
+        )
+    }
+
+    /// offset of the `ports` array of a connection in `range`, which is searched after its name if it is named
+    fn conn(&self, range: Range<usize>, name: Option<&str>, ports: &[String]) -> Option<usize> {
+        let range = match name {
+            Some(name) => self.node(range, name)?,
+            None => range,
+        };
+        let first = match ports.first() {
+            Some(first) => first,
+            None => return self.empty_ports(range),
+        };
+        self.strings(range.clone(), first)
+            .into_iter()
+            .find(|&offset| {
+                // the other ports follow in order before the end of the array
+                let end = self.source[offset..range.end]
+                    .find(']')
+                    .map_or(range.end, |i| offset + i);
+                let mut from = offset + 1;
+                ports[1..]
+                    .iter()
+                    .all(|port| match self.string(from..end, port) {
+                        Some(i) => {
+                            from = i + 1;
+                            true
+                        }
+                        None => false,
+                    })
+            })
+    }
+
+    /// offset of an empty `ports` array in `range`
+    fn empty_ports(&self, range: Range<usize>) -> Option<usize> {
+        let source = &self.source[range.clone()];
+        let mut start = 0;
+        while let Some(i) = source[start..].find("ports") {
+            let offset = start + i;
+            let rest = source[offset + "ports".len()..]
+                .trim_start_matches(|c: char| c == '"' || c == '\'')
+                .trim_start();
+            if let Some(rest) = rest.strip_prefix('=').or_else(|| rest.strip_prefix(':')) {
+                if let Some(rest) = rest.trim_start().strip_prefix('[') {
+                    if rest.trim_start().starts_with(']') {
+                        return Some(range.start + offset);
+                    }
+                }
+            }
+            start = offset + "ports".len();
+        }
+        None
+    }
+}
+
+struct NodePorts {
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+struct Validator<'a> {
+    local_key: u64,
+    config: &'a presentation::Config,
+    locator: Locator<'a>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+enum Direction {
+    Rx,
+    Tx,
+}
+
+struct ResolvedPort<'a> {
+    node: &'a presentation::Node,
+    is_shared: bool,
+    direction: Direction,
+    ty: PortTy,
+}
+
+impl<'a> Validator<'a> {
+    fn report(
+        &mut self,
+        severity: Severity,
+        kind: DiagnosticKind,
+        graph: Option<&str>,
+        message: String,
+        span: Option<Span>,
+    ) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            kind,
+            graph: graph.map(|x| x.to_owned()),
+            message,
+            span,
+        });
+    }
+
+    fn node_ports(&self, ty: &str) -> Option<NodePorts> {
+        let ty = ty.split('|').next().unwrap().trim();
+        if let Some(graph) = self.config.graphs.iter().find(|g| g.name == ty) {
+            Some(NodePorts {
+                inputs: graph.inputs.iter().map(|x| x.name.clone()).collect(),
+                outputs: graph.outputs.iter().map(|x| x.name.clone()).collect(),
+            })
+        } else {
+            let inputs = crate::node::inputs(self.local_key, ty).ok()?;
+            let outputs = crate::node::outputs(self.local_key, ty).ok()?;
+            Some(NodePorts {
+                inputs: inputs.into_iter().collect(),
+                outputs: outputs.into_iter().collect(),
+            })
+        }
+    }
+
+    fn name_span(&self, graph: &presentation::Graph, name: &str) -> Option<Span> {
+        self.locator
+            .scope(Some(&graph.name))
+            .and_then(|range| self.locator.key_value(range, "name", name))
+            .map(|offset| self.locator.span_of(offset))
+    }
+
+    fn is_graph(&self, ty: &str) -> bool {
+        ty.split('|')
+            .all(|ty| self.config.graphs.iter().any(|g| g.name == ty.trim()))
+    }
+
+    fn check_node(&mut self, graph: Option<&str>, node: &presentation::Node) {
+        let span = self
+            .locator
+            .scope(graph)
+            .and_then(|range| self.locator.node(range, &node.entity.name))
+            .and_then(|range| self.locator.key_value(range, "ty", &node.entity.ty))
+            .map(|offset| self.locator.span_of(offset));
+        for ty in node.entity.ty.split('|').map(|x| x.trim()) {
+            if self.node_ports(ty).is_none() {
+                self.report(
+                    Severity::Error,
+                    DiagnosticKind::UnknownNodeType,
+                    graph,
+                    format!("unexpected node type [{}] of node {}", ty, node.entity.name),
+                    span,
+                );
+            }
+        }
+    }
+
+    /// resolve a port of the connection located in `range`
+    fn resolve(
+        &mut self,
+        graph: &'a presentation::Graph,
+        port_s: &str,
+        range: Option<Range<usize>>,
+    ) -> Option<ResolvedPort<'a>> {
+        let span = range
+            .and_then(|range| self.locator.string(range, port_s))
+            .map(|offset| self.locator.span_of(offset));
+        let ((n, p), _) = match Port::parse(port_s) {
+            Ok(ret) => ret,
+            Err(err) => {
+                self.report(
+                    Severity::Error,
+                    DiagnosticKind::UnknownPort,
+                    Some(&graph.name),
+                    err.to_string(),
+                    span,
+                );
+                return None;
+            }
+        };
+        let config = self.config;
+        let (node, is_shared) = if let Some(node) = graph.nodes.iter().find(|x| x.entity.name == n)
+        {
+            (node, false)
+        } else if let Some(node) = config.nodes.iter().find(|x| x.entity.name == n) {
+            (node, true)
+        } else {
+            self.report(
+                Severity::Error,
+                DiagnosticKind::UnknownNode,
+                Some(&graph.name),
+                format!("unexpected node name [{}] in port {}", n, port_s),
+                span,
+            );
+            return None;
+        };
+        // unknown types have been reported by `check_node`
+        let ports = self.node_ports(&node.entity.ty)?;
+        for utility in MAPPING {
+            let p = (utility.mapping)(p);
+            if ports.inputs.contains(&p) {
+                return Some(ResolvedPort {
+                    node,
+                    is_shared,
+                    direction: Direction::Rx,
+                    ty: utility.ty,
+                });
+            } else if ports.outputs.contains(&p) {
+                return Some(ResolvedPort {
+                    node,
+                    is_shared,
+                    direction: Direction::Tx,
+                    ty: utility.ty,
+                });
+            }
+        }
+        self.report(
+            Severity::Error,
+            DiagnosticKind::UnknownPort,
+            Some(&graph.name),
+            format!("unexpected port {}", port_s),
+            span,
+        );
+        None
+    }
+
+    fn check_conn(
+        &mut self,
+        graph: &'a presentation::Graph,
+        name: Option<&str>,
+        conn: &'a presentation::Connection,
+    ) -> (Vec<ResolvedPort<'a>>, Vec<ResolvedPort<'a>>) {
+        let scope = self.locator.scope(Some(&graph.name));
+        let at = scope
+            .clone()
+            .and_then(|range| self.locator.conn(range, name, &conn.ports));
+        let span = at.map(|offset| self.locator.span_of(offset));
+        let display = match name {
+            Some(name) => format!("connection {}", name),
+            None => format!("connection {:?}", conn.ports),
+        };
+        if conn.cap == 0 {
+            self.report(
+                Severity::Error,
+                DiagnosticKind::ZeroCapacity,
+                Some(&graph.name),
+                format!("capacity of {} is zero", display),
+                span,
+            );
+        }
+        if conn.ports.is_empty() {
+            self.report(
+                Severity::Error,
+                DiagnosticKind::UnusedConnection,
+                Some(&graph.name),
+                format!("encountered an unused {}", display),
+                span,
+            );
+        }
+
+        let mut rx = vec![];
+        let mut tx = vec![];
+        for port_s in &conn.ports {
+            let range = scope.clone().zip(at).map(|(scope, at)| at..scope.end);
+            if let Some(port) = self.resolve(graph, port_s, range) {
+                match port.direction {
+                    Direction::Rx => rx.push(port),
+                    Direction::Tx => tx.push(port),
+                }
+            }
+        }
+        (rx, tx)
+    }
+
+    fn check_dyn(
+        &mut self,
+        graph: &presentation::Graph,
+        name: Option<&str>,
+        conn: &presentation::Connection,
+        rx: &[ResolvedPort],
+        tx: &[ResolvedPort],
+        is_io: bool,
+    ) {
+        let is_dyn = |p: &&ResolvedPort| matches!(p.ty, PortTy::Dyn);
+        let dyn_rxn = rx.iter().filter(is_dyn).count();
+        let dyn_txn = tx.iter().filter(is_dyn).count();
+        if dyn_rxn == 0 && dyn_txn == 0 {
+            return;
+        }
+        let mut misuses = vec![];
+        if is_io {
+            misuses.push("dyn inputs or outputs of graph");
+        } else if dyn_rxn > 0 && dyn_txn > 0 {
+            misuses.push("rx & tx of channel both are dyn");
+        } else {
+            let (dyn_ports, peers) = if dyn_rxn > 0 { (rx, tx) } else { (tx, rx) };
+            if dyn_ports.len() > 1 || peers.len() != 1 {
+                misuses.push("dyn port shared with multiple subgraphs");
+            }
+            if dyn_ports.iter().any(|p| !is_dyn(&p)) {
+                misuses.push("dyn port shared with static ports");
+            }
+            for peer in peers {
+                if peer.is_shared {
+                    misuses.push("dyn share subgraph is not supported");
+                } else if !self.is_graph(&peer.node.entity.ty) {
+                    misuses.push("dyn port should connect with a subgraph");
+                }
+            }
+        }
+
+        let span = self
+            .locator
+            .scope(Some(&graph.name))
+            .and_then(|range| self.locator.conn(range, name, &conn.ports))
+            .map(|offset| self.locator.span_of(offset));
+        for msg in misuses {
+            self.report(
+                Severity::Error,
+                DiagnosticKind::DynPortMisuse,
+                Some(&graph.name),
+                format!("{} in connection {:?}", msg, conn.ports),
+                span,
+            );
+        }
+    }
+
+    fn check_graph(&mut self, graph: &'a presentation::Graph) {
+        for node in &graph.nodes {
+            self.check_node(Some(&graph.name), node);
+        }
+
+        for conn in &graph.connections {
+            let (rx, tx) = self.check_conn(graph, None, conn);
+            self.check_dyn(graph, None, conn, &rx, &tx, false);
+        }
+
+        for input in &graph.inputs {
+            let (rx, tx) = self.check_conn(graph, Some(&input.name), &input.conn);
+            if !input.conn.ports.is_empty() && rx.is_empty() {
+                let span = self.name_span(graph, &input.name);
+                self.report(
+                    Severity::Warning,
+                    DiagnosticKind::DanglingInput,
+                    Some(&graph.name),
+                    format!("input {} is not consumed by any node", input.name),
+                    span,
+                );
+            }
+            self.check_dyn(graph, Some(&input.name), &input.conn, &rx, &tx, true);
+        }
+
+        for output in &graph.outputs {
+            let (rx, tx) = self.check_conn(graph, Some(&output.name), &output.conn);
+            if !output.conn.ports.is_empty() && tx.is_empty() {
+                let span = self.name_span(graph, &output.name);
+                self.report(
+                    Severity::Warning,
+                    DiagnosticKind::DanglingOutput,
+                    Some(&graph.name),
+                    format!("output {} is not produced by any node", output.name),
+                    span,
+                );
+            }
+            self.check_dyn(graph, Some(&output.name), &output.conn, &rx, &tx, true);
+        }
+
+        // subgraph nodes whose inputs or outputs are never connected
+        let mut used: HashMap<&str, Vec<&str>> = HashMap::new();
+        for conn in graph
+            .connections
+            .iter()
+            .chain(graph.inputs.iter().map(|x| &x.conn))
+            .chain(graph.outputs.iter().map(|x| &x.conn))
+        {
+            for port_s in &conn.ports {
+                if let Ok(((n, p), _)) = Port::parse(port_s) {
+                    used.entry(n).or_default().push(p);
+                }
+            }
+        }
+        for node in &graph.nodes {
+            let ty = node.entity.ty.trim();
+            if let Some(subgraph) = self.config.graphs.iter().find(|g| g.name == ty) {
+                let used = used.get(node.entity.name.as_str());
+                let is_used = |name: &str| used.map(|x| x.contains(&name)).unwrap_or(false);
+                for input in subgraph.inputs.iter().filter(|x| !is_used(&x.name)) {
+                    let span = self.name_span(graph, &node.entity.name);
+                    self.report(
+                        Severity::Warning,
+                        DiagnosticKind::DanglingInput,
+                        Some(&graph.name),
+                        format!(
+                            "input {} of subgraph node {} is not connected",
+                            input.name, node.entity.name
+                        ),
+                        span,
+                    );
+                }
+                for output in subgraph.outputs.iter().filter(|x| !is_used(&x.name)) {
+                    let span = self.name_span(graph, &node.entity.name);
+                    self.report(
+                        Severity::Warning,
+                        DiagnosticKind::DanglingOutput,
+                        Some(&graph.name),
+                        format!(
+                            "output {} of subgraph node {} is not connected",
+                            output.name, node.entity.name
+                        ),
+                        span,
+                    );
+                }
+            }
+        }
+    }
+
+    fn check(&mut self) {
+        let config = self.config;
+        if !config.graphs.iter().any(|g| g.name == config.main) {
+            let span = self
+                .locator
+                .key_value(0..self.locator.source.len(), "main", &config.main)
+                .map(|offset| self.locator.span_of(offset));
+            self.report(
+                Severity::Error,
+                DiagnosticKind::UnknownGraph,
+                None,
+                format!("graph {} is not exist", config.main),
+                span,
+            );
+        }
+        for node in &config.nodes {
+            self.check_node(None, node);
+        }
+        for graph in &config.graphs {
+            self.check_graph(graph);
+        }
+    }
+}
+
+// the 0-based line and column of a parse error
+fn line_col(err: &anyhow::Error) -> Option<(usize, usize)> {
+    if let Some(err) = err.downcast_ref::<toml::de::Error>() {
+        err.line_col()
+    } else if let Some(err) = err.downcast_ref::<serde_json::Error>() {
+        Some((err.line().checked_sub(1)?, err.column().checked_sub(1)?))
+    } else if let Some(err) = err.downcast_ref::<serde_yaml::Error>() {
+        err.location()
+            .map(|location| (location.line() - 1, location.column() - 1))
+    } else {
+        None
+    }
+}
+
+/// Collect all diagnostics of a config parsed from `template` without building it
+pub(crate) fn validate(
+    local_key: u64,
+    template: &str,
+    config: Result<presentation::Config>,
+) -> Vec<Diagnostic> {
+    let config = match config {
+        Ok(config) => config,
+        Err(err) => {
+            let span = line_col(&err).map(|(line, column)| Span {
+                line: line + 1,
+                column: column + 1,
+            });
+            // the position of parse errors is relative to the rendered template
+            let templated = Locator::new(template, &[]).templated;
+            let span = span.filter(|_| !templated);
+            return vec![Diagnostic {
+                severity: Severity::Error,
+                kind: DiagnosticKind::Parse,
+                graph: None,
+                message: err.to_string(),
+                span,
+            }];
+        }
+    };
+    let mut validator = Validator {
+        local_key,
+        config: &config,
+        locator: Locator::new(template, &config.graphs),
+        diagnostics: vec![],
+    };
+    validator.check();
+    validator.diagnostics
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Builder;
+
+    fn kinds(diagnostics: &[Diagnostic]) -> Vec<DiagnosticKind> {
+        diagnostics.iter().map(|x| x.kind).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let diagnostics = Builder::default()
+            .template(
+                r#"
+main="test"
+[[graphs]]
+name="test"
+inputs=[{name="inp",cap=1,ports=["t:inp"]}]
+outputs=[{name="out",cap=1,ports=["t:out"]}]
+nodes=[{name="t",ty="Transform"}]
+            "#
+                .to_owned(),
+            )
+            .validate();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_collect_all() {
+        let diagnostics = Builder::default()
+            .template(
+                r#"
+main="test"
+[[graphs]]
+name="test"
+inputs=[{name="inp",cap=0,ports=["t:inp"]}]
+nodes=[
+    {name="t",ty="Transform"},
+    {name="u",ty="UnknownType"},
+]
+connections=[
+    {cap=1,ports=["t:out", "x:inp"]},
+    {cap=1,ports=["t:out", "t:unknown"]},
+    {cap=1,ports=[]},
+]
+            "#
+                .to_owned(),
+            )
+            .validate();
+        let kinds = kinds(&diagnostics);
+        assert!(kinds.contains(&DiagnosticKind::ZeroCapacity));
+        assert!(kinds.contains(&DiagnosticKind::UnknownNodeType));
+        assert!(kinds.contains(&DiagnosticKind::UnknownNode));
+        assert!(kinds.contains(&DiagnosticKind::UnknownPort));
+        assert!(kinds.contains(&DiagnosticKind::UnusedConnection));
+
+        let unknown = diagnostics
+            .iter()
+            .find(|x| x.kind == DiagnosticKind::UnknownNodeType)
+            .unwrap();
+        assert_eq!(
+            unknown.span,
+            Some(Span {
+                line: 8,
+                column: 18
+            })
+        );
+    }
+
+    #[test]
+    fn test_dyn_misuse() {
+        let diagnostics = Builder::default()
+            .template(
+                r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[
+    {name="a",ty="DynOutTransform"},
+    {name="b",ty="Transform"},
+]
+inputs=[{name="inp",cap=1,ports=["a:inp"]}]
+connections=[{cap=1,ports=["a:out", "b:inp"]}]
+            "#
+                .to_owned(),
+            )
+            .validate();
+        assert!(kinds(&diagnostics).contains(&DiagnosticKind::DynPortMisuse));
+    }
+
+    #[test]
+    fn test_parse() {
+        let diagnostics = Builder::default().template("main=".to_owned()).validate();
+        assert_eq!(kinds(&diagnostics), vec![DiagnosticKind::Parse]);
+    }
+
+    #[test]
+    fn test_span() {
+        let diagnostics = Builder::default()
+            .template(
+                r#"
+main="test"
+[[graphs]]
+name="sub"
+nodes=[{name="t",ty="Transform"}]
+inputs=[{name="inp",cap=1,ports=["t:inp"]}]
+outputs=[{name="out",cap=1,ports=["t:out"]}]
+[[graphs]]
+name="test"
+nodes=[{name="t",ty="UnknownType"}, {name="s",ty="sub"}]
+inputs=[{name="inp",cap=1,ports=["s:inp"]}]
+outputs=[{name="out",cap=1,ports=["s:out"]}]
+connections=[{cap=1,ports=["s:out", "t:inp"]}, {cap=0,ports=["s:out", "x:inp"]}]
+            "#
+                .to_owned(),
+            )
+            .validate();
+        let span = |kind| {
+            diagnostics
+                .iter()
+                .find(|x| x.kind == kind)
+                .and_then(|x| x.span)
+        };
+        // names repeated in other graphs or connections are skipped
+        assert_eq!(
+            span(DiagnosticKind::UnknownNodeType),
+            Some(Span {
+                line: 10,
+                column: 21
+            })
+        );
+        assert_eq!(
+            span(DiagnosticKind::ZeroCapacity),
+            Some(Span {
+                line: 13,
+                column: 61
+            })
+        );
+        assert_eq!(
+            span(DiagnosticKind::UnknownNode),
+            Some(Span {
+                line: 13,
+                column: 71
+            })
+        );
+
+        // parse errors of templates are located in the rendered text
+        let diagnostics = Builder::default()
+            .template("{{ a = 1 -}}\nmain=".to_owned())
+            .validate();
+        assert_eq!(kinds(&diagnostics), vec![DiagnosticKind::Parse]);
+        assert_eq!(diagnostics[0].span, None);
+    }
+
+    #[test]
+    fn test_graph_builder() {
+        let sub = || {
+            crate::GraphBuilder::new("sub")
+                .node("t", "Transform", Default::default())
+                .input("inp", "t:inp", 1)
+                .output("out", "t:out", 1)
+        };
+        let diagnostics = Builder::default().graph(sub()).validate();
+        assert!(diagnostics.is_empty());
+
+        // the template refers to the subgraph added by the builder
+        let diagnostics = Builder::default()
+            .template(
+                r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="s",ty="sub"}]
+inputs=[{name="inp",cap=1,ports=["s:inp"]}]
+outputs=[{name="out",cap=1,ports=["s:out"]}]
+            "#
+                .to_owned(),
+            )
+            .graph(sub())
+            .validate();
+        assert!(diagnostics.is_empty());
+    }
+
+    #[test]
+    fn test_format() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("test.json");
+        std::fs::write(
+            &path,
+            r#"{
+    "main": "test",
+    "graphs": [{
+        "name": "test",
+        "nodes": [{"name": "t", "ty": "Transform"}],
+        "inputs": [{"name": "inp", "cap": 1, "ports": ["t:inp"]}],
+        "outputs": [{"name": "out", "cap": 1, "ports": ["t:out"]}]
+    }]
+}"#,
+        )?;
+        let diagnostics = Builder::default().template_file(&path)?.validate();
+        assert!(diagnostics.is_empty());
+
+        std::fs::write(&path, "{\n    \"main\": }")?;
+        let diagnostics = Builder::default().template_file(&path)?.validate();
+        assert_eq!(kinds(&diagnostics), vec![DiagnosticKind::Parse]);
+        assert_eq!(diagnostics[0].span.map(|span| span.line), Some(2));
+        Ok(())
+    }
+}
@@ -55,3 +55,5 @@ pub const EVENT_STOP: &str = "stop";
 pub const CMD_START: &str = "start";
 pub const CMD_STOP: &str = "stop";
 pub const CMD_NOOP: &str = "noop";
+pub const CMD_PAUSE: &str = "pause";
+pub const CMD_RESUME: &str = "resume";
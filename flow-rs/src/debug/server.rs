@@ -112,6 +112,41 @@ async fn dowith_request(req: RequestMessage) -> Result<()> {
                 .ok_or_else(|| anyhow!("feature[{}] not found", req.feature))?;
             (command.stop)(req.seq_id);
         }
+        CMD_PAUSE | CMD_RESUME => {
+            // `graph` pauses all instances of the graph, and `graph`, `node` pauses all instances of the node
+            let graph = req
+                .args
+                .get("graph")
+                .and_then(|x| x.as_str())
+                .ok_or_else(|| anyhow!("graph is required by {}", req.command))?;
+            let node = req.args.get("node").and_then(|x| x.as_str());
+            let key = match node {
+                Some(node) => format!("{}/{}", graph, node),
+                None => graph.to_owned(),
+            };
+            let paused = req.command == CMD_PAUSE;
+            crate::graph::set_paused(&key, paused);
+
+            let mut args = req.args.clone();
+            args.insert("paused".to_owned(), paused.into());
+            let others = match serde_json::to_value(ResponseMessage {
+                success: true,
+                feature: req.feature,
+                seq_id: req.seq_id,
+                command: req.command,
+                args,
+            })? {
+                serde_json::Value::Object(others) => others,
+                _ => unreachable!(),
+            };
+            PORT.0
+                .send(ProtocolMessage {
+                    ty: TYPE_RESPONSE.to_owned(),
+                    others,
+                })
+                .await
+                .ok();
+        }
         _ => unreachable!(),
     }
     Ok(())
@@ -9,11 +9,13 @@
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
 use super::{AnyEnvelope, SealedEnvelope};
+use crate::trace::TraceContext;
 use std::any::Any;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// `EnvelopeInfo` is a type that represents common information for a message
-#[derive(Default, Clone)]
+#[derive(Clone)]
 pub struct EnvelopeInfo {
     /// Sequence id, and could be repeat
     pub partial_id: Option<u64>,
@@ -27,6 +29,47 @@ pub struct EnvelopeInfo {
     pub tag: Option<String>,
     /// Extra data
     pub extra_data: Option<Arc<dyn Any + Send + Sync>>,
+    /// When the envelope is created, which is inherited by repacked envelopes
+    pub created_at: Option<Instant>,
+    /// The envelope is expired after the deadline, see `channel::Expiry`
+    pub deadline: Option<Instant>,
+    /// Envelopes with higher priority are received first from priority channels, see `ChannelStorage::bound_priority`
+    pub priority: i32,
+    /// The trace the envelope belongs to, see `trace::Span::root`
+    pub trace: Option<TraceContext>,
+}
+
+impl Default for EnvelopeInfo {
+    fn default() -> Self {
+        EnvelopeInfo {
+            partial_id: None,
+            from_addr: None,
+            to_addr: None,
+            transfer_addr: None,
+            tag: None,
+            extra_data: None,
+            created_at: Some(Instant::now()),
+            deadline: None,
+            priority: 0,
+            trace: None,
+        }
+    }
+}
+
+impl EnvelopeInfo {
+    /// Set the deadline to `timeout` later
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some(Instant::now() + timeout);
+    }
+    /// Time elapsed since the envelope is created
+    pub fn age(&self) -> Option<Duration> {
+        self.created_at.map(|created_at| created_at.elapsed())
+    }
+    /// Return true if the deadline is passed
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .map_or(false, |deadline| deadline <= Instant::now())
+    }
 }
 /// `Envelope<M>` is a type that contains a info:`EnvelopeInfo` and a message:`M`
 ///
@@ -8,14 +8,16 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
-use crate::channel::ChannelStorage;
+use crate::channel::{ChannelStats, ChannelStorage, ExpiredAction, Expiry};
 use crate::config::interlayer as config;
 use anyhow::Result;
+use std::sync::Arc;
 
 #[derive(Clone)]
 pub struct AnyChannel {
     storage: Option<ChannelStorage>,
     info: config::Connection,
+    stats: Arc<ChannelStats>,
 }
 
 impl AnyChannel {
@@ -23,15 +25,40 @@ impl AnyChannel {
         Ok(AnyChannel {
             storage: None,
             info: cfg.clone(),
+            stats: Default::default(),
         })
     }
 
+    pub fn with_stats(mut self, stats: Arc<ChannelStats>) -> AnyChannel {
+        self.stats = stats;
+        self
+    }
+
     pub fn set(&mut self, storage: ChannelStorage) {
         self.storage = Some(storage)
     }
 
     pub fn make(&self) -> ChannelStorage {
-        ChannelStorage::bound(self.info.cap)
+        self.expire(
+            if self.info.priority {
+                ChannelStorage::bound_priority(self.info.cap)
+            } else {
+                ChannelStorage::bound(self.info.cap)
+            }
+            .with_stats(self.stats.clone())
+            .with_policy(self.info.policy),
+        )
+    }
+
+    /// Apply the ttl of the connection to the channel, whose expired envelopes are dropped
+    pub fn expire(&self, storage: ChannelStorage) -> ChannelStorage {
+        match self.info.ttl {
+            Some(ttl) => storage.with_expiry(Expiry {
+                ttl: Some(ttl),
+                action: ExpiredAction::Drop,
+            }),
+            None => storage,
+        }
     }
 
     pub fn get(&self) -> &ChannelStorage {
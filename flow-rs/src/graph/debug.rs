@@ -19,11 +19,18 @@ struct NodeQps {
     name: String,
     qps: HashMap<String, (usize, usize)>, // size, qps
     is_block: bool,
+    is_paused: bool,
 }
 
 impl Graph {
     pub(super) fn dmon(&self) -> JoinHandle<anyhow::Result<()>> {
         let conns: Vec<_> = self.conns.values().cloned().collect();
+        let pauses: HashMap<_, _> = self
+            .nodes
+            .keys()
+            .map(|name| (name.clone(), self.pause_token(name)))
+            .collect();
+        let is_paused = move |name: &str| pauses.get(name).map_or(false, |p| p.is_paused());
         let ctx = self.ctx.clone();
         let mut first = true; // drop qps result fetched first
         crate::rt::task::spawn(async move {
@@ -64,6 +71,7 @@ impl Graph {
                                     name: tx.node_name.clone(),
                                     qps: Default::default(),
                                     is_block: false,
+                                    is_paused: is_paused(&tx.node_name),
                                 });
                                 qps.qps
                                     .insert(tx.port_name.clone(), (size, tx_qps as usize));
@@ -73,6 +81,7 @@ impl Graph {
                                     name: rx.node_name.clone(),
                                     qps: Default::default(),
                                     is_block: false,
+                                    is_paused: is_paused(&rx.node_name),
                                 });
                                 qps.qps
                                     .insert(rx.port_name.clone(), (size, rx_qps as usize));
@@ -0,0 +1,169 @@
+/**
+ * \file flow-rs/src/graph/instances.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::shutdown::Running;
+use super::{Context, Graph, MainGraph};
+use crate::channel::ChannelStorage;
+use crate::registry::Collect;
+use crate::resource::ResourceCollection;
+use crate::rt::task::JoinHandle;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// A live instance of a dynamic subgraph, see `MainGraph::dyn_instances`
+#[derive(Clone, Debug)]
+pub struct DynInstance {
+    /// The key the instance is created with by `DynPorts::create`, e.g. the stream id
+    pub key: u64,
+    /// The dynamic subgraph
+    pub topic: String,
+    pub created: SystemTime,
+    /// The number of envelopes sent into inputs of the instance
+    pub received: u64,
+    /// The number of envelopes sent out from outputs of the instance
+    pub sent: u64,
+}
+
+struct Entry {
+    key: u64,
+    created: SystemTime,
+    inputs: Vec<ChannelStorage>,
+    outputs: Vec<ChannelStorage>,
+    running: Arc<Running>,
+    ctx: Context,
+}
+
+impl Entry {
+    fn kill(&self) {
+        for input in &self.inputs {
+            input.close();
+        }
+        self.running.cancel();
+        self.ctx.close();
+    }
+}
+
+/// Live instances of a dynamic subgraph in a graph load
+#[derive(Default)]
+pub(crate) struct DynInstances {
+    next_id: AtomicU64,
+    entries: Mutex<HashMap<u64, Entry>>,
+}
+crate::collect!(String, DynInstances);
+
+impl DynInstances {
+    fn get(local_key: u64, topic: &str) -> Arc<DynInstances> {
+        DynInstances::registry_local()
+            .get(local_key)
+            .get_or_insert_with(topic.to_owned(), Default::default)
+    }
+
+    fn list(&self, topic: &str) -> Vec<DynInstance> {
+        let sum =
+            |chans: &[ChannelStorage]| -> u64 { chans.iter().map(|c| c.stats().sent()).sum() };
+        self.entries
+            .lock()
+            .unwrap()
+            .values()
+            .map(|entry| DynInstance {
+                key: entry.key,
+                topic: topic.to_owned(),
+                created: entry.created,
+                received: sum(&entry.inputs),
+                sent: sum(&entry.outputs),
+            })
+            .collect()
+    }
+
+    fn kill(&self, key: u64) -> usize {
+        let entries = self.entries.lock().unwrap();
+        let mut n = 0;
+        for entry in entries.values().filter(|entry| entry.key == key) {
+            entry.kill();
+            n += 1;
+        }
+        n
+    }
+}
+
+impl Graph {
+    /// Start the graph as the instance `key` of the dynamic subgraph `topic`, which is listed until it is finished
+    pub(crate) fn start_dyn(
+        &mut self,
+        topic: &str,
+        key: u64,
+        resource: ResourceCollection,
+    ) -> JoinHandle<Result<()>> {
+        let instances = DynInstances::get(self.ctx.local_key, topic);
+        let ports = |names: &[String]| -> Vec<_> {
+            names
+                .iter()
+                .map(|name| self.conns[name].get().clone())
+                .collect()
+        };
+        let entry = Entry {
+            key,
+            created: SystemTime::now(),
+            inputs: ports(&self.inputs),
+            outputs: ports(&self.outputs),
+            running: self.running.clone(),
+            ctx: self.ctx.clone(),
+        };
+        let id = instances.next_id.fetch_add(1, Ordering::Relaxed);
+        instances.entries.lock().unwrap().insert(id, entry);
+        let handle = self.start(Some(resource));
+        crate::rt::task::spawn(async move {
+            let ret = handle.await;
+            instances.entries.lock().unwrap().remove(&id);
+            ret
+        })
+    }
+}
+
+impl MainGraph {
+    fn dyn_topics(&self) -> Result<Vec<String>> {
+        if self.finish.is_finalized() {
+            return Err(anyhow!("graph is finished"));
+        }
+        let mut topics = DynInstances::registry_local()
+            .get(self.global_ctx.local_key)
+            .keys();
+        topics.sort();
+        Ok(topics)
+    }
+
+    /// Live instances of all dynamic subgraphs, sorted by topic and creation time
+    pub fn dyn_instances(&self) -> Result<Vec<DynInstance>> {
+        let local_key = self.global_ctx.local_key;
+        let mut instances = vec![];
+        for topic in self.dyn_topics()? {
+            let mut list = DynInstances::get(local_key, &topic).list(&topic);
+            list.sort_by_key(|instance| (instance.created, instance.key));
+            instances.append(&mut list);
+        }
+        Ok(instances)
+    }
+
+    /// Terminate the instances of the dynamic subgraph `topic` created with `key`, and return how many are terminated.
+    ///
+    /// Inputs of the instances are closed and their nodes are cancelled without draining, so the node which
+    /// created them sees the ports closed.
+    pub fn kill_dyn(&self, topic: &str, key: u64) -> Result<usize> {
+        let topics = self.dyn_topics()?;
+        if !topics.iter().any(|t| t == topic) && !self.config.graphs.iter().any(|g| g.name == topic)
+        {
+            return Err(anyhow!("dynamic subgraph {} not found", topic));
+        }
+        Ok(DynInstances::get(self.global_ctx.local_key, topic).kill(key))
+    }
+}
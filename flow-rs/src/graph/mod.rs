@@ -12,20 +12,41 @@ mod channel;
 mod context;
 #[cfg(feature = "debug")]
 mod debug;
+mod instances;
 mod node;
+mod pause;
+mod reload;
+mod shutdown;
+mod snapshot;
 mod subgraph;
+mod supervisor;
 
 use crate::broker::Broker;
+use crate::channel::{ExpiredAction, Expiry};
+use crate::config::format::Format;
 use crate::config::interlayer as config;
+use crate::config::presentation::RestartPolicy;
 use crate::config::table::merge_table;
+use crate::node::Gate;
 use crate::prelude::*;
+use crate::record::Recorder;
 use crate::rt::task::JoinHandle;
 use anyhow::{anyhow, Result};
 use channel::*;
 pub use context::*;
-use futures_util::{pin_mut, select, FutureExt};
+use futures_util::stream::FuturesUnordered;
+use futures_util::{pin_mut, select_biased, FutureExt, StreamExt};
+pub use instances::DynInstance;
 use node::AnyNode;
+#[cfg(feature = "debug")]
+pub(crate) use pause::set_paused;
+pub use reload::ReloadReport;
+pub(crate) use shutdown::Running;
+pub use shutdown::{ShutdownHandle, ShutdownReport};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use supervisor::Spawn;
 use toml::value::Table;
 
 pub(crate) struct GraphSlice {
@@ -34,11 +55,52 @@ pub(crate) struct GraphSlice {
 }
 crate::collect!(String, GraphSlice);
 
+/// Register the info of a graph, so that the graph can be referenced before it is translated
+pub(crate) fn register_placeholder(local_key: u64, cfg: &crate::config::presentation::Graph) {
+    let info = NodeInfo {
+        inputs: cfg.inputs.iter().map(|conn| conn.name.clone()).collect(),
+        outputs: cfg.outputs.iter().map(|conn| conn.name.clone()).collect(),
+    };
+    GraphSlice::registry_local().get(local_key).insert(
+        cfg.name.clone(),
+        GraphSlice {
+            cons: Box::new(move |_, _| Err(anyhow!("graph is not loaded"))),
+            info,
+        },
+    );
+}
+
+/// Register the constructor of a graph, which is used by all instances created after
+pub(crate) fn register(local_key: u64, cfg: &config::Graph) {
+    let cfg = cfg.clone();
+    let info = NodeInfo {
+        inputs: cfg.inputs.clone(),
+        outputs: cfg.outputs.clone(),
+    };
+    GraphSlice::registry_local().get(local_key).insert(
+        cfg.name.clone(),
+        GraphSlice {
+            cons: Box::new(move |name, table| {
+                Graph::load(context(name, cfg.name.clone(), local_key), &cfg, table)
+            }),
+            info,
+        },
+    );
+}
+
 /// Represents a graph with nodes and connections, which implement `Node` and `Actor`.
 pub struct MainGraph {
     graph: Graph,
     global_resources: ResourceCollection,
     global_ctx: Context,
+    config: config::Config,
+    // where the template is read from and its format, which are used to parse the config again in `reload`
+    source: (Option<PathBuf>, Format),
+    recorder: Option<Arc<Recorder>>,
+    snapshot: Mutex<snapshot::Snapshot>,
+    finish: Arc<shutdown::Finish>,
+    // shared nodes, which are cancelled along with the graph by `shutdown`
+    shared: Arc<Running>,
 }
 
 impl MainGraph {
@@ -46,6 +108,8 @@ impl MainGraph {
         mut graph: Graph,
         global_ctx: Context,
         global_resources: ResourceCollection,
+        config: config::Config,
+        shared: Arc<Running>,
     ) -> MainGraph {
         let mut v = vec![];
         for name in &graph.inputs {
@@ -62,15 +126,34 @@ impl MainGraph {
             graph,
             global_ctx,
             global_resources,
+            config,
+            source: Default::default(),
+            recorder: None,
+            snapshot: Default::default(),
+            finish: Default::default(),
+            shared,
         }
     }
     /// Get an input port from the graph by name
     pub fn input(&self, name: &str) -> Option<Sender> {
-        self.graph.conns.get(name).map(|x| x.get().sender())
+        let sender = self.graph.conns.get(name)?.get().sender();
+        Some(match &self.recorder {
+            Some(recorder) => sender.with_observer(recorder.observer(name)),
+            None => sender,
+        })
     }
     /// Get an output port from the graph by name
     pub fn output(&self, name: &str) -> Option<Receiver> {
-        self.graph.conns.get(name).map(|x| x.get().receiver())
+        let receiver = self.graph.conns.get(name)?.get().receiver();
+        Some(match &self.recorder {
+            Some(recorder) => receiver.with_observer(recorder.observer(name)),
+            None => receiver,
+        })
+    }
+    /// Record envelopes sent into ports got by `input`, and received from ports got by `output` after,
+    /// see `record::Recording` and `record::Replayer`
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(Arc::new(recorder));
     }
     /// Get inputs names
     pub fn input_names(&self) -> Vec<&str> {
@@ -90,24 +173,35 @@ impl MainGraph {
             .graph
             .start(Some(std::mem::take(&mut self.global_resources)));
         let global_ctx = self.global_ctx.clone();
+        let finish = self.finish.clone();
+        finish.start();
         crate::rt::task::spawn(async move {
-            handle.await?;
-            SharedProxy::registry_local()
-                .get(global_ctx.local_key)
-                .for_each(|proxy| proxy.close());
-            global_ctx.close();
+            let ret: Result<()> = async {
+                handle.await?;
+                // already finalized by a timed out `shutdown`
+                if finish.is_finalized() {
+                    return Ok(());
+                }
+                SharedProxy::registry_local()
+                    .get(global_ctx.local_key)
+                    .for_each(|proxy| proxy.close());
+                global_ctx.close();
 
-            let handles = crate::node::SharedHandle::registry_local()
-                .get(global_ctx.local_key)
-                .to_vec();
-            for handle in handles {
-                let handle =
-                    std::sync::Arc::try_unwrap(handle).unwrap_or_else(|_| panic!("internal error"));
-                handle.0.await?;
+                let handles = crate::node::SharedHandle::registry_local()
+                    .get(global_ctx.local_key)
+                    .to_vec();
+                for handle in handles {
+                    let handle = std::sync::Arc::try_unwrap(handle)
+                        .unwrap_or_else(|_| panic!("internal error"));
+                    handle.0.await?;
+                }
+                // clear graph local resources
+                finish.finalize(global_ctx.local_key);
+                Ok(())
             }
-            // clear graph local resources
-            finalize(global_ctx.local_key);
-            Ok(())
+            .await;
+            finish.notify();
+            ret
         })
     }
 }
@@ -122,6 +216,10 @@ pub(crate) struct Graph {
     ctx: Context,
     resources: UniqueResourceCollection,
     is_shared: bool,
+    stops: HashMap<String, Vec<StopToken>>,
+    spawner: Option<crate::rt::channel::Sender<Spawn>>,
+    running: Arc<shutdown::Running>,
+    pause: PauseToken,
 }
 
 impl Graph {
@@ -130,6 +228,8 @@ impl Graph {
         let mut conns = HashMap::new();
         let mut broker = Broker::new();
         let mut shares = HashMap::new();
+        // channels created before their connections, as they are diverted into by others
+        let mut targets = HashMap::new();
 
         // global
         let resources = UniqueResourceCollection::new(ctx.local_key, ctx.id, &config.resources);
@@ -161,12 +261,24 @@ impl Graph {
                 }
 
                 if dyn_rxn > 0 || dyn_txn > 0 {
+                    if cfg.ttl.is_some() || cfg.expired.is_some() {
+                        return Err(anyhow!("ttl of dyn connections is not supported"));
+                    }
+                    // shared by all instances of the node creating dynamic subgraphs
+                    let admission = cfg
+                        .max_instances
+                        .map(|max| Arc::new(crate::node::Admission::new(max, cfg.admission)));
                     let subgraph = cfg
                         .rx
                         .iter()
                         .chain(cfg.tx.iter())
                         .find(|p| !p.is_dyn())
                         .unwrap();
+                    let pool = cfg.pool_size.filter(|&size| size > 0).map(|size| {
+                        let subgraph_args = &config.nodes[&subgraph.node_name].entity.args;
+                        let args = merge_table(args.clone(), subgraph_args.clone());
+                        Arc::new(crate::node::Pool::new(size, args))
+                    });
                     for port in cfg.rx.iter().chain(cfg.tx.iter()) {
                         if port.is_dyn() {
                             // add dyn subgraph resources into trigger node
@@ -175,6 +287,25 @@ impl Graph {
                             let mut res = subgraph_cfg.res.clone();
                             let nodes = nodes.get_mut(&port.node_name).unwrap();
                             let info = nodes.info_mut();
+                            if info.restart.policy != RestartPolicy::Never {
+                                return Err(anyhow!(
+                                    "restart policy is not supported by node {} with dyn ports",
+                                    port.node_name
+                                ));
+                            }
+                            // the node would build dynamic subgraphs with the registries of its plugin, which miss the graphs
+                            #[cfg(feature = "dylib")]
+                            if config.nodes[&port.node_name]
+                                .entity
+                                .ty
+                                .iter()
+                                .any(|ty| crate::loader::dylib::is_dylib_node(ctx.local_key, ty))
+                            {
+                                return Err(anyhow!(
+                                    "dyn ports are not supported by node {} loaded from a shared library",
+                                    port.node_name
+                                ));
+                            }
                             info.res.append(&mut res);
                             for node in nodes.get_mut().iter_mut() {
                                 let mut clients = vec![];
@@ -187,6 +318,11 @@ impl Graph {
                                         local_key: ctx.local_key,
                                         target: subgraph.port_name.clone(),
                                         cap: cfg.cap,
+                                        policy: cfg.policy,
+                                        priority: cfg.priority,
+                                        idle_timeout: cfg.idle_timeout,
+                                        admission: admission.clone(),
+                                        pool: pool.clone(),
                                         brokers: clients,
                                         args: merge_table(args.clone(), subgraph_args.clone()),
                                     },
@@ -195,29 +331,63 @@ impl Graph {
                         }
                     }
                 } else {
-                    let mut channel = AnyChannel::new(cfg)?;
-                    channel.set(channel.make());
+                    if cfg.idle_timeout.is_some() {
+                        return Err(anyhow!(
+                            "idle timeout of static connection {} is not supported",
+                            k
+                        ));
+                    }
+                    if cfg.max_instances.is_some() {
+                        return Err(anyhow!(
+                            "max instances of static connection {} is not supported",
+                            k
+                        ));
+                    }
+                    if cfg.pool_size.is_some() {
+                        return Err(anyhow!("pool of static connection {} is not supported", k));
+                    }
+                    let mut channel = AnyChannel::new(cfg)?
+                        .with_stats(crate::metrics::channel_stats(&ctx.ty, k, cfg));
+                    let mut storage = targets.remove(k).unwrap_or_else(|| channel.make());
+                    if let Some(expired) = &cfg.expired {
+                        let target = expired_target(config, expired)?;
+                        if target == *k {
+                            return Err(anyhow!("connection {} is diverted into itself", k));
+                        }
+                        let target = match conns.get(&target) {
+                            Some(target) => target.get().clone(),
+                            None => {
+                                let target_cfg = &config.connections[&target];
+                                let storage = AnyChannel::new(target_cfg)?
+                                    .with_stats(crate::metrics::channel_stats(
+                                        &ctx.ty, &target, target_cfg,
+                                    ))
+                                    .make();
+                                targets.entry(target).or_insert(storage).clone()
+                            }
+                        };
+                        storage = storage.with_expiry(Expiry {
+                            ttl: cfg.ttl,
+                            action: ExpiredAction::Divert(target),
+                        });
+                    }
+                    channel.set(storage);
                     let info = channel.info();
                     for port in info.rx.iter().chain(info.tx.iter()) {
                         if let Some(nodes) = nodes.get_mut(&port.node_name) {
-                            for node in nodes.get_mut().iter_mut() {
-                                node.set_port(&port.port_name, port.port_tag, channel.get());
-                            }
+                            nodes.set_port(&port.port_name, port.port_tag, channel.get());
                         } else {
                             if config.is_shared {
                                 return Err(anyhow!("nested shared graph is not support"));
                             }
-                            let shared_proxy =
-                                shares.entry(port.node_name.clone()).or_insert_with(|| {
-                                    SharedProxy::registry_local()
-                                        .get(ctx.local_key)
-                                        .get(&port.node_name)
-                                        .unwrap_or_else(|| {
-                                            panic!("unexpected node {}", port.node_name)
-                                        })
-                                        .as_ref()
-                                        .clone()
-                                });
+                            if !shares.contains_key(&port.node_name) {
+                                let proxy = SharedProxy::registry_local()
+                                    .get(ctx.local_key)
+                                    .get(&port.node_name)
+                                    .ok_or_else(|| anyhow!("unexpected node {}", port.node_name))?;
+                                shares.insert(port.node_name.clone(), proxy.as_ref().clone());
+                            }
+                            let shared_proxy = shares.get_mut(&port.node_name).unwrap();
                             shared_proxy.set_port(&port.port_name, channel.get());
                         }
                     }
@@ -227,6 +397,11 @@ impl Graph {
                 if dyn_rxn > 0 || dyn_txn > 0 {
                     return Err(anyhow!("dyn inputs or outputs of graph"));
                 }
+                if cfg.expired.is_some() {
+                    return Err(anyhow!(
+                        "expired port of inputs or outputs of graph is not supported"
+                    ));
+                }
                 for port in cfg.rx.iter().chain(cfg.tx.iter()) {
                     if let Some(node) = config.nodes.get(&port.node_name) {
                         if node.is_dyn {
@@ -236,7 +411,8 @@ impl Graph {
                         }
                     }
                 }
-                let channel = AnyChannel::new(cfg)?;
+                let channel = AnyChannel::new(cfg)?
+                    .with_stats(crate::metrics::channel_stats(&ctx.ty, k, cfg));
                 conns.insert(k.clone(), channel);
             }
         }
@@ -251,10 +427,17 @@ impl Graph {
             outputs: config.outputs.clone(),
             shares,
             is_shared: config.is_shared,
+            stops: Default::default(),
+            spawner: None,
+            running: Default::default(),
+            pause: Default::default(),
         })
     }
 
     pub(crate) fn start(&mut self, resource: Option<ResourceCollection>) -> JoinHandle<Result<()>> {
+        let local_key = self.ctx.local_key;
+        // an instance of a subgraph is also paused with the node it is started as
+        self.pause = PauseToken::current().with(Gate::get(local_key, &self.ctx.ty));
         let ext_resource = resource.unwrap_or_else(|| {
             UniqueResourceCollection::new(self.ctx.local_key, self.ctx.id, &Default::default())
                 .take_into_arc()
@@ -264,22 +447,25 @@ impl Graph {
         for (_, shared) in shares {
             shared.build();
         }
-        let mut handles = vec![self.broker.run()];
+        let mut tasks: FuturesUnordered<_> = vec![self.broker.run()].into_iter().collect();
         let mut alone_tasks = vec![
             #[cfg(feature = "debug")]
             self.dmon(),
         ];
-        let nodes: Vec<_> = self
-            .nodes
-            .values_mut()
-            .map(|node| {
-                let is_alone = node.info().inputs.is_empty() && node.info().outputs.is_empty();
-                let res_names: Vec<_> = node.info().res.to_vec();
-                (is_alone, res_names, node.get_into())
-            })
-            .collect();
+        let mut spawns = vec![];
+        let names: Vec<_> = self.nodes.keys().cloned().collect();
+        for name in names {
+            let pause = self.pause_token(&name);
+            let node = self.nodes.get_mut(&name).unwrap();
+            let (tokens, mut node_spawns) = Spawn::from_node(local_key, node, pause);
+            self.stops.insert(name, tokens);
+            spawns.append(&mut node_spawns);
+        }
+        let (spawner, spawned) = crate::rt::channel::unbounded();
+        self.spawner = Some(spawner);
 
         let context = self.ctx.clone();
+        let running = self.running.clone();
         let inputs: Vec<_> = self
             .inputs
             .iter()
@@ -298,32 +484,27 @@ impl Graph {
 
         let handle = crate::rt::task::spawn(async move {
             let res = ext_resource.chain(in_resource).await;
-            for (is_alone, res_names, nodes) in nodes {
-                for node in nodes {
-                    let res = res.filter(
-                        res_names
-                            .iter()
-                            .map(|x| x.as_str())
-                            .collect::<Vec<_>>()
-                            .as_slice(),
-                    );
-                    if is_alone {
-                        alone_tasks.push(node.start(context.clone(), res));
-                    } else {
-                        handles.push(node.start(context.clone(), res));
-                    }
+            for spawn in spawns {
+                let is_alone = spawn.is_alone;
+                let name = spawn.name.clone();
+                let token = spawn.token.clone();
+                let handle = running.track(name, token, spawn.start(&context, &res));
+                if is_alone {
+                    alone_tasks.push(handle);
+                } else {
+                    tasks.push(handle);
                 }
             }
 
             let wait_ctx = context.wait().fuse();
-            let wait_tasks = futures_util::future::try_join_all(handles).fuse();
+            let mut spawned = spawned.fuse();
             let mut wait_i =
                 futures_util::future::join_all(inputs.iter().map(|conn| conn.wait_rx_closed()))
                     .fuse();
             let mut wait_o =
                 futures_util::future::join_all(outputs.iter().map(|conn| conn.wait_tx_closed()))
                     .fuse();
-            pin_mut!(wait_ctx, wait_tasks);
+            pin_mut!(wait_ctx);
 
             let mut state = 0;
             let mut cb = || {
@@ -335,19 +516,36 @@ impl Graph {
 
             let mut ret = Ok(());
             loop {
-                select! {
+                // spawns are polled first, so the replacements of reloaded nodes are
+                // started before the replaced ones are finished
+                select_biased! {
+                    spawn = spawned.select_next_some() => {
+                        let is_alone = spawn.is_alone;
+                        let name = spawn.name.clone();
+                        let token = spawn.token.clone();
+                        let handle = running.track(name, token, spawn.start(&context, &res));
+                        if is_alone {
+                            alone_tasks.push(handle);
+                        } else {
+                            tasks.push(handle);
+                        }
+                    }
                     _ = wait_ctx => {
                         for input in &inputs {
                             input.close();
                         }
+                        spawned.get_ref().close();
                     }
-                    task_ret = wait_tasks => {
+                    task_ret = tasks.next() => {
                         match task_ret {
-                            Ok(_) => cb(),
-                            err => {
-                                ret = err.map(|_| ());
-                                context.close()
+                            Some(Ok(_)) => (),
+                            Some(err) => {
+                                ret = err;
+                                context.close();
+                                // the remaining tasks are detached, as they may never finish
+                                tasks = FuturesUnordered::new();
                             },
+                            None => cb(),
                         }
                     },
                     _ = wait_o => {
@@ -373,3 +571,29 @@ impl Graph {
         handle
     }
 }
+
+// find the static connection received by the port `expired`
+fn expired_target(config: &config::Graph, expired: &str) -> Result<String> {
+    let ((node, port), _) = config::Port::parse(expired)?;
+    config
+        .connections
+        .iter()
+        .filter(|(name, _)| !config.inputs.contains(name) && !config.outputs.contains(name))
+        .filter(|(_, conn)| conn.rx.iter().chain(conn.tx.iter()).all(|p| !p.is_dyn()))
+        .find(|(_, conn)| {
+            conn.rx.iter().any(|p| {
+                p.node_name == node
+                    && crate::config::MAPPING
+                        .iter()
+                        .any(|utility| (utility.mapping)(port) == p.port_name)
+            })
+        })
+        .map(|(name, _)| name.clone())
+        .ok_or_else(|| {
+            anyhow!(
+                "expired port {} is not an input port in graph {}",
+                expired,
+                config.name
+            )
+        })
+}
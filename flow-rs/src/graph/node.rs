@@ -8,27 +8,86 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
+use crate::channel::ChannelStorage;
 use crate::config::interlayer as config;
+use crate::config::presentation::BalancePolicy;
 use crate::config::table::merge_table;
 use crate::node::Actor;
 use anyhow::Result;
 use toml::value::Table;
 
+pub type PortRecord = (String, Option<u64>, ChannelStorage);
+
 pub struct AnyNode {
     nodes: Vec<Box<dyn Actor>>,
     #[allow(dead_code)]
     info: config::Node,
+    ports: Vec<PortRecord>,
+    // the ports of each instance, which have their own queues if the node is balanced
+    instance_ports: Vec<Vec<PortRecord>>,
+    dispatchers: Vec<Box<dyn Actor>>,
 }
 
 impl AnyNode {
     pub fn new(local_key: u64, mut info: config::Node, extra_args: Table) -> Result<AnyNode> {
         info.entity.args = merge_table(extra_args, info.entity.args);
+        let nodes = crate::node::load_static(local_key, &info)?;
         Ok(AnyNode {
-            nodes: crate::node::load_static(local_key, &info)?,
+            instance_ports: vec![vec![]; nodes.len()],
+            nodes,
             info,
+            ports: vec![],
+            dispatchers: vec![],
         })
     }
 
+    /// Set the port of all instances, and record it for rewiring restarted instances.
+    ///
+    /// If the node is balanced, each instance receives from its own replica of the queue instead, which
+    /// envelopes of an input are dispatched into.
+    pub fn set_port(&mut self, port_name: &str, tag: Option<u64>, channel: &ChannelStorage) {
+        let balanced = self.info.balance != BalancePolicy::SharedQueue
+            && self.nodes.len() > 1
+            && self.info.inputs.iter().any(|input| input == port_name);
+        if balanced {
+            let replicas: Vec<_> = self.nodes.iter().map(|_| channel.replica()).collect();
+            self.dispatchers.push(crate::node::dispatcher(
+                self.info.balance,
+                channel,
+                &replicas,
+            ));
+            for ((node, ports), replica) in self
+                .nodes
+                .iter_mut()
+                .zip(self.instance_ports.iter_mut())
+                .zip(replicas)
+            {
+                node.set_port(port_name, tag, &replica);
+                ports.push((port_name.to_owned(), tag, replica));
+            }
+        } else {
+            for (node, ports) in self.nodes.iter_mut().zip(self.instance_ports.iter_mut()) {
+                node.set_port(port_name, tag, channel);
+                ports.push((port_name.to_owned(), tag, channel.clone()));
+            }
+        }
+        self.ports
+            .push((port_name.to_owned(), tag, channel.clone()));
+    }
+
+    pub fn ports(&self) -> &Vec<PortRecord> {
+        &self.ports
+    }
+
+    /// The ports of the `i`th instance
+    pub fn instance_ports(&self, i: usize) -> &Vec<PortRecord> {
+        &self.instance_ports[i]
+    }
+
+    pub fn take_dispatchers(&mut self) -> Vec<Box<dyn Actor>> {
+        std::mem::take(&mut self.dispatchers)
+    }
+
     #[allow(dead_code)]
     pub fn first(&self) -> &dyn Actor {
         self.nodes.first().map(|n| n.as_ref()).unwrap()
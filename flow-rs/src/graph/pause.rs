@@ -0,0 +1,81 @@
+/**
+ * \file flow-rs/src/graph/pause.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::{Graph, MainGraph};
+use crate::node::{Gate, PauseToken};
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+
+impl Graph {
+    /// The pause token shared by all instances of the node `name`
+    pub(super) fn pause_token(&self, name: &str) -> PauseToken {
+        self.pause.with(Gate::get(
+            self.ctx.local_key,
+            &format!("{}/{}", self.ctx.ty, name),
+        ))
+    }
+}
+
+impl MainGraph {
+    // a node of the main graph, or a subgraph
+    fn gate(&self, name: &str) -> Result<Arc<Gate>> {
+        if self.finish.is_finalized() {
+            return Err(anyhow!("graph is finished"));
+        }
+        let local_key = self.global_ctx.local_key;
+        let main = &self.config.main;
+        let is_node = self
+            .config
+            .graphs
+            .iter()
+            .any(|g| &g.name == main && g.nodes.contains_key(name));
+        if is_node {
+            Ok(Gate::get(local_key, &format!("{}/{}", main, name)))
+        } else if name != main && self.config.graphs.iter().any(|g| g.name == name) {
+            Ok(Gate::get(local_key, name))
+        } else {
+            Err(anyhow!("node or subgraph {} not found", name))
+        }
+    }
+
+    /// Hold the node `name` of the main graph before its next exec, or all nodes of all instances of the subgraph `name`,
+    /// including dynamic ones.
+    ///
+    /// The paused nodes stop receiving, so their upstream is blocked by backpressure or dropped by the overflow policy.
+    /// A paused graph can not finish until it is resumed.
+    pub fn pause(&self, name: &str) -> Result<()> {
+        self.gate(name)?.pause();
+        Ok(())
+    }
+
+    /// Resume the node or the subgraph `name` paused by `pause`
+    pub fn resume(&self, name: &str) -> Result<()> {
+        self.gate(name)?.resume();
+        Ok(())
+    }
+
+    pub fn is_paused(&self, name: &str) -> Result<bool> {
+        Ok(self.gate(name)?.is_paused())
+    }
+}
+
+/// Pause or resume the gate `key` of all graph loads, which is used by the debugger
+#[cfg(feature = "debug")]
+pub(crate) fn set_paused(key: &str, paused: bool) {
+    use crate::registry::Collect;
+    for local_key in Gate::registry_local().local_keys() {
+        let gate = Gate::get(local_key, key);
+        if paused {
+            gate.pause();
+        } else {
+            gate.resume();
+        }
+    }
+}
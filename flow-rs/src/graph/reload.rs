@@ -0,0 +1,334 @@
+/**
+ * \file flow-rs/src/graph/reload.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::node::AnyNode;
+use super::supervisor::Spawn;
+use super::{Graph, GraphSlice, MainGraph};
+use crate::config::format::Format;
+use crate::config::interlayer as config;
+use crate::config::parser::Parser;
+use crate::config::presentation;
+use crate::node::NodeSlice;
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeSet, HashMap};
+use std::path::PathBuf;
+
+/// What is changed by `MainGraph::reload`
+#[derive(Clone, Debug, Default)]
+pub struct ReloadReport {
+    /// Nodes of the main graph, whose instances are drained and replaced
+    pub restarted: Vec<String>,
+    /// Subgraphs, whose new definitions are used by instances created after reloading
+    pub updated_graphs: Vec<String>,
+}
+
+impl MainGraph {
+    pub(crate) fn set_source(&mut self, path: Option<PathBuf>, format: Format) {
+        self.source = (path, format);
+    }
+
+    /// Reload the graph from an updated config, and replace only nodes and subgraphs that changed.
+    ///
+    /// Shared nodes, global resources and the connections of the main graph are kept alive, so
+    /// a config changing them is refused, and the graph is left untouched. The template is parsed in the format of
+    /// the one the graph is built from, and relative includes are resolved against the path it is read from.
+    pub fn reload(&mut self, template: &str, dynamic: &str) -> Result<ReloadReport> {
+        let local_key = self.global_ctx.local_key;
+        let (path, format) = &self.source;
+        let config: presentation::Config =
+            Parser::from_str_in(template, path.as_deref(), Some(dynamic), *format)?;
+        for cfg in &config.graphs {
+            // keep the constructors of existing graphs until the new config is checked
+            if GraphSlice::registry_local()
+                .get(local_key)
+                .get(&cfg.name)
+                .is_none()
+            {
+                super::register_placeholder(local_key, cfg);
+            }
+        }
+        let config = crate::config::translate_config(local_key, config)?;
+        let report = diff(local_key, &self.config, &config)?;
+
+        let main = config
+            .graphs
+            .iter()
+            .find(|g| g.name == config.main)
+            .unwrap();
+        let mut nodes = vec![];
+        for name in &report.restarted {
+            nodes.push(self.graph.rebuild_node(name, main.nodes[name].clone())?);
+        }
+        crate::config::graphviz::dump(&config)?;
+
+        for cfg in &config.graphs {
+            if self.config.graphs.iter().all(|g| g != cfg) {
+                super::register(local_key, cfg);
+            }
+        }
+        for node in nodes {
+            self.graph.replace_node(node);
+        }
+        self.config = config;
+        Ok(report)
+    }
+}
+
+impl Graph {
+    /// Create the instances of the node with a new config, and wire them to the channels of the old ones
+    fn rebuild_node(&self, name: &str, cfg: config::Node) -> Result<AnyNode> {
+        let old = self
+            .nodes
+            .get(name)
+            .ok_or_else(|| anyhow!("unexpected node {}", name))?;
+        let mut node = AnyNode::new(self.ctx.local_key, cfg, Default::default())?;
+        for (port_name, tag, channel) in old.ports() {
+            node.set_port(port_name, *tag, channel);
+        }
+        Ok(node)
+    }
+
+    /// Replace the node, and stop the old instances after their current exec if the graph is running
+    fn replace_node(&mut self, mut node: AnyNode) {
+        let name = node.info().entity.name.clone();
+        if let Some(spawner) = &self.spawner {
+            let pause = self.pause_token(&name);
+            let (tokens, spawns) = Spawn::from_node(self.ctx.local_key, &mut node, pause);
+            for spawn in spawns {
+                // fails only if the graph is finished
+                spawner.try_send(spawn).ok();
+            }
+            for token in self.stops.insert(name.clone(), tokens).unwrap_or_default() {
+                token.stop();
+            }
+        }
+        self.nodes.insert(name, node);
+    }
+}
+
+fn diff(local_key: u64, old: &config::Config, new: &config::Config) -> Result<ReloadReport> {
+    if old.main != new.main {
+        return Err(anyhow!(
+            "main graph is changed from {} to {}",
+            old.main,
+            new.main
+        ));
+    }
+    if old.resources != new.resources {
+        return Err(anyhow!("global resources are changed"));
+    }
+    if old.nodes != new.nodes {
+        return Err(anyhow!("shared nodes are changed"));
+    }
+
+    let old_graphs: HashMap<_, _> = old.graphs.iter().map(|g| (&g.name, g)).collect();
+    let new_main = new
+        .graphs
+        .iter()
+        .find(|g| g.name == new.main)
+        .ok_or_else(|| anyhow!("graph {} is not exist", new.main))?;
+    let restarted = diff_main(local_key, old_graphs[&old.main], new_main)?;
+
+    let pinned = pinned(new);
+    let mut updated_graphs = vec![];
+    for cfg in &new.graphs {
+        if cfg.name == new.main {
+            continue;
+        }
+        if let Some(old) = old_graphs.get(&cfg.name) {
+            if *old == cfg {
+                continue;
+            }
+            if old.inputs != cfg.inputs || old.outputs != cfg.outputs {
+                return Err(anyhow!(
+                    "inputs or outputs of graph {} are changed",
+                    cfg.name
+                ));
+            }
+        }
+        if pinned.contains(&cfg.name) {
+            return Err(anyhow!(
+                "graph {} is changed, but it is instantiated statically by the main graph or shared nodes",
+                cfg.name
+            ));
+        }
+        updated_graphs.push(cfg.name.clone());
+    }
+    updated_graphs.sort();
+
+    Ok(ReloadReport {
+        restarted,
+        updated_graphs,
+    })
+}
+
+fn diff_main(local_key: u64, old: &config::Graph, new: &config::Graph) -> Result<Vec<String>> {
+    if old.resources != new.resources {
+        return Err(anyhow!("resources of graph {} are changed", new.name));
+    }
+    if old.inputs != new.inputs || old.outputs != new.outputs || old.connections != new.connections
+    {
+        return Err(anyhow!("connections of graph {} are changed", new.name));
+    }
+    let old_names: BTreeSet<_> = old.nodes.keys().collect();
+    let new_names: BTreeSet<_> = new.nodes.keys().collect();
+    if old_names != new_names {
+        return Err(anyhow!("nodes of graph {} are added or removed", new.name));
+    }
+
+    let mut restarted = vec![];
+    for name in new_names {
+        let (old_node, new_node) = (&old.nodes[name], &new.nodes[name]);
+        if old_node == new_node {
+            continue;
+        }
+        if old_node.is_dyn || new_node.is_dyn {
+            return Err(anyhow!("dyn node {} can not be reloaded", name));
+        }
+        let has_dyn_port = new
+            .connections
+            .values()
+            .flat_map(|conn| conn.rx.iter().chain(conn.tx.iter()))
+            .any(|port| port.node_name == *name && port.is_dyn());
+        if has_dyn_port {
+            return Err(anyhow!("node {} with dyn ports can not be reloaded", name));
+        }
+        let is_subgraph = new_node
+            .entity
+            .ty
+            .iter()
+            .any(|ty| NodeSlice::registry_local().get(local_key).get(ty).is_none());
+        if is_subgraph {
+            return Err(anyhow!("subgraph node {} can not be reloaded", name));
+        }
+        restarted.push(name.clone());
+    }
+    Ok(restarted)
+}
+
+// graphs instantiated once when the main graph is loaded
+fn pinned(config: &config::Config) -> BTreeSet<String> {
+    let mut pinned = BTreeSet::new();
+    let mut pending: Vec<_> = config
+        .nodes
+        .values()
+        .flat_map(|node| node.entity.ty.iter())
+        .chain(std::iter::once(&config.main))
+        .collect();
+    while let Some(name) = pending.pop() {
+        if !pinned.insert(name.clone()) {
+            continue;
+        }
+        if let Some(graph) = config.graphs.iter().find(|g| g.name == *name) {
+            pending.extend(
+                graph
+                    .nodes
+                    .values()
+                    .filter(|node| !node.is_dyn)
+                    .flat_map(|node| node.entity.ty.iter()),
+            );
+        }
+    }
+    pinned
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn node(name: &str, ty: &str, is_dyn: bool) -> config::Node {
+        config::Node {
+            entity: config::Entity {
+                name: name.to_owned(),
+                ty: vec![ty.to_owned()],
+                args: Default::default(),
+            },
+            res: vec![],
+            cloned: None,
+            balance: Default::default(),
+            inputs: vec![],
+            outputs: vec![],
+            is_dyn,
+            is_shared: false,
+            restart: Default::default(),
+        }
+    }
+
+    fn graph(name: &str, nodes: Vec<config::Node>) -> config::Graph {
+        config::Graph {
+            name: name.to_owned(),
+            resources: Default::default(),
+            nodes: nodes
+                .into_iter()
+                .map(|n| (n.entity.name.clone(), n))
+                .collect(),
+            inputs: vec![],
+            outputs: vec![],
+            connections: Default::default(),
+            is_shared: false,
+            global_res: vec![],
+        }
+    }
+
+    fn main_config(graphs: Vec<config::Graph>) -> config::Config {
+        config::Config {
+            resources: Default::default(),
+            nodes: Default::default(),
+            graphs,
+            main: "main".to_owned(),
+            metrics: None,
+            trace: None,
+        }
+    }
+
+    #[test]
+    fn test_pinned() {
+        let cfg = main_config(vec![
+            graph("main", vec![node("a", "A", false), node("b", "B", true)]),
+            graph("A", vec![node("c", "C", false)]),
+            graph("B", vec![node("d", "D", false)]),
+            graph("C", vec![]),
+            graph("D", vec![]),
+        ]);
+        let pinned = pinned(&cfg);
+        assert!(pinned.contains("A"));
+        assert!(pinned.contains("C"));
+        assert!(!pinned.contains("B"));
+        assert!(!pinned.contains("D"));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = main_config(vec![
+            graph("main", vec![node("b", "B", true)]),
+            graph("B", vec![node("d", "D", false)]),
+        ]);
+        let mut new = old.clone();
+        new.graphs[1].nodes.get_mut("d").unwrap().cloned = Some(2);
+        let report = diff(0, &old, &new).unwrap();
+        assert!(report.restarted.is_empty());
+        assert_eq!(report.updated_graphs, vec!["B".to_owned()]);
+
+        let mut new = old.clone();
+        new.resources.insert(
+            "r".to_owned(),
+            presentation::Entity {
+                name: "r".to_owned(),
+                ty: "R".to_owned(),
+                args: Default::default(),
+            },
+        );
+        assert!(diff(0, &old, &new).is_err());
+
+        let mut new = old.clone();
+        new.graphs[0].nodes.get_mut("b").unwrap().cloned = Some(2);
+        assert!(diff(0, &old, &new).is_err());
+    }
+}
@@ -0,0 +1,202 @@
+/**
+ * \file flow-rs/src/graph/shutdown.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::{Context, MainGraph};
+use crate::channel::ChannelStorage;
+use crate::node::StopToken;
+use crate::rt::task::JoinHandle;
+use anyhow::{anyhow, Result};
+use event_listener::Event;
+use futures_util::future::{AbortHandle, Abortable};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// What is done by `MainGraph::shutdown`
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownReport {
+    /// Whether all envelopes are drained and all nodes are finished before the timeout
+    pub drained: bool,
+    /// Nodes of the main graph and shared nodes, whose instances failed to stop in time and are cancelled
+    pub cancelled: Vec<String>,
+}
+
+/// Node instances of a graph which are still running, so that they can be cancelled
+#[derive(Default)]
+pub(crate) struct Running {
+    next_id: AtomicU64,
+    tasks: Mutex<HashMap<u64, (String, StopToken, AbortHandle)>>,
+}
+
+impl Running {
+    /// Track the task of an instance of the node `name`, which is controlled by `token`
+    pub fn track(
+        self: &Arc<Self>,
+        name: String,
+        token: StopToken,
+        mut handle: JoinHandle<Result<()>>,
+    ) -> JoinHandle<Result<()>> {
+        let (abort, registration) = AbortHandle::new_pair();
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.tasks
+            .lock()
+            .unwrap()
+            .insert(id, (name.clone(), token, abort));
+        let running = self.clone();
+        crate::rt::task::spawn(async move {
+            let ret = match Abortable::new(&mut handle, registration).await {
+                Ok(ret) => ret,
+                Err(_) => {
+                    handle.cancel().await;
+                    Err(anyhow!("node {} is cancelled", name))
+                }
+            };
+            running.tasks.lock().unwrap().remove(&id);
+            ret
+        })
+    }
+
+    /// Cancel the current exec of all running instances, which are finalized then, and return names of their nodes
+    pub fn cancel(&self) -> BTreeSet<String> {
+        let tasks = self.tasks.lock().unwrap();
+        let mut names = BTreeSet::new();
+        for (name, token, _) in tasks.values() {
+            token.cancel();
+            names.insert(name.clone());
+        }
+        names
+    }
+
+    /// Abort instances ignoring cancellation, e.g. actors not derived by `Actor`, which are never finalized
+    pub fn abort(&self) {
+        for (_, _, abort) in self.tasks.lock().unwrap().values() {
+            abort.abort();
+        }
+    }
+}
+
+/// Whether the task started by `MainGraph::start` is finished
+#[derive(Default)]
+pub(super) struct Finish {
+    started: AtomicBool,
+    finished: AtomicBool,
+    finalized: AtomicBool,
+    event: Event,
+}
+
+impl Finish {
+    pub fn start(&self) {
+        self.started.store(true, Ordering::SeqCst);
+    }
+
+    pub fn notify(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+        self.event.notify(usize::MAX);
+    }
+
+    /// Clear graph local resources, which is done only once
+    pub fn finalize(&self, local_key: u64) {
+        if !self.finalized.swap(true, Ordering::SeqCst) {
+            crate::registry::finalize(local_key);
+        }
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.finalized.load(Ordering::SeqCst)
+    }
+
+    async fn wait(&self, timeout: Duration) -> bool {
+        if !self.started.load(Ordering::SeqCst) {
+            return true;
+        }
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.finished.load(Ordering::SeqCst) {
+                return true;
+            }
+            let listener = self.event.listen();
+            if self.finished.load(Ordering::SeqCst) {
+                return true;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if crate::rt::future::timeout(remaining, listener)
+                .await
+                .is_err()
+            {
+                return self.finished.load(Ordering::SeqCst);
+            }
+        }
+    }
+}
+
+/// A handle to shut down a main graph from elsewhere, e.g. a signal handler, see `MainGraph::shutdown`
+#[derive(Clone)]
+pub struct ShutdownHandle {
+    inputs: Vec<ChannelStorage>,
+    running: Arc<Running>,
+    shared: Arc<Running>,
+    finish: Arc<Finish>,
+    global_ctx: Context,
+}
+
+impl ShutdownHandle {
+    /// Close inputs of the graph, and wait at most `timeout` for envelopes in flight to be drained through all nodes.
+    ///
+    /// The current exec of nodes of the main graph and shared nodes still running at the timeout are cancelled, and
+    /// the nodes are finalized then. The graph gets another `timeout` to finish, and after that nodes ignoring the
+    /// cancellation are aborted without being finalized, and local resources of the graph are finalized anyway.
+    pub async fn shutdown(&self, timeout: Duration) -> ShutdownReport {
+        for input in &self.inputs {
+            input.close();
+        }
+        let drained = self.finish.wait(timeout).await;
+        let mut cancelled = vec![];
+        if !drained {
+            let mut names = self.running.cancel();
+            names.append(&mut self.shared.cancel());
+            cancelled = names.into_iter().collect();
+            log::warn!("nodes {:?} failed to stop in time, cancelled", cancelled);
+            self.global_ctx.close();
+            if !self.finish.wait(timeout).await {
+                self.running.abort();
+                self.shared.abort();
+                log::error!(
+                    "graph failed to stop after cancelling, the remaining tasks are aborted without finalizing"
+                );
+            }
+        }
+        self.finish.finalize(self.global_ctx.local_key);
+        ShutdownReport { drained, cancelled }
+    }
+}
+
+impl MainGraph {
+    /// Get a handle to shut down the graph, which can be cloned and sent to other threads
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        ShutdownHandle {
+            inputs: self
+                .graph
+                .inputs
+                .iter()
+                .map(|name| self.graph.conns[name].get().clone())
+                .collect(),
+            running: self.graph.running.clone(),
+            shared: self.shared.clone(),
+            finish: self.finish.clone(),
+            global_ctx: self.global_ctx.clone(),
+        }
+    }
+
+    /// Shut down the graph gracefully, see `ShutdownHandle::shutdown`
+    pub async fn shutdown(self, timeout: Duration) -> ShutdownReport {
+        self.shutdown_handle().shutdown(timeout).await
+    }
+}
@@ -0,0 +1,71 @@
+/**
+ * \file flow-rs/src/graph/snapshot.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::MainGraph;
+use crate::config::graphviz::{self, Live};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Counters of the last snapshot, which the throughput and the blocked state are measured against
+pub(super) struct Snapshot {
+    at: Instant,
+    received: HashMap<String, u64>,
+    blocked: HashMap<String, Duration>,
+}
+
+impl Default for Snapshot {
+    fn default() -> Self {
+        Snapshot {
+            at: Instant::now(),
+            received: Default::default(),
+            blocked: Default::default(),
+        }
+    }
+}
+
+impl MainGraph {
+    /// Render the graph in the DOT language, where connections of the main graph are annotated with
+    /// their queue depth, blocked state and throughput since the last snapshot, and colored by them
+    pub fn snapshot_dot(&self) -> String {
+        graphviz::dump_dot_live(&self.config, &self.live())
+    }
+
+    /// The same as `snapshot_dot`, but rendered as svg without graphviz
+    pub fn snapshot_svg(&self) -> String {
+        graphviz::dump_svg_live(&self.config, &self.live())
+    }
+
+    fn live(&self) -> HashMap<String, Live> {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let now = Instant::now();
+        let secs = now.duration_since(snapshot.at).as_secs_f64();
+        let mut live = HashMap::new();
+        for (name, conn) in &self.graph.conns {
+            let storage = conn.get();
+            let received = storage.stats().received();
+            let last = snapshot.received.insert(name.clone(), received);
+            let delta = received.saturating_sub(last.unwrap_or(0));
+            // senders waited on the full channel since the last snapshot
+            let blocked = storage.stats().blocked();
+            let last = snapshot.blocked.insert(name.clone(), blocked);
+            let blocked = blocked > last.unwrap_or_default();
+            live.insert(
+                name.clone(),
+                Live {
+                    queued: storage.len(),
+                    blocked,
+                    throughput: if secs > 0.0 { delta as f64 / secs } else { 0.0 },
+                },
+            );
+        }
+        snapshot.at = now;
+        live
+    }
+}
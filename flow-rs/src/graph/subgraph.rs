@@ -17,12 +17,11 @@ impl Node for Graph {
         let port_name = port_name.to_owned();
         let local_key = self.ctx.local_key;
         if let Some(chan) = self.conns.get_mut(&port_name) {
+            let channel = &chan.expire(channel.clone());
             chan.set(channel.clone());
             for port in chan.info().rx.iter().chain(chan.info().tx.iter()) {
                 if let Some(nodes) = self.nodes.get_mut(&port.node_name) {
-                    for node in nodes.get_mut() {
-                        node.set_port(&port.port_name, tag, channel);
-                    }
+                    nodes.set_port(&port.port_name, tag, channel);
                 } else {
                     if self.is_shared {
                         panic!("nested shared graph is not support");
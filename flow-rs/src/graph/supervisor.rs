@@ -0,0 +1,210 @@
+/**
+ * \file flow-rs/src/graph/supervisor.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::node::{AnyNode, PortRecord};
+use super::Context;
+use crate::channel::ChannelBase;
+use crate::config::interlayer as config;
+use crate::config::presentation::RestartPolicy;
+use crate::node::{with_pause_token, with_stop_token, Actor, PauseToken, StopToken};
+use crate::resource::ResourceCollection;
+use crate::rt::task::JoinHandle;
+use anyhow::Result;
+use std::time::Duration;
+
+const MAX_BACKOFF_SHIFT: u32 = 5;
+
+/// A node instance waiting to be started by the graph
+pub(crate) struct Spawn {
+    pub is_alone: bool,
+    pub name: String,
+    res_names: Vec<String>,
+    respawn: Option<Respawn>,
+    pub token: StopToken,
+    pause: PauseToken,
+    actor: Box<dyn Actor>,
+}
+
+impl Spawn {
+    /// Take all instances of the node, and return them with their stop tokens, all instances share the pause token.
+    ///
+    /// If the node is balanced, only the tokens of its dispatchers are returned, and the instances are finished
+    /// after their own queues are drained.
+    pub fn from_node(
+        local_key: u64,
+        node: &mut AnyNode,
+        pause: PauseToken,
+    ) -> (Vec<StopToken>, Vec<Spawn>) {
+        let info = node.info();
+        let is_alone = info.inputs.is_empty() && info.outputs.is_empty();
+        let respawns: Vec<_> = (0..node.get().len())
+            .map(|i| {
+                if info.restart.policy != RestartPolicy::Never {
+                    Some(Respawn::new(
+                        local_key,
+                        info.clone(),
+                        node.instance_ports(i).clone(),
+                    ))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let name = info.entity.name.clone();
+        let res_names = info.res.clone();
+        let dispatchers = node.take_dispatchers();
+        let spawn = |name: &str, res_names: &[String], respawn, actor| {
+            let token = StopToken::default();
+            let spawn = Spawn {
+                is_alone,
+                name: name.to_owned(),
+                res_names: res_names.to_vec(),
+                respawn,
+                token: token.clone(),
+                pause: pause.clone(),
+                actor,
+            };
+            (token, spawn)
+        };
+        let (mut tokens, mut spawns): (Vec<_>, Vec<_>) = node
+            .get_into()
+            .into_iter()
+            .zip(respawns)
+            .map(|(actor, respawn)| spawn(&name, &res_names, respawn, actor))
+            .unzip();
+        if !dispatchers.is_empty() {
+            let name = format!("__{}xBalance__", name);
+            let (dispatch_tokens, mut dispatch_spawns): (Vec<_>, Vec<_>) = dispatchers
+                .into_iter()
+                .map(|actor| spawn(&name, &[], None, actor))
+                .unzip();
+            tokens = dispatch_tokens;
+            spawns.append(&mut dispatch_spawns);
+        }
+        (tokens, spawns)
+    }
+
+    pub fn start(self, ctx: &Context, res: &ResourceCollection) -> JoinHandle<Result<()>> {
+        let res = res.filter(
+            self.res_names
+                .iter()
+                .map(|x| x.as_str())
+                .collect::<Vec<_>>()
+                .as_slice(),
+        );
+        let (name, actor) = (self.name, self.actor);
+        with_pause_token(&self.pause, || {
+            with_stop_token(&self.token, || match self.respawn {
+                Some(respawn) => respawn.supervise(actor, ctx.clone(), res),
+                None => crate::trace::with_node(&ctx.ty, &name, || {
+                    crate::metrics::with_node(&ctx.ty, &name, || actor.start(ctx.clone(), res))
+                }),
+            })
+        })
+    }
+}
+
+/// Everything needed to recreate a node instance and rewire it to the channels of the graph
+#[derive(Clone)]
+pub(crate) struct Respawn {
+    local_key: u64,
+    info: config::Node,
+    ports: Vec<PortRecord>,
+}
+
+impl Respawn {
+    pub fn new(local_key: u64, info: config::Node, ports: Vec<PortRecord>) -> Respawn {
+        Respawn {
+            local_key,
+            info,
+            ports,
+        }
+    }
+
+    fn rebuild(&self) -> Result<Box<dyn Actor>> {
+        let mut actor = crate::node::load_one(self.local_key, &self.info)?;
+        for (port_name, tag, channel) in &self.ports {
+            actor.set_port(port_name, *tag, channel);
+        }
+        Ok(actor)
+    }
+
+    // whether any input of the node is still open, e.g. the node exits before its upstream is finished
+    fn is_input_open(&self) -> bool {
+        self.ports.iter().any(|(port_name, _, channel)| {
+            self.info.inputs.contains(port_name) && !channel.is_closed()
+        })
+    }
+
+    /// Run the actor, and restart it according to the restart policy when it fails or, with
+    /// `RestartPolicy::Always`, when it exits while its inputs are still open
+    pub fn supervise(
+        self,
+        actor: Box<dyn Actor>,
+        ctx: Context,
+        res: ResourceCollection,
+    ) -> JoinHandle<Result<()>> {
+        // the channels must not be closed while the failed instance is replaced
+        let holds: Vec<_> = self
+            .ports
+            .iter()
+            .map(|(_, _, channel)| channel.hold())
+            .collect();
+        let token = StopToken::current();
+        let pause = PauseToken::current();
+        crate::rt::task::spawn(async move {
+            let _holds = holds;
+            let restart = &self.info.restart;
+            let name = &self.info.entity.name;
+            let mut actor = actor;
+            let mut retries = 0;
+            loop {
+                let handle = with_pause_token(&pause, || {
+                    with_stop_token(&token, || {
+                        crate::trace::with_node(&ctx.ty, name, || {
+                            crate::metrics::with_node(&ctx.ty, name, || {
+                                actor.start(ctx.clone(), res.clone())
+                            })
+                        })
+                    })
+                });
+                // a panic aborts the process, as the workspace is built with `panic = 'abort'`
+                let err = match handle.await {
+                    Ok(_) => {
+                        if restart.policy != RestartPolicy::Always
+                            || token.is_stopped()
+                            || !self.is_input_open()
+                        {
+                            return Ok(());
+                        }
+                        None
+                    }
+                    Err(err) => Some(err),
+                };
+                if token.is_stopped() || restart.max_retries.map_or(false, |max| retries >= max) {
+                    return err.map_or(Ok(()), Err);
+                }
+                match &err {
+                    Some(err) => log::warn!("node {} failed: {}, restarting", name, err),
+                    None => log::warn!("node {} exited with open inputs, restarting", name),
+                }
+                let backoff = restart.backoff << std::cmp::min(retries, MAX_BACKOFF_SHIFT);
+                if backoff > 0 {
+                    crate::rt::task::sleep(Duration::from_millis(backoff)).await;
+                }
+                if ctx.is_closed() {
+                    return err.map_or(Ok(()), Err);
+                }
+                retries += 1;
+                actor = self.rebuild()?;
+            }
+        })
+    }
+}
@@ -24,12 +24,16 @@ pub mod graph;
 #[cfg(feature = "python")]
 pub mod helper;
 pub mod loader;
+#[doc(hidden)]
+pub mod metrics;
 pub mod node;
+pub mod record;
 #[doc(hidden)]
 pub mod registry;
 pub mod resource;
 #[cfg(test)]
 pub mod sandbox;
+pub mod trace;
 
 pub mod prelude {
     #[doc(hidden)]
@@ -40,7 +44,7 @@ pub mod prelude {
     pub use super::node::*;
     pub use super::registry::*;
     pub use super::resource::*;
-    pub use super::Builder;
+    pub use super::{Builder, GraphBuilder, NodePorts, PortRef};
     /// Re-exports async_std as rt
     pub use async_std as rt;
     pub use flow_derive::*;
@@ -49,21 +53,29 @@ pub mod prelude {
 use anyhow::{anyhow, Result};
 /// Re-exports async_std as rt
 pub use async_std as rt;
+pub use config::builder::{GraphBuilder, NodePorts, PortRef};
+pub use config::format::Format;
+pub use config::resolve::Resolved;
+pub use config::validate::{Diagnostic, DiagnosticKind, Severity, Span};
 #[doc(hidden)]
 pub use ctor::*;
 #[cfg(feature = "debug")]
 pub use debug::Server as DebugServer;
 pub use flow_derive::*;
-use node::NodeInfo;
 use prelude::MainGraph;
 use registry::Collect;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 /// A builder to load graph with config
 pub struct Builder {
     local_key: u64,
     template: String,
+    // where the template is read from, which relative includes are resolved against
+    path: Option<PathBuf>,
+    format: Format,
     dynamic: String,
+    graphs: Vec<config::presentation::Graph>,
+    main: Option<String>,
 }
 
 impl Default for Builder {
@@ -79,7 +91,11 @@ impl Builder {
         Builder {
             local_key,
             template: Default::default(),
+            path: None,
+            format: Default::default(),
             dynamic: Default::default(),
+            graphs: vec![],
+            main: None,
         }
     }
 
@@ -88,8 +104,11 @@ impl Builder {
         self
     }
 
+    /// Read the template in toml, json or yaml, which is guessed by the extension
     pub fn template_file<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
         self.template = std::fs::read_to_string(path.as_ref())?;
+        self.path = Some(path.as_ref().to_owned());
+        self.format = Format::from_path(path.as_ref());
         Ok(self)
     }
 
@@ -100,6 +119,8 @@ impl Builder {
 
     pub fn template(mut self, template: String) -> Self {
         self.template = template;
+        self.path = None;
+        self.format = Format::Toml;
         self
     }
 
@@ -108,11 +129,65 @@ impl Builder {
         self
     }
 
+    /// Add a graph built in rust, which is appended to the graphs of the template if there is one
+    pub fn graph(mut self, graph: GraphBuilder) -> Self {
+        self.graphs.push(graph.into_config());
+        self
+    }
+
+    /// Set the main graph, which defaults to the first graph added by `graph` if there is no template
+    pub fn main(mut self, name: &str) -> Self {
+        self.main = Some(name.to_owned());
+        self
+    }
+
+    fn config(&self) -> Result<config::presentation::Config> {
+        let mut config = if self.template.is_empty() && !self.graphs.is_empty() {
+            config::presentation::Config {
+                include: vec![],
+                resources: vec![],
+                nodes: vec![],
+                graphs: vec![],
+                main: self.graphs[0].name.clone(),
+                metrics: None,
+                trace: None,
+            }
+        } else {
+            config::parser::Parser::from_str_in(
+                self.template.as_ref(),
+                self.path.as_deref(),
+                Some(self.dynamic.as_ref()),
+                self.format,
+            )?
+        };
+        config.graphs.extend(self.graphs.iter().cloned());
+        if let Some(main) = &self.main {
+            config.main = main.clone();
+        }
+        Ok(config)
+    }
+
+    /// Check the config without building it, and collect all problems found
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        config::validate::validate(self.local_key, &self.template, self.config())
+    }
+
+    /// Resolve includes, templates and the connections expanded by the loader without building the graph
+    pub fn resolve(self) -> Result<Resolved> {
+        let local_key = self.local_key;
+        let config = self.config()?;
+        for cfg in &config.graphs {
+            graph::register_placeholder(local_key, cfg);
+        }
+        let config = config::translate_config(local_key, config)?;
+        Ok(config::resolve::resolve(&config))
+    }
+
     pub fn build(self) -> Result<MainGraph> {
-        load_impl(
-            self.local_key,
-            config::parser::Parser::from_str(self.template.as_ref(), Some(self.dynamic.as_ref()))?,
-        )
+        let local_key = self.local_key;
+        let mut graph = load_impl(local_key, self.config()?)?;
+        graph.set_source(self.path, self.format);
+        Ok(graph)
     }
 }
 
@@ -125,17 +200,7 @@ lazy_static::lazy_static!(
 fn load_impl(local_key: u64, config: config::presentation::Config) -> Result<graph::MainGraph> {
     // register subgraph info
     for cfg in &config.graphs {
-        let info = NodeInfo {
-            inputs: cfg.inputs.iter().map(|conn| conn.name.clone()).collect(),
-            outputs: cfg.outputs.iter().map(|conn| conn.name.clone()).collect(),
-        };
-        graph::GraphSlice::registry_local().get(local_key).insert(
-            cfg.name.clone(),
-            graph::GraphSlice {
-                cons: Box::new(move |_, _| Err(anyhow!("graph is not loaded"))),
-                info,
-            },
-        );
+        graph::register_placeholder(local_key, cfg);
     }
     let global_nodes_keys: Vec<_> = config
         .nodes
@@ -147,26 +212,23 @@ fn load_impl(local_key: u64, config: config::presentation::Config) -> Result<gra
     let config = config::translate_config(local_key, config)?;
     config::graphviz::dump(&config)?;
 
+    if let Some(cfg) = &config.metrics {
+        #[cfg(feature = "metrics")]
+        metrics::serve(cfg);
+        #[cfg(not(feature = "metrics"))]
+        log::warn!(
+            "metrics on port {} is configured, but the metrics feature is not enabled",
+            cfg.port
+        );
+    }
+
+    if let Some(cfg) = &config.trace {
+        trace::install(cfg)?;
+    }
+
     // update graph constructor
     for cfg in &config.graphs {
-        let cfg = cfg.clone();
-        let info = NodeInfo {
-            inputs: cfg.inputs.clone(),
-            outputs: cfg.outputs.clone(),
-        };
-        graph::GraphSlice::registry_local().get(local_key).insert(
-            cfg.name.clone(),
-            graph::GraphSlice {
-                cons: Box::new(move |name, table| {
-                    graph::Graph::load(
-                        graph::context(name, cfg.name.clone(), local_key),
-                        &cfg,
-                        table,
-                    )
-                }),
-                info,
-            },
-        );
+        graph::register(local_key, cfg);
     }
 
     // register shared nodes
@@ -174,11 +236,12 @@ fn load_impl(local_key: u64, config: config::presentation::Config) -> Result<gra
     let global_resources =
         resource::UniqueResourceCollection::new(ctx.local_key, ctx.id, &config.resources)
             .take_into_arc();
+    let shared = std::sync::Arc::new(graph::Running::default());
     for k in global_nodes_keys {
         let cfg = config.nodes.get(&k).unwrap();
         node::SharedProxy::registry_local().get(local_key).insert(
             cfg.entity.name.clone(),
-            node::load_shared(cfg, &config, ctx.clone(), global_resources.clone())?,
+            node::load_shared(cfg, &config, ctx.clone(), global_resources.clone(), &shared)?,
         );
     }
 
@@ -187,7 +250,7 @@ fn load_impl(local_key: u64, config: config::presentation::Config) -> Result<gra
         .get(&config.main)
         .map(|slice| (slice.cons)(config.main.clone(), &Default::default()))
     {
-        Some(ret) => ret.map(|g| MainGraph::new(g, ctx, global_resources)),
+        Some(ret) => ret.map(|g| MainGraph::new(g, ctx, global_resources, config, shared)),
         _ => Err(anyhow!("graph {} is not exist", config.main)),
     }
 }
@@ -0,0 +1,274 @@
+/**
+ * \file flow-rs/src/loader/dylib.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::{Loader, Plugin, PluginType};
+use crate::channel::ChannelStorage;
+use crate::graph::Context;
+use crate::metrics::ExecObserver;
+use crate::node::{Actor, DynPortsConfig, Node, NodeSlice, PauseToken, StopToken};
+use crate::registry::Collect;
+use crate::resource::{ResourceCollection, ResourceSlice};
+use crate::rt::task::JoinHandle;
+use anyhow::{anyhow, Result};
+use libloading::{Library, Symbol};
+use std::collections::HashSet;
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Plugins are only compatible with the host built with the same version of flow-rs,
+/// as node types are passed through the rust abi
+#[doc(hidden)]
+pub const ABI_VERSION: &str = concat!("flow-rs/", env!("CARGO_PKG_VERSION"), "\0");
+
+const ABI_SYMBOL: &[u8] = b"__megflow_abi_version\0";
+const COLLECT_SYMBOL: &[u8] = b"__megflow_collect\0";
+
+lazy_static::lazy_static! {
+    // node types refer to the code of libraries, so libraries are never unloaded
+    static ref LIBRARIES: Mutex<Vec<Library>> = Default::default();
+    // node types loaded from libraries, with the local keys of their graph loads
+    static ref NODES: Mutex<HashSet<(u64, String)>> = Default::default();
+}
+
+/// Whether the node type `ty` of the graph load `local_key` is loaded from a shared library
+pub(crate) fn is_dylib_node(local_key: u64, ty: &str) -> bool {
+    NODES.lock().unwrap().contains(&(local_key, ty.to_owned()))
+}
+
+/// The tokens read by `Actor::start` from thread locals, which are separate in the plugin and the host
+struct Tokens {
+    stop: StopToken,
+    pause: PauseToken,
+    exec: ExecObserver,
+}
+
+type StartFn = fn(Box<dyn Actor>, Context, ResourceCollection, Tokens) -> JoinHandle<Result<()>>;
+
+// called through the function pointer collected from the plugin, so the tokens are set into its thread locals
+fn start(
+    actor: Box<dyn Actor>,
+    ctx: Context,
+    resources: ResourceCollection,
+    tokens: Tokens,
+) -> JoinHandle<Result<()>> {
+    crate::node::with_stop_token(&tokens.stop, || {
+        crate::node::with_pause_token(&tokens.pause, || {
+            crate::metrics::with_observer(&tokens.exec, || actor.start(ctx, resources))
+        })
+    })
+}
+
+/// Nodes and resources registered in a plugin, see `dylib_export!`
+#[doc(hidden)]
+pub struct Collection {
+    nodes: Vec<(String, Arc<NodeSlice>)>,
+    resources: Vec<(String, Arc<ResourceSlice>)>,
+    start: StartFn,
+}
+
+/// Write nodes and resources registered in the plugin into `collection`, which points to an uninitialized
+/// `Collection` of the host
+///
+/// # Safety
+///
+/// `collection` must be valid for writes, and the host must be built with the same version of flow-rs
+#[doc(hidden)]
+pub unsafe fn export(collection: *mut c_void) {
+    (collection as *mut Collection).write(collect())
+}
+
+/// Collect nodes and resources registered by `node_register!` and `resource_register!` in the plugin
+#[doc(hidden)]
+pub fn collect() -> Collection {
+    let nodes = NodeSlice::registry_global();
+    let resources = ResourceSlice::registry_global();
+    Collection {
+        nodes: nodes
+            .keys()
+            .into_iter()
+            .filter_map(|name| nodes.get(&name).map(|slice| (name, slice)))
+            .collect(),
+        resources: resources
+            .keys()
+            .into_iter()
+            .filter_map(|name| resources.get(&name).map(|slice| (name, slice)))
+            .collect(),
+        start,
+    }
+}
+
+/// A node of the plugin, which is started with the tokens of the host
+struct DylibNode {
+    inner: Box<dyn Actor>,
+    start: StartFn,
+}
+
+impl Node for DylibNode {
+    fn set_port(&mut self, port_name: &str, tag: Option<u64>, channel: &ChannelStorage) {
+        self.inner.set_port(port_name, tag, channel)
+    }
+    fn set_port_dynamic(&mut self, port_name: &str, config: DynPortsConfig) {
+        self.inner.set_port_dynamic(port_name, config)
+    }
+    fn close(&mut self) {
+        self.inner.close()
+    }
+    fn is_allinp_closed(&self) -> bool {
+        self.inner.is_allinp_closed()
+    }
+}
+
+impl Actor for DylibNode {
+    fn start(
+        self: Box<Self>,
+        ctx: Context,
+        resources: ResourceCollection,
+    ) -> JoinHandle<Result<()>> {
+        let tokens = Tokens {
+            stop: StopToken::current(),
+            pause: PauseToken::current(),
+            exec: ExecObserver::current(),
+        };
+        (self.start)(self.inner, ctx, resources, tokens)
+    }
+}
+
+struct NodePlugin {
+    local_key: u64,
+    name: String,
+    slice: Arc<NodeSlice>,
+    start: StartFn,
+}
+
+impl Plugin for NodePlugin {
+    fn submit(&self) {
+        let slice = self.slice.clone();
+        let start = self.start;
+        NODES
+            .lock()
+            .unwrap()
+            .insert((self.local_key, self.name.clone()));
+        NodeSlice::registry_local().get(self.local_key).insert(
+            self.name.clone(),
+            NodeSlice {
+                cons: Box::new(move |name, args| {
+                    Box::new(DylibNode {
+                        inner: (slice.cons)(name, args),
+                        start,
+                    })
+                }),
+                info: self.slice.info.clone(),
+            },
+        );
+    }
+}
+
+struct ResourcePlugin {
+    local_key: u64,
+    name: String,
+    slice: Arc<ResourceSlice>,
+}
+
+impl Plugin for ResourcePlugin {
+    fn submit(&self) {
+        let slice = self.slice.clone();
+        ResourceSlice::registry_local().get(self.local_key).insert(
+            self.name.clone(),
+            ResourceSlice {
+                cons: Box::new(move |name, args| (slice.cons)(name, args)),
+            },
+        );
+    }
+}
+
+struct DylibLoader;
+
+impl Loader for DylibLoader {
+    fn load_from_scope(&self, _: u64) -> Result<Vec<Box<dyn Plugin>>> {
+        // nodes linked into the host are registered by their ctors
+        Ok(vec![])
+    }
+
+    fn load_from_file(
+        &self,
+        local_key: u64,
+        _: Option<&Path>,
+        plugin_path: &Path,
+    ) -> Result<Vec<Box<dyn Plugin>>> {
+        let library = unsafe { Library::new(plugin_path)? };
+        let collection = unsafe {
+            let abi: Symbol<extern "C" fn() -> *const c_char> = library.get(ABI_SYMBOL)?;
+            let abi = CStr::from_ptr(abi()).to_str()?;
+            let expected = ABI_VERSION.trim_end_matches('\0');
+            if abi != expected {
+                return Err(anyhow!(
+                    "plugin {} is built with {}, but {} is expected",
+                    plugin_path.display(),
+                    abi,
+                    expected
+                ));
+            }
+            let collect: Symbol<unsafe extern "C" fn(*mut c_void)> = library.get(COLLECT_SYMBOL)?;
+            let mut collection = std::mem::MaybeUninit::<Collection>::uninit();
+            collect(collection.as_mut_ptr() as *mut c_void);
+            collection.assume_init()
+        };
+        LIBRARIES.lock().unwrap().push(library);
+
+        // the plugin also carries its own copy of builtin nodes, which are skipped
+        let nodes = NodeSlice::registry_local().get(local_key);
+        let resources = ResourceSlice::registry_local().get(local_key);
+        let mut plugins = vec![];
+        for (name, slice) in collection.nodes {
+            if nodes.get(&name).is_none() {
+                plugins.push(Box::new(NodePlugin {
+                    local_key,
+                    name,
+                    slice,
+                    start: collection.start,
+                }) as Box<dyn Plugin>);
+            }
+        }
+        for (name, slice) in collection.resources {
+            if resources.get(&name).is_none() {
+                plugins.push(Box::new(ResourcePlugin {
+                    local_key,
+                    name,
+                    slice,
+                }) as Box<dyn Plugin>);
+            }
+        }
+        Ok(plugins)
+    }
+}
+
+crate::submit!(
+    PluginType::Dylib,
+    Box::new(DylibLoader {}) as Box<dyn Loader>
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_collect() {
+        let collection = collect();
+        assert!(collection.nodes.iter().any(|(name, _)| name == "Transform"));
+    }
+
+    #[test]
+    fn test_abi_version() {
+        assert!(ABI_VERSION.ends_with('\0'));
+        assert!(ABI_VERSION.starts_with("flow-rs/"));
+    }
+}
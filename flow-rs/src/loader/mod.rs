@@ -8,11 +8,14 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
+#[cfg(feature = "dylib")]
+pub mod dylib;
 #[cfg(feature = "python")]
 pub mod python;
 
 use crate::registry::Collect;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -30,9 +33,11 @@ trait Loader: Send + Sync {
     fn load_from_scope(&self, local_key: u64) -> Result<Vec<Box<dyn Plugin>>>;
 }
 
-#[derive(Copy, Clone, Hash, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
 pub enum PluginType {
     Python,
+    /// Shared libraries exporting their nodes by `dylib_export!`
+    Dylib,
 }
 
 crate::collect!(PluginType, Box<dyn Loader>);
@@ -43,19 +48,23 @@ pub struct LoaderConfig {
     pub ty: PluginType,
 }
 
-use std::sync::Once;
-static ONCE_LOAD_FROM_SCOPE: Once = Once::new();
+use std::sync::Mutex;
+lazy_static::lazy_static! {
+    static ref LOADED_FROM_SCOPE: Mutex<HashSet<PluginType>> = Default::default();
+}
 
 pub(crate) fn load(local_key: u64, cfg: &LoaderConfig) -> Result<()> {
-    let loader = <Box<dyn Loader>>::registry_global().get(&cfg.ty).unwrap();
-    ONCE_LOAD_FROM_SCOPE.call_once(|| {
+    let loader = <Box<dyn Loader>>::registry_global()
+        .get(&cfg.ty)
+        .ok_or_else(|| anyhow!("plugin type {:?} is not enabled", cfg.ty))?;
+    if LOADED_FROM_SCOPE.lock().unwrap().insert(cfg.ty) {
         for plugin in loader
             .load_from_scope(local_key)
             .expect("cannot load plugins from current scope")
         {
             plugin.submit();
         }
-    });
+    }
 
     if let Some(plugin_path) = &cfg.plugin_path {
         for entry in fs::read_dir(&plugin_path)? {
@@ -81,6 +90,16 @@ impl PluginType {
     fn check(&self, path: &Path) -> Option<PathBuf> {
         match *self {
             PluginType::Python => PluginType::check_python(path),
+            PluginType::Dylib => PluginType::check_dylib(path),
+        }
+    }
+
+    fn check_dylib(path: &Path) -> Option<PathBuf> {
+        match path.extension() {
+            Some(ext) if path.is_file() && ext == std::env::consts::DLL_EXTENSION => {
+                Some(path.to_path_buf())
+            }
+            _ => None,
         }
     }
 
@@ -17,6 +17,7 @@ use pyo3::{
     types::{IntoPyDict, PyDict},
 };
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 static ERR_MSG: &str = "use after move";
 
@@ -56,6 +57,7 @@ impl PyEnvelope {
             restore!(transfer_addr);
             restore!(partial_id);
             restore!(tag);
+            restore!(priority);
             if let Some(extra_data) = info.get_item("extra_data") {
                 target.extra_data = Some(Arc::new(extra_data.to_object(py)))
             }
@@ -136,6 +138,54 @@ impl PyEnvelope {
         let envelope = self.imp.as_mut().expect(ERR_MSG);
         envelope.info_mut().tag = Some(tag);
     }
+
+    #[getter(priority)]
+    fn get_priority(&self) -> i32 {
+        let envelope = self.imp.as_ref().expect(ERR_MSG);
+        envelope.info().priority
+    }
+
+    #[setter(priority)]
+    fn set_priority(&mut self, priority: i32) {
+        let envelope = self.imp.as_mut().expect(ERR_MSG);
+        envelope.info_mut().priority = priority;
+    }
+
+    #[getter(timeout)]
+    fn get_timeout(&self) -> Option<u64> {
+        let envelope = self.imp.as_ref().expect(ERR_MSG);
+        envelope.info().deadline.map(|deadline| {
+            deadline
+                .saturating_duration_since(Instant::now())
+                .as_millis() as u64
+        })
+    }
+
+    #[setter(timeout)]
+    fn set_timeout(&mut self, timeout_ms: u64) {
+        let envelope = self.imp.as_mut().expect(ERR_MSG);
+        envelope
+            .info_mut()
+            .set_timeout(Duration::from_millis(timeout_ms));
+    }
+
+    #[getter(age)]
+    fn get_age(&self) -> Option<u64> {
+        let envelope = self.imp.as_ref().expect(ERR_MSG);
+        envelope.info().age().map(|age| age.as_millis() as u64)
+    }
+
+    #[getter(is_expired)]
+    fn get_is_expired(&self) -> bool {
+        let envelope = self.imp.as_ref().expect(ERR_MSG);
+        envelope.info().is_expired()
+    }
+
+    #[getter(trace_id)]
+    fn get_trace_id(&self) -> Option<String> {
+        let envelope = self.imp.as_ref().expect(ERR_MSG);
+        envelope.info().trace.map(|trace| trace.trace_id_hex())
+    }
 }
 
 impl IntoPyDict for EnvelopeInfo {
@@ -151,6 +201,7 @@ impl IntoPyDict for EnvelopeInfo {
         store!(transfer_addr);
         store!(partial_id);
         store!(tag);
+        store!(priority);
         dict
     }
 }
@@ -10,12 +10,16 @@
  */
 use super::port::*;
 use super::RegistryNodeParams;
+use flow_rs::metrics::ExecObserver;
+use flow_rs::node::{PauseToken, StopToken};
 use flow_rs::prelude::*;
+use flow_rs::trace::ExecSpan;
 use pyo3::prelude::*;
 use pyo3::types::{PyDict, PyList};
 use stackful::stackful;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Instant;
 
 pub(crate) struct PyNode {
     imp: PyObject,
@@ -98,23 +102,37 @@ impl PyNode {
         }
     }
 
-    async fn exec(&mut self) {
+    async fn exec(&mut self) -> anyhow::Result<()> {
         stackful(|| {
             Python::with_gil(|py| {
                 if let Err(err) = self.imp.call_method0(py, "exec") {
                     err.print(py);
-                    panic!("python node {} exec fault!", self.name);
+                    Err(anyhow::anyhow!("python node {} exec fault!", self.name))
+                } else {
+                    Ok(())
                 }
             })
         })
-        .await;
+        .await
     }
 
-    async fn start_loop(&mut self, res: ResourceCollection) {
+    async fn start_loop(
+        &mut self,
+        res: ResourceCollection,
+        exec_observer: ExecObserver,
+        exec_span: ExecSpan,
+        stop_token: StopToken,
+        pause_token: PauseToken,
+    ) -> anyhow::Result<()> {
         self.initialize(res).await;
         let mut empty_n = 0;
         loop {
-            self.exec().await;
+            pause_token.wait().await;
+            let exec_start = Instant::now();
+            let exec_guard = exec_span.enter();
+            self.exec().await?;
+            drop(exec_guard);
+            exec_observer.observe(exec_start);
             if !self.inputs.is_empty() {
                 let mut min_empty_n = usize::MAX;
                 for ports in self.inputs.values() {
@@ -133,11 +151,12 @@ impl PyNode {
 
                 empty_n = min_empty_n;
             }
-            if self.is_allinp_closed() {
+            if self.is_allinp_closed() || stop_token.is_stopped() {
                 break;
             }
         }
         self.close();
+        Ok(())
     }
 }
 
@@ -189,17 +208,21 @@ impl Actor for PyNode {
         _: Context,
         res: ResourceCollection,
     ) -> rt::task::JoinHandle<anyhow::Result<()>> {
+        let exec_observer = ExecObserver::current();
+        let exec_span = ExecSpan::current();
+        let stop_token = StopToken::current();
+        let pause_token = PauseToken::current();
         if self.exclusive {
             flow_rs::rt::task::spawn_blocking(move || {
                 flow_rs::rt::task::block_on(async move {
-                    self.start_loop(res).await;
-                });
-                Ok(())
+                    self.start_loop(res, exec_observer, exec_span, stop_token, pause_token)
+                        .await
+                })
             })
         } else {
             flow_rs::rt::task::spawn_local(async move {
-                self.start_loop(res).await;
-                Ok(())
+                self.start_loop(res, exec_observer, exec_span, stop_token, pause_token)
+                    .await
             })
         }
     }
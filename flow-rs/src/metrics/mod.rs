@@ -0,0 +1,83 @@
+/**
+ * \file flow-rs/src/metrics/mod.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+mod registry;
+#[cfg(feature = "metrics")]
+mod server;
+
+use crate::channel::ChannelStats;
+use crate::config::interlayer as config;
+use registry::Histogram;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[cfg(feature = "metrics")]
+pub(crate) use server::serve;
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<Histogram>>> = RefCell::new(None);
+}
+
+/// Records the exec latency of the node being started in the current thread
+#[derive(Clone, Default)]
+pub struct ExecObserver(Option<Arc<Histogram>>);
+
+impl ExecObserver {
+    /// Must be called in `Actor::start` before the actor is spawned
+    pub fn current() -> ExecObserver {
+        ExecObserver(CURRENT.with(|current| current.borrow().clone()))
+    }
+
+    pub fn observe(&self, start: Instant) {
+        if let Some(histogram) = &self.0 {
+            histogram.observe(start.elapsed());
+        }
+    }
+}
+
+/// Call `f`, which starts a node recorded by `observer`
+#[cfg(feature = "dylib")]
+pub(crate) fn with_observer<F, R>(observer: &ExecObserver, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let prev = CURRENT.with(|current| current.replace(observer.0.clone()));
+    let ret = f();
+    CURRENT.with(|current| current.replace(prev));
+    ret
+}
+
+/// Call `f`, which starts the node `node` of the graph `graph`
+pub(crate) fn with_node<F, R>(graph: &str, node: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    if !cfg!(feature = "metrics") {
+        return f();
+    }
+    let histogram = registry::REGISTRY.lock().unwrap().node(graph, node);
+    let prev = CURRENT.with(|current| current.replace(Some(histogram)));
+    let ret = f();
+    CURRENT.with(|current| current.replace(prev));
+    ret
+}
+
+/// Get the statistics of the connection `name` of the graph `graph`, which is shared by all instances of the graph
+pub(crate) fn channel_stats(
+    graph: &str,
+    name: &str,
+    cfg: &config::Connection,
+) -> Arc<ChannelStats> {
+    if !cfg!(feature = "metrics") {
+        return Default::default();
+    }
+    registry::REGISTRY.lock().unwrap().channel(graph, name, cfg)
+}
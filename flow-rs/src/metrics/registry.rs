@@ -0,0 +1,236 @@
+/**
+ * \file flow-rs/src/metrics/registry.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::channel::ChannelStats;
+use crate::config::interlayer as config;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// upper bounds of histogram buckets in seconds
+const BUCKETS: &[f64] = &[
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+lazy_static::lazy_static! {
+    pub(super) static ref REGISTRY: Mutex<Registry> = Default::default();
+}
+
+pub struct Histogram {
+    buckets: Vec<AtomicU64>,
+    count: AtomicU64,
+    sum: AtomicU64, // nanoseconds
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            buckets: BUCKETS.iter().map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&self, dur: Duration) {
+        let secs = dur.as_secs_f64();
+        for (bound, bucket) in BUCKETS.iter().zip(self.buckets.iter()) {
+            if secs <= *bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(dur.as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+struct ChannelEntry {
+    from: String,
+    to: String,
+    stats: Arc<ChannelStats>,
+}
+
+#[derive(Default)]
+pub(super) struct Registry {
+    // (graph, connection)
+    channels: BTreeMap<(String, String), ChannelEntry>,
+    // (graph, node)
+    nodes: BTreeMap<(String, String), Arc<Histogram>>,
+}
+
+fn ports(ports: &[config::Port]) -> String {
+    ports
+        .iter()
+        .map(|p| format!("{}:{}", p.node_name, p.port_name))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Registry {
+    pub fn node(&mut self, graph: &str, node: &str) -> Arc<Histogram> {
+        self.nodes
+            .entry((graph.to_owned(), node.to_owned()))
+            .or_default()
+            .clone()
+    }
+
+    pub fn channel(
+        &mut self,
+        graph: &str,
+        name: &str,
+        cfg: &config::Connection,
+    ) -> Arc<ChannelStats> {
+        self.channels
+            .entry((graph.to_owned(), name.to_owned()))
+            .or_insert_with(|| ChannelEntry {
+                from: ports(&cfg.tx),
+                to: ports(&cfg.rx),
+                stats: Default::default(),
+            })
+            .stats
+            .clone()
+    }
+
+    /// Render all metrics in the prometheus text format
+    #[cfg_attr(not(feature = "metrics"), allow(dead_code))]
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let channel_metrics: &[(&str, &str, &str, fn(&ChannelStats) -> String)] = &[
+            (
+                "megflow_channel_depth",
+                "gauge",
+                "Number of envelopes waiting in the connection",
+                |s| s.queued().to_string(),
+            ),
+            (
+                "megflow_channel_sent_total",
+                "counter",
+                "Number of envelopes sent into the connection",
+                |s| s.sent().to_string(),
+            ),
+            (
+                "megflow_channel_received_total",
+                "counter",
+                "Number of envelopes received from the connection",
+                |s| s.received().to_string(),
+            ),
+            (
+                "megflow_channel_expired_total",
+                "counter",
+                "Number of expired envelopes dropped or diverted by receivers",
+                |s| s.expired().to_string(),
+            ),
+            (
+                "megflow_channel_dropped_total",
+                "counter",
+                "Number of envelopes dropped by the overflow policy of the full connection",
+                |s| s.dropped().to_string(),
+            ),
+            (
+                "megflow_channel_blocked_seconds_total",
+                "counter",
+                "Time spent by senders waiting on the full connection",
+                |s| s.blocked().as_secs_f64().to_string(),
+            ),
+        ];
+        for (name, ty, help, value) in channel_metrics {
+            writeln!(out, "# HELP {} {}", name, help).unwrap();
+            writeln!(out, "# TYPE {} {}", name, ty).unwrap();
+            for ((graph, conn), entry) in &self.channels {
+                writeln!(
+                    out,
+                    "{}{{graph=\"{}\",connection=\"{}\",from=\"{}\",to=\"{}\"}} {}",
+                    name,
+                    escape(graph),
+                    escape(conn),
+                    escape(&entry.from),
+                    escape(&entry.to),
+                    value(&entry.stats)
+                )
+                .unwrap();
+            }
+        }
+
+        let name = "megflow_node_exec_seconds";
+        writeln!(out, "# HELP {} Latency of the node exec", name).unwrap();
+        writeln!(out, "# TYPE {} histogram", name).unwrap();
+        for ((graph, node), histogram) in &self.nodes {
+            let labels = format!("graph=\"{}\",node=\"{}\"", escape(graph), escape(node));
+            for (bound, bucket) in BUCKETS.iter().zip(histogram.buckets.iter()) {
+                writeln!(
+                    out,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    name,
+                    labels,
+                    bound,
+                    bucket.load(Ordering::Relaxed)
+                )
+                .unwrap();
+            }
+            let count = histogram.count.load(Ordering::Relaxed);
+            let sum = Duration::from_nanos(histogram.sum.load(Ordering::Relaxed));
+            writeln!(out, "{}_bucket{{{},le=\"+Inf\"}} {}", name, labels, count).unwrap();
+            writeln!(out, "{}_sum{{{}}} {}", name, labels, sum.as_secs_f64()).unwrap();
+            writeln!(out, "{}_count{{{}}} {}", name, labels, count).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let mut registry = Registry::default();
+        let histogram = registry.node("g", "a");
+        histogram.observe(Duration::from_millis(3));
+        histogram.observe(Duration::from_secs(10));
+        let cfg = config::Connection {
+            cap: 1,
+            tx: vec![],
+            rx: vec![],
+            ttl: None,
+            expired: None,
+            idle_timeout: None,
+            max_instances: None,
+            admission: Default::default(),
+            pool_size: None,
+            policy: Default::default(),
+            priority: false,
+        };
+        registry.channel("g", "c", &cfg);
+
+        let out = registry.render();
+        assert!(
+            out.contains("megflow_channel_depth{graph=\"g\",connection=\"c\",from=\"\",to=\"\"} 0")
+        );
+        assert!(
+            out.contains("megflow_node_exec_seconds_bucket{graph=\"g\",node=\"a\",le=\"0.001\"} 0")
+        );
+        assert!(
+            out.contains("megflow_node_exec_seconds_bucket{graph=\"g\",node=\"a\",le=\"0.005\"} 1")
+        );
+        assert!(
+            out.contains("megflow_node_exec_seconds_bucket{graph=\"g\",node=\"a\",le=\"+Inf\"} 2")
+        );
+        assert!(out.contains("megflow_node_exec_seconds_count{graph=\"g\",node=\"a\"} 2"));
+    }
+}
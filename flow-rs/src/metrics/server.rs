@@ -0,0 +1,44 @@
+/**
+ * \file flow-rs/src/metrics/server.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::registry::REGISTRY;
+use crate::config::presentation::Metrics;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use warp::Filter;
+
+lazy_static::lazy_static! {
+    static ref SERVING: Mutex<HashSet<u16>> = Default::default();
+}
+
+/// Serve the metrics on `0.0.0.0:port/path`, only one server is started for each port
+pub(crate) fn serve(cfg: &Metrics) {
+    if !SERVING.lock().unwrap().insert(cfg.port) {
+        return;
+    }
+    let mut route = warp::any().boxed();
+    for segment in cfg.path.split('/').filter(|s| !s.is_empty()) {
+        route = route.and(warp::path(segment.to_owned())).boxed();
+    }
+    let route = route.and(warp::path::end()).and(warp::get()).map(|| {
+        warp::reply::with_header(
+            REGISTRY.lock().unwrap().render(),
+            "content-type",
+            "text/plain; version=0.0.4",
+        )
+    });
+    let port = cfg.port;
+    crate::rt::task::spawn(async move {
+        match warp::serve(route).try_bind_ephemeral(([0, 0, 0, 0], port)) {
+            Ok((_, server)) => server.await,
+            Err(err) => log::error!("metrics server failed to listen on {}: {}", port, err),
+        }
+    });
+}
@@ -0,0 +1,195 @@
+/**
+ * \file flow-rs/src/node/admission.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::channel::ChannelStorage;
+use crate::config::presentation::AdmissionPolicy;
+use anyhow::Result;
+use event_listener::Event;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// The error of `DynPorts::create`, when `max_instances` dynamic subgraphs are alive and the admission
+/// policy of the connection is `reject`
+#[derive(Clone, Debug)]
+pub struct TooManyInstances {
+    pub topic: String,
+    pub max: usize,
+}
+
+impl fmt::Display for TooManyInstances {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "too many instances of dynamic subgraph {}, at most {}",
+            self.topic, self.max
+        )
+    }
+}
+
+impl std::error::Error for TooManyInstances {}
+
+struct Slot {
+    key: u64,
+    inputs: Vec<ChannelStorage>,
+    created: Instant,
+    evicted: bool,
+    reaped: Arc<Mutex<Vec<u64>>>,
+}
+
+impl Slot {
+    // when an envelope is sent into the instance last time, or when it is created
+    fn used(&self) -> Instant {
+        self.inputs
+            .iter()
+            .filter_map(|input| input.stats().last_sent())
+            .fold(self.created, std::cmp::max)
+    }
+}
+
+/// Instances of dynamic subgraphs created through a dyn connection, shared by all instances of the creating node
+pub(crate) struct Admission {
+    max: usize,
+    policy: AdmissionPolicy,
+    next_id: AtomicU64,
+    slots: Mutex<HashMap<u64, Slot>>,
+    event: Event,
+}
+
+impl Admission {
+    pub fn new(max: usize, policy: AdmissionPolicy) -> Admission {
+        Admission {
+            max,
+            policy,
+            next_id: AtomicU64::new(0),
+            slots: Default::default(),
+            event: Event::new(),
+        }
+    }
+
+    /// Take a slot for the instance `key` with `inputs`, and return its id which must be released
+    /// when the instance is finished.
+    ///
+    /// Keys of evicted instances are pushed into `reaped` of the ports which created them.
+    pub async fn acquire(
+        &self,
+        topic: &str,
+        key: u64,
+        inputs: Vec<ChannelStorage>,
+        reaped: Arc<Mutex<Vec<u64>>>,
+    ) -> Result<u64> {
+        loop {
+            let listener = self.event.listen();
+            {
+                let mut slots = self.slots.lock().unwrap();
+                if alive(&slots) >= self.max {
+                    match self.policy {
+                        AdmissionPolicy::Wait => (),
+                        AdmissionPolicy::Reject => {
+                            return Err(TooManyInstances {
+                                topic: topic.to_owned(),
+                                max: self.max,
+                            }
+                            .into())
+                        }
+                        AdmissionPolicy::Evict => evict(topic, &mut slots),
+                    }
+                }
+                if alive(&slots) < self.max {
+                    let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                    let slot = Slot {
+                        key,
+                        created: Instant::now(),
+                        evicted: false,
+                        inputs,
+                        reaped,
+                    };
+                    slots.insert(id, slot);
+                    return Ok(id);
+                }
+            }
+            listener.await;
+        }
+    }
+
+    pub fn release(&self, id: u64) {
+        self.slots.lock().unwrap().remove(&id);
+        self.event.notify(usize::MAX);
+    }
+}
+
+fn alive(slots: &HashMap<u64, Slot>) -> usize {
+    slots.values().filter(|slot| !slot.evicted).count()
+}
+
+// close the least recently used instance
+fn evict(topic: &str, slots: &mut HashMap<u64, Slot>) {
+    let lru = slots
+        .iter_mut()
+        .filter(|(_, slot)| !slot.evicted)
+        .min_by_key(|(id, slot)| (slot.used(), **id));
+    if let Some((_, slot)) = lru {
+        log::warn!(
+            "dynamic subgraph {} instance {} is evicted for a new one",
+            topic,
+            slot.key
+        );
+        for input in &slot.inputs {
+            input.close();
+        }
+        slot.evicted = true;
+        slot.reaped.lock().unwrap().push(slot.key);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::channel::ChannelBase;
+
+    #[flow_rs::rt::test]
+    async fn test_evict() -> Result<()> {
+        let admission = Admission::new(2, AdmissionPolicy::Evict);
+        let reaped: Arc<Mutex<Vec<u64>>> = Default::default();
+        let channels: Vec<_> = (0..3).map(|_| ChannelStorage::bound(4)).collect();
+        for (key, channel) in channels.iter().enumerate().take(2) {
+            let inputs = vec![channel.clone()];
+            admission
+                .acquire("sub", key as u64, inputs, reaped.clone())
+                .await?;
+        }
+        // the instance 0 is used later than the instance 1
+        let sender = channels[0].sender();
+        sender.send(crate::envelope::Envelope::new(0)).await.ok();
+        let inputs = vec![channels[2].clone()];
+        admission.acquire("sub", 2, inputs, reaped.clone()).await?;
+        assert_eq!(*reaped.lock().unwrap(), vec![1]);
+        assert!(channels[1].is_closed());
+        assert!(!channels[0].is_closed());
+        Ok(())
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_reject() -> Result<()> {
+        let admission = Admission::new(1, AdmissionPolicy::Reject);
+        let reaped: Arc<Mutex<Vec<u64>>> = Default::default();
+        let id = admission.acquire("sub", 0, vec![], reaped.clone()).await?;
+        let err = admission
+            .acquire("sub", 1, vec![], reaped.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<TooManyInstances>().unwrap().max, 1);
+        admission.release(id);
+        admission.acquire("sub", 1, vec![], reaped).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+/**
+ * \file flow-rs/src/node/balance.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::config::presentation::BalancePolicy;
+use anyhow::Result;
+use flow_rs::prelude::*;
+
+/// Dispatch envelopes from an input of a node into the queues of its instances
+#[inputs(inp)]
+#[outputs(out:{})]
+#[derive(Node, Actor, Default)]
+struct Balance {
+    policy: BalancePolicy,
+    next: usize,
+    // closed when the dispatcher is finished, as restarting instances hold their queues open
+    replicas: Vec<ChannelStorage>,
+}
+
+/// Create a dispatcher from `source` into `replicas`, the queues of instances in order
+pub(crate) fn dispatcher(
+    policy: BalancePolicy,
+    source: &ChannelStorage,
+    replicas: &[ChannelStorage],
+) -> Box<dyn Actor> {
+    let mut balance = Box::new(Balance {
+        policy,
+        replicas: replicas.to_vec(),
+        ..Default::default()
+    });
+    balance.set_port("inp", None, source);
+    for (i, replica) in replicas.iter().enumerate() {
+        balance.set_port("{out}", Some(i as u64), replica);
+    }
+    balance
+}
+
+impl Balance {
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {
+        for replica in &self.replicas {
+            replica.close();
+        }
+    }
+
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(mut msg) = self.inp.recv_any().await {
+            let n = self.replicas.len();
+            match (self.policy, msg.info().from_addr) {
+                (BalancePolicy::ConsistentHash, Some(from_addr)) => {
+                    let i = jump_hash(from_addr, n);
+                    if self.out[&(i as u64)].send_any(msg).await.is_err() {
+                        log::warn!(
+                            "envelope from {} is dropped, instance {} is finished",
+                            from_addr,
+                            i
+                        );
+                        self.replicas[i].stats().on_discarded();
+                    }
+                }
+                _ => {
+                    // skip instances which are finished
+                    for _ in 0..n {
+                        let i = self.next as u64;
+                        self.next = (self.next + 1) % n;
+                        match self.out[&i].send_any(msg).await {
+                            Ok(_) => break,
+                            Err(err) => msg = err.0,
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Jump consistent hash, which moves the fewest keys when the number of buckets changes
+fn jump_hash(mut key: u64, buckets: usize) -> usize {
+    let (mut b, mut j) = (-1i64, 0i64);
+    while j < buckets as i64 {
+        b = j;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        j = ((b + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as i64;
+    }
+    b as usize
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_jump_hash() {
+        for key in 0..100 {
+            let i = jump_hash(key, 4);
+            assert!(i < 4);
+            assert_eq!(i, jump_hash(key, 4));
+            // keys are moved only into the new bucket
+            let j = jump_hash(key, 5);
+            assert!(j == i || j == 4);
+        }
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_consistent_hash() {
+        let source = ChannelStorage::bound(8);
+        let replicas: Vec<_> = (0..3).map(|_| ChannelStorage::bound(8)).collect();
+        let ctx = crate::graph::context("test".to_owned(), "Balance".to_owned(), 0);
+        let handle = dispatcher(BalancePolicy::ConsistentHash, &source, &replicas)
+            .start(ctx, Default::default());
+        let sender = source.sender();
+        for i in 0..6 {
+            let mut envelope = Envelope::new(i).seal();
+            envelope.info_mut().from_addr = Some(i % 2);
+            sender.send_any(envelope).await.ok();
+        }
+        sender.close();
+        handle.await.unwrap();
+        // all envelopes of a stream are dispatched into the same instance
+        for (i, replica) in replicas.iter().enumerate() {
+            assert!(replica.is_closed());
+            let receiver = replica.receiver();
+            while let Ok(envelope) = receiver.recv::<u64>().await {
+                assert_eq!(jump_hash(envelope.info().from_addr.unwrap(), 3), i);
+            }
+        }
+    }
+    #[flow_rs::rt::test]
+    async fn test_finished_instance() {
+        let source = ChannelStorage::bound(8);
+        let replicas: Vec<_> = (0..2).map(|_| ChannelStorage::bound(8)).collect();
+        replicas[jump_hash(0, 2)].close();
+        let ctx = crate::graph::context("test".to_owned(), "Balance".to_owned(), 0);
+        let handle = dispatcher(BalancePolicy::ConsistentHash, &source, &replicas)
+            .start(ctx, Default::default());
+        let sender = source.sender();
+        let mut envelope = Envelope::new(0).seal();
+        envelope.info_mut().from_addr = Some(0);
+        sender.send_any(envelope).await.ok();
+        sender.close();
+        handle.await.unwrap();
+        // envelopes dispatched into a finished instance are counted as dropped
+        assert_eq!(replicas[jump_hash(0, 2)].stats().dropped(), 1);
+    }
+}
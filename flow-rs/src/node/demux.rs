@@ -10,6 +10,7 @@
  */
 use anyhow::Result;
 use flow_rs::prelude::*;
+use futures_util::FutureExt;
 use rt::task::JoinHandle;
 use std::collections::HashMap;
 use toml::value::Table;
@@ -19,6 +20,8 @@ use toml::value::Table;
 #[derive(Node, Actor, Default)]
 struct DynDemux {
     tasks: HashMap<u64, JoinHandle<Result<()>>>,
+    // subgraphs closed by the idle timeout of `out`
+    reaped: Vec<JoinHandle<Result<()>>>,
     resources: Option<ResourceCollection>,
 }
 
@@ -34,10 +37,26 @@ impl DynDemux {
         for (_, task) in std::mem::take(&mut self.tasks) {
             task.await.ok();
         }
+        for task in std::mem::take(&mut self.reaped) {
+            task.await.ok();
+        }
     }
 
     async fn exec(&mut self, _: &Context) -> Result<()> {
         if let Ok(msg) = self.inp.recv_any().await {
+            for id in self.out.reap() {
+                if let Some(task) = self.tasks.remove(&id) {
+                    self.reaped.push(task);
+                }
+            }
+            // only subgraphs still finalizing are kept, so the handles do not pile up
+            self.reaped = std::mem::take(&mut self.reaped)
+                .into_iter()
+                .filter_map(|mut task| match (&mut task).now_or_never() {
+                    Some(_) => None,
+                    None => Some(task),
+                })
+                .collect();
             let id = msg
                 .info()
                 .to_addr
@@ -50,27 +69,23 @@ impl DynDemux {
                 }
             } else {
                 if !self.out.cache().contains_key(&id) {
-                    if let Some(tag) = tag {
-                        self.tasks.insert(
-                            id,
-                            self.out
-                                .create_spec(
-                                    id,
-                                    tag,
-                                    self.resources.clone().unwrap(),
-                                    Default::default(),
-                                )
-                                .await
-                                .expect("create subgraph fault"),
-                        );
+                    let resources = self.resources.clone().unwrap();
+                    let task = if let Some(tag) = tag {
+                        self.out
+                            .create_spec(id, tag, resources, Default::default())
+                            .await
                     } else {
-                        self.tasks.insert(
-                            id,
-                            self.out
-                                .create(id, self.resources.clone().unwrap(), Default::default())
-                                .await
-                                .expect("create subgraph fault"),
-                        );
+                        self.out.create(id, resources, Default::default()).await
+                    };
+                    match task {
+                        Ok(task) => {
+                            self.tasks.insert(id, task);
+                        }
+                        Err(err) if err.is::<TooManyInstances>() => {
+                            log::warn!("envelope to {} is dropped, {}", id, err);
+                            return Ok(());
+                        }
+                        Err(err) => panic!("create subgraph fault: {:?}", err),
                     }
                 }
                 let out = self.out.fetch_with_cache().await.get(&id).unwrap();
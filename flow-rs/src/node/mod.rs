@@ -8,13 +8,21 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
+mod admission;
+mod balance;
 mod bcast;
 mod demux;
 mod noop;
+mod pause;
+mod pool;
 mod port;
 mod reorder;
+mod router;
 mod shared;
+mod stop;
 mod transform;
+mod window;
+mod zip;
 
 use crate::channel::ChannelStorage;
 use crate::graph::Context;
@@ -22,10 +30,18 @@ use crate::graph::GraphSlice;
 use crate::registry::Collect;
 use crate::resource::ResourceCollection;
 use crate::rt::task::JoinHandle;
+pub(crate) use admission::Admission;
+pub use admission::TooManyInstances;
 use anyhow::{anyhow, Result};
+pub(crate) use balance::dispatcher;
+pub use pause::PauseToken;
+pub(crate) use pause::{with_pause_token, Gate};
+pub(crate) use pool::Pool;
 pub use port::*;
 pub(crate) use shared::*;
 use std::collections::BTreeSet;
+pub(crate) use stop::with_stop_token;
+pub use stop::StopToken;
 use toml::value::Table;
 
 #[doc(hidden)]
@@ -60,21 +76,25 @@ pub(crate) fn load_static(
     local_key: u64,
     config: &crate::config::interlayer::Node,
 ) -> Result<Vec<Box<dyn Actor>>> {
+    (0..config.cloned.unwrap_or(1))
+        .into_iter()
+        .map(|_| load_one(local_key, config))
+        .collect()
+}
+
+pub(crate) fn load_one(
+    local_key: u64,
+    config: &crate::config::interlayer::Node,
+) -> Result<Box<dyn Actor>> {
     if config.entity.ty.len() > 1 {
         return Err(anyhow!("static subgraph/node dont support dyn type"));
     }
     let ty = config.entity.ty.first().unwrap();
     if let Some(node) = NodeSlice::registry_local().get(local_key).get(ty) {
-        Ok((0..config.cloned.unwrap_or(1))
-            .into_iter()
-            .map(|_| (node.cons)(config.entity.name.clone(), &config.entity.args))
-            .collect())
+        Ok((node.cons)(config.entity.name.clone(), &config.entity.args))
     } else if let Some(graph) = GraphSlice::registry_local().get(local_key).get(ty) {
-        (0..config.cloned.unwrap_or(1))
-            .into_iter()
-            .map(|_| (graph.cons)(config.entity.name.clone(), &config.entity.args))
-            .map(|g| g.map(|g| Box::new(g) as Box<dyn Actor>))
-            .collect()
+        (graph.cons)(config.entity.name.clone(), &config.entity.args)
+            .map(|g| Box::new(g) as Box<dyn Actor>)
     } else {
         Err(anyhow!("unexpected node type {:?}", config.entity.ty))
     }
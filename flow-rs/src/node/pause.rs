@@ -0,0 +1,92 @@
+/**
+ * \file flow-rs/src/node/pause.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::registry::Collect;
+use event_listener::Event;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    static CURRENT: RefCell<Option<PauseToken>> = RefCell::new(None);
+}
+
+/// A switch shared by all instances of a node, or of a graph, in a graph load
+#[derive(Default)]
+pub(crate) struct Gate {
+    paused: AtomicBool,
+    event: Event,
+}
+crate::collect!(String, Gate);
+
+impl Gate {
+    /// The gate `key` of the graph load `local_key`, which is `graph` for a graph or `graph/node` for a node
+    pub fn get(local_key: u64, key: &str) -> Arc<Gate> {
+        Gate::registry_local()
+            .get(local_key)
+            .get_or_insert_with(key.to_owned(), Default::default)
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.event.notify(usize::MAX);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+/// A token to hold a node instance before its next exec, while the node or any graph containing it is paused
+#[derive(Clone, Default)]
+pub struct PauseToken(Vec<Arc<Gate>>);
+
+impl PauseToken {
+    /// The token of the node being started in the current thread, must be called in `Actor::start`
+    /// before the actor is spawned
+    pub fn current() -> PauseToken {
+        CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    pub(crate) fn with(&self, gate: Arc<Gate>) -> PauseToken {
+        let mut gates = self.0.clone();
+        gates.push(gate);
+        PauseToken(gates)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.iter().any(|gate| gate.is_paused())
+    }
+
+    /// Wait until the node and all graphs containing it are resumed
+    pub async fn wait(&self) {
+        while let Some(gate) = self.0.iter().find(|gate| gate.is_paused()) {
+            let listener = gate.event.listen();
+            if gate.is_paused() {
+                listener.await;
+            }
+        }
+    }
+}
+
+/// Call `f`, which starts a node instance controlled by `token`
+pub(crate) fn with_pause_token<F, R>(token: &PauseToken, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let prev = CURRENT.with(|current| current.replace(Some(token.clone())));
+    let ret = f();
+    CURRENT.with(|current| current.replace(prev));
+    ret
+}
@@ -0,0 +1,81 @@
+/**
+ * \file flow-rs/src/node/pool.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::graph::{Graph, GraphSlice};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use toml::value::Table;
+
+#[derive(Default)]
+struct Warm {
+    ready: Vec<(Arc<GraphSlice>, Graph)>,
+    building: usize,
+}
+
+/// Dynamic subgraphs built ahead of time for a dyn connection, shared by all instances of the creating node.
+///
+/// An instance can not be reused after it is finished, as its nodes are consumed by their actors, so the pool
+/// is refilled in the background whenever an instance is taken.
+pub(crate) struct Pool {
+    size: usize,
+    args: Table,
+    topics: Mutex<HashMap<String, Warm>>,
+}
+
+impl Pool {
+    pub fn new(size: usize, args: Table) -> Pool {
+        Pool {
+            size,
+            args,
+            topics: Default::default(),
+        }
+    }
+
+    /// Take a subgraph of `topic` built by `slice`, and build another one in the background
+    pub fn take(self: &Arc<Self>, topic: &str, slice: &Arc<GraphSlice>) -> Option<Graph> {
+        let graph = {
+            let mut topics = self.topics.lock().unwrap();
+            let warm = topics.entry(topic.to_owned()).or_default();
+            // subgraphs built before a reload are dropped
+            warm.ready.retain(|(built, _)| Arc::ptr_eq(built, slice));
+            warm.ready.pop().map(|(_, graph)| graph)
+        };
+        self.fill(topic, slice);
+        graph
+    }
+
+    /// Build subgraphs of `topic` in the background, until there are `size` ones
+    pub fn fill(self: &Arc<Self>, topic: &str, slice: &Arc<GraphSlice>) {
+        let n = {
+            let mut topics = self.topics.lock().unwrap();
+            let warm = topics.entry(topic.to_owned()).or_default();
+            let n = self.size.saturating_sub(warm.ready.len() + warm.building);
+            warm.building += n;
+            n
+        };
+        for _ in 0..n {
+            let pool = self.clone();
+            let topic = topic.to_owned();
+            let slice = slice.clone();
+            crate::rt::task::spawn_blocking(move || {
+                let graph = (slice.cons)(format!("{}_instance", topic), &pool.args);
+                let mut topics = pool.topics.lock().unwrap();
+                let warm = topics.get_mut(&topic).unwrap();
+                warm.building -= 1;
+                match graph {
+                    Ok(graph) => warm.ready.push((slice, graph)),
+                    Err(err) => {
+                        log::error!("failed to build dynamic subgraph {}: {:#}", topic, err)
+                    }
+                }
+            });
+        }
+    }
+}
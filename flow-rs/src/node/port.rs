@@ -10,6 +10,7 @@
  */
 use crate::broker::BrokerClient;
 use crate::channel::{ChannelStorage, Receiver, Sender};
+use crate::config::presentation::OverflowPolicy;
 use crate::config::table::merge_table;
 use crate::future::select_ok;
 use crate::graph::GraphSlice;
@@ -20,9 +21,11 @@ use crate::rt::task::JoinHandle;
 use anyhow::{anyhow, Result};
 use std::collections::HashMap;
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use toml::value::Table;
 
-use super::Node;
+use super::{Admission, Node, Pool};
 
 #[derive(Clone)]
 struct DynConns {
@@ -35,6 +38,11 @@ pub struct DynPortsConfig {
     pub(crate) local_key: u64,
     pub(crate) target: String,
     pub(crate) cap: usize,
+    pub(crate) policy: OverflowPolicy,
+    pub(crate) priority: bool,
+    pub(crate) idle_timeout: Option<Duration>,
+    pub(crate) admission: Option<Arc<Admission>>,
+    pub(crate) pool: Option<Arc<Pool>>,
     pub(crate) brokers: Vec<BrokerClient>,
     pub(crate) args: Table,
 }
@@ -44,29 +52,58 @@ pub struct DynPorts<V> {
     local_key: u64,
     target: String,
     cap: usize,
+    policy: OverflowPolicy,
+    priority: bool,
+    idle_timeout: Option<Duration>,
+    admission: Option<Arc<Admission>>,
+    pool: Option<Arc<Pool>>,
     brokers: HashMap<String, BrokerClient>,
     cache: HashMap<u64, V>,
+    reaped: Arc<Mutex<Vec<u64>>>,
     args: Table,
     _v_holder: PhantomData<V>,
 }
 
 impl<V> DynPorts<V> {
     pub fn new(config: DynPortsConfig) -> DynPorts<V> {
+        if let Some(pool) = &config.pool {
+            let slices = GraphSlice::registry_local().get(config.local_key);
+            for broker in &config.brokers {
+                if let Some(slice) = slices.get(broker.topic()) {
+                    pool.fill(broker.topic(), &slice);
+                }
+            }
+        }
         DynPorts {
             local_key: config.local_key,
             target: config.target,
             cap: config.cap,
+            policy: config.policy,
+            priority: config.priority,
+            idle_timeout: config.idle_timeout,
+            admission: config.admission,
+            pool: config.pool,
             brokers: config
                 .brokers
                 .into_iter()
                 .map(|x| (x.topic().to_owned(), x))
                 .collect(),
             cache: HashMap::new(),
+            reaped: Default::default(),
             args: config.args,
             _v_holder: Default::default(),
         }
     }
 
+    fn make(&self) -> ChannelStorage {
+        let channel = if self.priority {
+            ChannelStorage::bound_priority(self.cap)
+        } else {
+            ChannelStorage::bound(self.cap)
+        };
+        channel.with_policy(self.policy)
+    }
+
     pub async fn create(
         &mut self,
         key: u64,
@@ -102,25 +139,63 @@ impl<V> DynPorts<V> {
             .get(self.local_key)
             .get(broker.topic())
         {
-            let args = merge_table(self.args.clone(), args);
-            let mut g = (slice.cons)(format!("{}_instance", broker.topic()), &args)?;
+            let warm = match &self.pool {
+                // subgraphs in the pool are built with the default args
+                Some(pool) if args.is_empty() => pool.take(broker.topic(), &slice),
+                _ => None,
+            };
+            let mut g = match warm {
+                Some(g) => g,
+                None => {
+                    let args = merge_table(self.args.clone(), args);
+                    (slice.cons)(format!("{}_instance", broker.topic()), &args)?
+                }
+            };
 
             let mut inputs = HashMap::new();
             let mut outputs = HashMap::new();
+            let mut channels = vec![];
 
             for input in &slice.info.inputs {
-                let channel = ChannelStorage::bound(self.cap);
+                let channel = self.make();
                 g.set_port(input.as_str(), None, &channel);
                 inputs.insert(input.clone(), channel.sender());
+                channels.push(channel);
             }
 
             for output in &slice.info.outputs {
-                let channel = ChannelStorage::bound(self.cap);
+                let channel = self.make();
                 g.set_port(output.as_str(), None, &channel);
                 outputs.insert(output.clone(), channel.receiver());
             }
 
-            let handle = g.start(Some(resource));
+            let slot = match &self.admission {
+                Some(admission) => {
+                    let reaped = self.reaped.clone();
+                    let id = admission
+                        .acquire(broker.topic(), key, channels.clone(), reaped)
+                        .await?;
+                    Some((admission.clone(), id))
+                }
+                None => None,
+            };
+            let mut handle = g.start_dyn(broker.topic(), key, resource);
+            if let Some((admission, id)) = slot {
+                handle = crate::rt::task::spawn(async move {
+                    let ret = handle.await;
+                    admission.release(id);
+                    ret
+                });
+            }
+            if let Some(timeout) = self.idle_timeout {
+                crate::rt::task::spawn(watch_idle(
+                    broker.topic().to_owned(),
+                    key,
+                    channels,
+                    timeout,
+                    self.reaped.clone(),
+                ));
+            }
 
             broker
                 .publish(DynConns {
@@ -136,6 +211,42 @@ impl<V> DynPorts<V> {
     }
 }
 
+// close the instance `key` when no envelope is sent into its inputs within `timeout`
+async fn watch_idle(
+    topic: String,
+    key: u64,
+    inputs: Vec<ChannelStorage>,
+    timeout: Duration,
+    reaped: Arc<Mutex<Vec<u64>>>,
+) {
+    let sent = || inputs.iter().map(|input| input.stats().sent()).sum::<u64>();
+    let mut last = sent();
+    let mut active = Instant::now();
+    loop {
+        crate::rt::task::sleep(timeout.saturating_sub(active.elapsed())).await;
+        if inputs.iter().all(|input| input.is_closed()) {
+            return;
+        }
+        let n = sent();
+        if n != last {
+            last = n;
+            active = Instant::now();
+        } else if active.elapsed() >= timeout {
+            log::warn!(
+                "dynamic subgraph {} instance {} received nothing in {:?}, closed",
+                topic,
+                key,
+                timeout
+            );
+            for input in &inputs {
+                input.close();
+            }
+            reaped.lock().unwrap().push(key);
+            return;
+        }
+    }
+}
+
 impl DynPorts<Receiver> {
     pub fn try_fetch(&self) -> Option<(u64, Receiver)> {
         for broker in self.brokers.values() {
@@ -242,6 +353,22 @@ impl DynPorts<Sender> {
         &self.cache
     }
 
+    /// Remove ports of instances closed by the idle timeout or evicted by the admission policy from the cache,
+    /// and return their keys
+    pub fn reap(&mut self) -> Vec<u64> {
+        let keys = std::mem::take(&mut *self.reaped.lock().unwrap());
+        keys.into_iter()
+            .filter(|key| match self.cache.get(key) {
+                // the key may be reused by a new instance
+                Some(port) if !port.is_closed() => false,
+                _ => {
+                    self.cache.remove(key);
+                    true
+                }
+            })
+            .collect()
+    }
+
     pub fn close(&self) {
         for broker in self.brokers.values() {
             broker.close();
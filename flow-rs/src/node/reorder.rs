@@ -8,79 +8,241 @@
  * software distributed under the License is distributed on an
  * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
  */
+use crate::rt;
 use anyhow::Result;
 use flow_rs::prelude::*;
-use std::collections::BTreeMap;
+use futures_util::{pin_mut, select, FutureExt};
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 use toml::value::Table;
 
+#[derive(Default)]
+struct Stream {
+    cache: BTreeMap<u64, SealedEnvelope>,
+    seq_id: u64,
+    // since when the stream waits for the envelope `seq_id`
+    waiting: Option<Instant>,
+}
+
+impl Stream {
+    // take cached envelopes in order from `seq_id`
+    fn pop(&mut self) -> Vec<SealedEnvelope> {
+        let mut ready = vec![];
+        while let Some(msg) = self.cache.remove(&self.seq_id) {
+            ready.push(msg);
+            self.seq_id += 1;
+        }
+        ready
+    }
+
+    // give up envelopes missing before the first cached one
+    fn skip(&mut self, key: Option<u64>) {
+        if let Some(&id) = self.cache.keys().next() {
+            log::warn!(
+                "envelopes {}..{} of stream {:?} are missing in reorder, and skipped",
+                self.seq_id,
+                id,
+                key
+            );
+            self.seq_id = id;
+        }
+    }
+
+    fn drain(&mut self, key: Option<u64>) -> Vec<SealedEnvelope> {
+        let mut ready = self.pop();
+        while !self.cache.is_empty() {
+            self.skip(key);
+            ready.extend(self.pop());
+        }
+        self.waiting = None;
+        ready
+    }
+}
+
+/// Emit envelopes in the order of `partial_id`, which starts from 0.
+///
+/// Sequences of different `from_addr` are reordered separately if `by_addr` is true, and a sequence is restarted
+/// after an empty envelope without `partial_id` of its `from_addr`. Missing envelopes are skipped after waiting
+/// for `timeout_ms`, or when more than `max_buffer` envelopes are cached, and late envelopes are dropped.
 #[inputs(inp)]
 #[outputs(out)]
 #[derive(Node, Actor, Default)]
 struct Reorder {
-    cache: BTreeMap<u64, SealedEnvelope>,
-    seq_id: u64,
+    by_addr: bool,
+    timeout: Option<Duration>,
+    max_buffer: Option<usize>,
+    streams: HashMap<Option<u64>, Stream>,
 }
 
 impl Reorder {
-    fn new(_: String, _: &Table) -> Reorder {
-        Default::default()
+    fn new(name: String, args: &Table) -> Reorder {
+        let by_addr = args
+            .get("by_addr")
+            .map(|by_addr| {
+                by_addr
+                    .as_bool()
+                    .unwrap_or_else(|| panic!("by_addr of node {} must be a boolean", name))
+            })
+            .unwrap_or(false);
+        let timeout = args.get("timeout_ms").map(|timeout| {
+            let timeout = timeout
+                .as_integer()
+                .filter(|&timeout| timeout > 0)
+                .unwrap_or_else(|| {
+                    panic!("timeout_ms of node {} must be a positive integer", name)
+                });
+            Duration::from_millis(timeout as u64)
+        });
+        let max_buffer = args.get("max_buffer").map(|max_buffer| {
+            max_buffer
+                .as_integer()
+                .filter(|&max_buffer| max_buffer >= 0)
+                .unwrap_or_else(|| {
+                    panic!("max_buffer of node {} must be a non-negative integer", name)
+                }) as usize
+        });
+        Reorder {
+            by_addr,
+            timeout,
+            max_buffer,
+            ..Default::default()
+        }
     }
 
     async fn initialize(&mut self, _: ResourceCollection) {}
     async fn finalize(&mut self) {}
 
     async fn exec(&mut self, _: &Context) -> Result<()> {
-        if let Ok(msg) = self.inp.recv_any().await {
-            let id = msg
-                .info()
-                .partial_id
-                .expect("partial_id required by reorder");
-            assert!(id >= self.seq_id);
-            if id == self.seq_id {
-                self.seq_id += 1;
-                self.out.send_any(msg).await.ok();
-            } else {
-                self.cache.insert(id, msg);
+        self.expire().await;
+        let deadline = self.timeout.and_then(|timeout| {
+            self.streams
+                .values()
+                .filter_map(|stream| stream.waiting)
+                .min()
+                .map(|waiting| waiting + timeout)
+        });
+        let msg = if let Some(deadline) = deadline {
+            let recv = self.inp.recv_any().fuse();
+            let timeout =
+                rt::task::sleep(deadline.saturating_duration_since(Instant::now())).fuse();
+            pin_mut!(recv, timeout);
+            select! {
+                msg = recv => Some(msg),
+                // missing envelopes are skipped by the next `expire`
+                _ = timeout => None,
             }
-
-            let mut stop = self.seq_id;
-            for &id in self.cache.keys() {
-                assert!(id >= self.seq_id);
-                if id == self.seq_id {
-                    self.seq_id += 1;
-                } else {
-                    stop = id;
-                    break;
+        } else {
+            Some(self.inp.recv_any().await)
+        };
+        match msg {
+            Some(Ok(msg)) if msg.is_none() && msg.info().partial_id.is_none() => {
+                let key = msg.info().from_addr.filter(|_| self.by_addr);
+                if let Some(mut stream) = self.streams.remove(&key) {
+                    for ready in stream.drain(key) {
+                        self.out.send_any(ready).await.ok();
+                    }
                 }
+                self.out.send_any(msg).await.ok();
             }
-            if stop != self.seq_id {
-                let rest = if stop > self.seq_id {
-                    let rest = self.cache.split_off(&stop);
-                    std::mem::replace(&mut self.cache, rest)
-                } else {
-                    std::mem::take(&mut self.cache)
-                };
-
-                for (_, msg) in rest {
+            Some(Ok(msg)) => self.push(msg).await,
+            // the input is flushed or closed
+            Some(Err(_)) => {
+                let mut ready = vec![];
+                for (key, stream) in self.streams.iter_mut() {
+                    ready.extend(stream.drain(*key));
+                }
+                for msg in ready {
                     self.out.send_any(msg).await.ok();
                 }
             }
-        } else {
-            assert!(self.cache.is_empty());
+            None => (),
         }
         Ok(())
     }
+
+    async fn push(&mut self, msg: SealedEnvelope) {
+        let key = msg.info().from_addr.filter(|_| self.by_addr);
+        let id = msg
+            .info()
+            .partial_id
+            .expect("partial_id required by reorder");
+        let stream = self.streams.entry(key).or_default();
+        if id < stream.seq_id || stream.cache.contains_key(&id) {
+            log::warn!(
+                "envelope {} of stream {:?} is late or duplicated in reorder, and dropped",
+                id,
+                key
+            );
+            return;
+        }
+        stream.cache.insert(id, msg);
+        let mut ready = stream.pop();
+        if self
+            .max_buffer
+            .map_or(false, |max| stream.cache.len() > max)
+        {
+            stream.skip(key);
+            ready.extend(stream.pop());
+        }
+        stream.waiting = if stream.cache.is_empty() {
+            None
+        } else if ready.is_empty() {
+            stream.waiting.or_else(|| Some(Instant::now()))
+        } else {
+            Some(Instant::now())
+        };
+        for msg in ready {
+            self.out.send_any(msg).await.ok();
+        }
+    }
+
+    // skip missing envelopes which are waited for `timeout`
+    async fn expire(&mut self) {
+        if let Some(timeout) = self.timeout {
+            let now = Instant::now();
+            let mut ready = vec![];
+            for (key, stream) in self.streams.iter_mut() {
+                if stream
+                    .waiting
+                    .map_or(false, |waiting| waiting + timeout <= now)
+                {
+                    stream.skip(*key);
+                    ready.extend(stream.pop());
+                    stream.waiting = Some(now).filter(|_| !stream.cache.is_empty());
+                }
+            }
+            for msg in ready {
+                self.out.send_any(msg).await.ok();
+            }
+        }
+    }
 }
 
 node_register!("Reorder", Reorder);
 
 #[cfg(test)]
 mod test {
+    use crate::channel::{Receiver, Sender};
     use crate::envelope::Envelope;
+    use crate::rt;
     use crate::sandbox::Sandbox;
     use futures_util::join;
     use rand::prelude::*;
     use rand::seq::SliceRandom;
+    use std::time::Duration;
+
+    async fn send(input: &Sender, partial_id: u64, from_addr: u64) {
+        let mut envelope = Envelope::new(0usize).seal();
+        envelope.info_mut().partial_id = Some(partial_id);
+        envelope.info_mut().from_addr = Some(from_addr);
+        input.send_any(envelope).await.ok();
+    }
+
+    async fn recv(output: &Receiver) -> (Option<u64>, Option<u64>) {
+        let envelope = output.recv_any().await.unwrap();
+        (envelope.info().partial_id, envelope.info().from_addr)
+    }
+
     #[flow_rs::rt::test]
     async fn test_reorder() {
         let reorder = Sandbox::pure("Reorder").unwrap();
@@ -111,4 +273,62 @@ mod test {
 
         handle.await.unwrap();
     }
+
+    #[flow_rs::rt::test]
+    async fn test_max_buffer() {
+        let args = toml::from_str("max_buffer=2").unwrap();
+        let reorder = Sandbox::with_args("Reorder", args).unwrap();
+        let input = reorder.input("inp").unwrap();
+        let output = reorder.output("out").unwrap();
+        let handle = reorder.start();
+        for i in [0, 2, 3, 4, 1, 5] {
+            send(&input, i, 0).await;
+        }
+        // the envelope 1 is skipped when 3 envelopes are cached, and dropped later
+        for i in [0, 2, 3, 4, 5] {
+            assert_eq!(recv(&output).await, (Some(i), Some(0)));
+        }
+        input.close();
+        handle.await.unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_timeout() {
+        let args = toml::from_str("timeout_ms=50\nby_addr=true").unwrap();
+        let reorder = Sandbox::with_args("Reorder", args).unwrap();
+        let input = reorder.input("inp").unwrap();
+        let output = reorder.output("out").unwrap();
+        let handle = reorder.start();
+        send(&input, 1, 0).await;
+        send(&input, 0, 1).await;
+        // streams are reordered separately
+        assert_eq!(recv(&output).await, (Some(0), Some(1)));
+        rt::task::sleep(Duration::from_millis(100)).await;
+        assert_eq!(recv(&output).await, (Some(1), Some(0)));
+        // the empty envelope restarts the stream
+        let mut empty = Envelope::<usize>::empty().seal();
+        empty.info_mut().from_addr = Some(1);
+        input.send_any(empty).await.ok();
+        assert_eq!(recv(&output).await, (None, Some(1)));
+        send(&input, 0, 1).await;
+        assert_eq!(recv(&output).await, (Some(0), Some(1)));
+        input.close();
+        handle.await.unwrap();
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_close() {
+        let reorder = Sandbox::pure("Reorder").unwrap();
+        let input = reorder.input("inp").unwrap();
+        let output = reorder.output("out").unwrap();
+        let handle = reorder.start();
+        send(&input, 3, 0).await;
+        send(&input, 1, 0).await;
+        // cached envelopes are emitted when the input is closed
+        input.close();
+        handle.await.unwrap();
+        assert_eq!(recv(&output).await, (Some(1), Some(0)));
+        assert_eq!(recv(&output).await, (Some(3), Some(0)));
+    }
 }
@@ -0,0 +1,286 @@
+/**
+ * \file flow-rs/src/node/router.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use anyhow::Result;
+use flow_rs::prelude::*;
+use serde::Deserialize;
+use toml::value::Table;
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum Tags {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Tags {
+    fn contains(&self, tag: &str) -> bool {
+        match self {
+            Tags::One(one) => one == tag,
+            Tags::Many(many) => many.iter().any(|x| x == tag),
+        }
+    }
+}
+
+/// A predicate on envelopes, all of whose conditions must hold
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+struct Route {
+    /// `tag` of the envelope is one of the tags
+    tag: Option<Tags>,
+    /// `partial_id` of the envelope is in the range `[start, end)`
+    partial_id: Option<(u64, u64)>,
+    from_addr: Option<(u64, u64)>,
+    to_addr: Option<(u64, u64)>,
+    /// `extra_data` of the envelope is a python dict with all these keys
+    #[serde(default)]
+    extra_keys: Vec<String>,
+    /// The message is a python dict with all these keys
+    #[serde(default)]
+    keys: Vec<String>,
+    /// The message is a python dict, and its values of these keys are equal to the given ones
+    #[serde(default)]
+    values: Table,
+}
+
+fn in_range(range: Option<(u64, u64)>, value: Option<u64>) -> bool {
+    match range {
+        Some((start, end)) => matches!(value, Some(value) if start <= value && value < end),
+        None => true,
+    }
+}
+
+impl Route {
+    fn is_python(&self) -> bool {
+        !self.extra_keys.is_empty() || !self.keys.is_empty() || !self.values.is_empty()
+    }
+
+    fn matches(&self, msg: &SealedEnvelope) -> bool {
+        let info = msg.info();
+        let tag = match (&self.tag, &info.tag) {
+            (Some(tags), Some(tag)) => tags.contains(tag),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+        tag && in_range(self.partial_id, info.partial_id)
+            && in_range(self.from_addr, info.from_addr)
+            && in_range(self.to_addr, info.to_addr)
+            && (!self.is_python() || self.matches_python(msg))
+    }
+
+    #[cfg(feature = "python")]
+    fn matches_python(&self, msg: &SealedEnvelope) -> bool {
+        use pyo3::types::PyDict;
+        use pyo3::{PyObject, Python};
+
+        Python::with_gil(|py| {
+            let dict = |obj: Option<&PyObject>| {
+                obj.and_then(|obj| obj.as_ref(py).downcast::<PyDict>().ok())
+            };
+            let has_keys = |dict: Option<&PyDict>, keys: &[String]| {
+                keys.is_empty()
+                    || dict.map_or(false, |dict| {
+                        keys.iter().all(|k| dict.get_item(k).is_some())
+                    })
+            };
+            let extra = dict(
+                msg.info()
+                    .extra_data
+                    .as_ref()
+                    .and_then(|extra| extra.downcast_ref::<PyObject>()),
+            );
+            let payload = dict(
+                msg.downcast_ref::<Envelope<PyObject>>()
+                    .filter(|envelope| envelope.is_some())
+                    .map(|envelope| envelope.get_ref()),
+            );
+            has_keys(extra, &self.extra_keys)
+                && has_keys(payload, &self.keys)
+                && self.values.iter().all(|(k, v)| {
+                    payload
+                        .and_then(|dict| dict.get_item(k))
+                        .map_or(false, |item| python_eq(item, v))
+                })
+        })
+    }
+
+    #[cfg(not(feature = "python"))]
+    fn matches_python(&self, _: &SealedEnvelope) -> bool {
+        false
+    }
+}
+
+#[cfg(feature = "python")]
+fn python_eq(item: &pyo3::PyAny, value: &toml::Value) -> bool {
+    use toml::Value;
+    match value {
+        Value::String(s) => item.extract::<String>().map_or(false, |x| &x == s),
+        Value::Integer(i) => item.extract::<i64>().map_or(false, |x| x == *i),
+        Value::Float(f) => item.extract::<f64>().map_or(false, |x| x == *f),
+        Value::Boolean(b) => item.extract::<bool>().map_or(false, |x| x == *b),
+        _ => false,
+    }
+}
+
+/// Route envelopes to `out:i`, where `i` is the index of the first route in `routes` matched by the envelope,
+/// or of all matched routes if `mode` is "all". Envelopes matching no route are dropped, and empty envelopes
+/// are sent to all outputs.
+#[inputs(inp)]
+#[outputs(out:[])]
+#[derive(Actor, Default)]
+struct Router {
+    routes: Vec<Route>,
+    all: bool,
+}
+
+impl Router {
+    fn new(name: String, args: &Table) -> Router {
+        let routes: Vec<Route> = args
+            .get("routes")
+            .map(|routes| {
+                routes
+                    .clone()
+                    .try_into()
+                    .unwrap_or_else(|err| panic!("invalid routes of node {}: {}", name, err))
+            })
+            .unwrap_or_default();
+        if cfg!(not(feature = "python")) && routes.iter().any(Route::is_python) {
+            panic!(
+                "routes on python objects of node {} require the python feature",
+                name
+            );
+        }
+        let all = match args.get("mode").and_then(|mode| mode.as_str()) {
+            None | Some("first") => false,
+            Some("all") => true,
+            Some(mode) => panic!("unknown mode {} of node {}", mode, name),
+        };
+        Router {
+            routes,
+            all,
+            ..Default::default()
+        }
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(msg) = self.inp.recv_any().await {
+            // empty envelopes carry no fields to match, e.g. the end of a stream
+            if msg.is_none() {
+                let targets: Vec<_> = (0..self.out.len()).collect();
+                self.send(msg, &targets).await;
+                return Ok(());
+            }
+            let mut matched = (0..self.routes.len()).filter(|&i| self.routes[i].matches(&msg));
+            let targets: Vec<_> = if self.all {
+                matched.collect()
+            } else {
+                matched.next().into_iter().collect()
+            };
+            self.send(msg, &targets).await;
+        }
+        Ok(())
+    }
+
+    async fn send(&self, msg: SealedEnvelope, targets: &[usize]) {
+        if let Some((&last, targets)) = targets.split_last() {
+            for &i in targets {
+                if let Some(out) = self.out.get(i) {
+                    out.send_any(msg.clone()).await.ok();
+                }
+            }
+            if let Some(out) = self.out.get(last) {
+                out.send_any(msg).await.ok();
+            }
+        }
+    }
+}
+
+impl Node for Router {
+    fn set_port(&mut self, port_name: &str, tag: Option<u64>, channel: &ChannelStorage) {
+        match (port_name, tag) {
+            ("inp", _) => self.inp = channel.receiver(),
+            // `out:i` is placed at `out[i]` to match the `i`th route, and outputs without a route are left unset
+            ("[out]", Some(tag)) => {
+                let i = tag as usize;
+                if self.out.len() <= i {
+                    self.out.resize_with(i + 1, Default::default);
+                }
+                self.out[i] = channel.sender();
+            }
+            ("[out]", None) => self.out.push(channel.sender()),
+            _ => unreachable!(),
+        }
+    }
+
+    fn set_port_dynamic(&mut self, _: &str, _: DynPortsConfig) {
+        unreachable!()
+    }
+
+    fn close(&mut self) {
+        self.out.clear();
+    }
+
+    fn is_allinp_closed(&self) -> bool {
+        self.inp.is_closed()
+    }
+}
+
+node_register!("Router", Router);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::sandbox::Sandbox;
+
+    fn routes(s: &str) -> Vec<Route> {
+        let table: Table = toml::from_str(s).unwrap();
+        table["routes"].clone().try_into().unwrap()
+    }
+
+    #[test]
+    fn test_matches() {
+        let routes = routes(r#"routes=[{tag=["a","b"]}, {partial_id=[0,10], to_addr=[1,2]}, {}]"#);
+        let matched = |tag: Option<&str>, partial_id: u64, to_addr: u64| {
+            let mut envelope = Envelope::new(0).seal();
+            envelope.info_mut().tag = tag.map(|tag| tag.to_owned());
+            envelope.info_mut().partial_id = Some(partial_id);
+            envelope.info_mut().to_addr = Some(to_addr);
+            routes
+                .iter()
+                .map(|route| route.matches(&envelope))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(matched(Some("b"), 10, 1), vec![true, false, true]);
+        assert_eq!(matched(Some("c"), 9, 1), vec![false, true, true]);
+        assert_eq!(matched(None, 0, 2), vec![false, false, true]);
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_router() {
+        let args = toml::from_str(r#"routes=[{tag="a"}, {tag="b"}]"#).unwrap();
+        let router = Sandbox::with_args("Router", args).unwrap();
+        let input = router.input("inp").unwrap();
+        let output = router.output("out").unwrap();
+        let handle = router.start();
+        for (i, tag) in ["b", "a"].iter().enumerate() {
+            let mut envelope = Envelope::new(i).seal();
+            envelope.info_mut().tag = Some(tag.to_string());
+            input.send_any(envelope).await.ok();
+        }
+        // only envelopes routed to `out:0` are received
+        assert_eq!(output.recv::<usize>().await.unwrap().get_ref(), &1);
+        input.close();
+        handle.await.unwrap();
+        assert!(output.is_empty());
+    }
+}
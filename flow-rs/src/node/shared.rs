@@ -84,9 +84,26 @@ impl Shared {
                 cap
             };
 
+        // the shared inputs are priority channels if any connection to them is
+        let find_priority = |port: &String| {
+            graphs.graphs.iter().any(|graph| {
+                graph.connections.values().any(|conn| {
+                    conn.priority
+                        && conn
+                            .rx
+                            .iter()
+                            .any(|p| p.node_name == cfg.entity.name && &p.port_name == port)
+                })
+            })
+        };
+
         for input in &cfg.inputs {
             let cap = find_cap(input);
-            let chan = ChannelStorage::bound(cap);
+            let chan = if find_priority(input) {
+                ChannelStorage::bound_priority(cap)
+            } else {
+                ChannelStorage::bound(cap)
+            };
             for node in &mut nodes {
                 node.set_port(input.as_str(), None, &chan);
             }
@@ -293,16 +310,19 @@ impl SharedProxy {
 
 crate::collect!(String, SharedProxy);
 
-pub fn load_shared(
+pub(crate) fn load_shared(
     cfg: &crate::config::interlayer::Node,
     graphs: &crate::config::interlayer::Config,
     ctx: Context,
     resources: ResourceCollection,
+    running: &Arc<crate::graph::Running>,
 ) -> Result<SharedProxy> {
     let local_key = ctx.local_key;
     let (s, r) = unbounded();
     let shared = Shared::new(ctx.local_key, r, cfg, graphs)?.boxed();
-    let handle = shared.start(ctx, resources);
+    let token = StopToken::default();
+    let handle = super::with_stop_token(&token, || shared.start(ctx, resources));
+    let handle = running.track(cfg.entity.name.clone(), token, handle);
     SharedHandle::registry_local()
         .get(local_key)
         .insert(cfg.entity.name.clone(), SharedHandle(handle));
@@ -0,0 +1,87 @@
+/**
+ * \file flow-rs/src/node/stop.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use event_listener::Event;
+use futures_util::future::{select, Either};
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+thread_local! {
+    static CURRENT: RefCell<Option<StopToken>> = RefCell::new(None);
+}
+
+#[derive(Default)]
+struct Inner {
+    stopped: AtomicBool,
+    cancelled: AtomicBool,
+    event: Event,
+}
+
+/// A token to ask a node instance to stop after its current exec, e.g. when it is replaced by reloading,
+/// or to cancel its current exec, e.g. when the graph fails to shut down in time
+#[derive(Clone, Default)]
+pub struct StopToken(Arc<Inner>);
+
+impl StopToken {
+    /// The token of the node being started in the current thread, must be called in `Actor::start`
+    /// before the actor is spawned
+    pub fn current() -> StopToken {
+        CURRENT.with(|current| current.borrow().clone().unwrap_or_default())
+    }
+
+    pub fn stop(&self) {
+        self.0.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.0.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Stop the node instance, and drop its current exec
+    pub fn cancel(&self) {
+        self.stop();
+        self.0.cancelled.store(true, Ordering::SeqCst);
+        self.0.event.notify(usize::MAX);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Run `fut` until the token is cancelled, and return `None` if `fut` is dropped unfinished
+    pub async fn or_cancelled<F: Future>(&self, fut: F) -> Option<F::Output> {
+        let cancelled = async {
+            while !self.is_cancelled() {
+                let listener = self.0.event.listen();
+                if !self.is_cancelled() {
+                    listener.await;
+                }
+            }
+        };
+        futures_util::pin_mut!(fut, cancelled);
+        match select(fut, cancelled).await {
+            Either::Left((ret, _)) => Some(ret),
+            Either::Right(_) => None,
+        }
+    }
+}
+
+/// Call `f`, which starts a node instance controlled by `token`
+pub(crate) fn with_stop_token<F, R>(token: &StopToken, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let prev = CURRENT.with(|current| current.replace(Some(token.clone())));
+    let ret = f();
+    CURRENT.with(|current| current.replace(prev));
+    ret
+}
@@ -0,0 +1,294 @@
+/**
+ * \file flow-rs/src/node/window.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::rt;
+use anyhow::Result;
+use flow_rs::prelude::*;
+use futures_util::{pin_mut, select, FutureExt};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use toml::value::Table;
+
+#[derive(Default)]
+struct Pending {
+    envelopes: VecDeque<(Instant, SealedEnvelope)>,
+    // the number of envelopes not emitted in any window
+    fresh: usize,
+    // the end of the current time window
+    end: Option<Instant>,
+}
+
+/// Group envelopes from the same `from_addr` into windows, and emit each window as an envelope of
+/// `Vec<SealedEnvelope>`, or of a python list of messages if all envelopes in the window are python envelopes.
+///
+/// A window contains `size` envelopes, or envelopes received in `time_ms`, and the next window starts `slide`
+/// envelopes or `slide_ms` later(tumbling windows by default). Unfinished windows are emitted when an empty
+/// envelope of the same `from_addr` is received, which is forwarded after the window, or when the input is
+/// flushed or closed.
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+struct Window {
+    size: usize,
+    slide: usize,
+    time: Option<(Duration, Duration)>,
+    pending: HashMap<Option<u64>, Pending>,
+}
+
+fn get_u64(name: &str, args: &Table, key: &str) -> Option<u64> {
+    args.get(key).map(|value| {
+        value
+            .as_integer()
+            .filter(|&value| value > 0)
+            .unwrap_or_else(|| panic!("{} of node {} must be a positive integer", key, name))
+            as u64
+    })
+}
+
+impl Window {
+    fn new(name: String, args: &Table) -> Window {
+        let size = get_u64(&name, args, "size");
+        let slide = get_u64(&name, args, "slide");
+        let time_ms = get_u64(&name, args, "time_ms");
+        let slide_ms = get_u64(&name, args, "slide_ms");
+        match (size, time_ms) {
+            (Some(size), None) => {
+                assert!(
+                    slide_ms.is_none(),
+                    "slide_ms of node {} requires time_ms",
+                    name
+                );
+                let slide = slide.unwrap_or(size);
+                assert!(slide <= size, "slide of node {} is larger than size", name);
+                Window {
+                    size: size as usize,
+                    slide: slide as usize,
+                    ..Default::default()
+                }
+            }
+            (None, Some(time_ms)) => {
+                assert!(slide.is_none(), "slide of node {} requires size", name);
+                let slide_ms = slide_ms.unwrap_or(time_ms);
+                assert!(
+                    slide_ms <= time_ms,
+                    "slide_ms of node {} is larger than time_ms",
+                    name
+                );
+                Window {
+                    time: Some((
+                        Duration::from_millis(time_ms),
+                        Duration::from_millis(slide_ms),
+                    )),
+                    ..Default::default()
+                }
+            }
+            _ => panic!("either size or time_ms of node {} is required", name),
+        }
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        self.tick().await;
+        let deadline = self
+            .pending
+            .values()
+            .filter_map(|pending| pending.end)
+            .min();
+        let msg = if let Some(deadline) = deadline {
+            let recv = self.inp.recv_any().fuse();
+            let timeout =
+                rt::task::sleep(deadline.saturating_duration_since(Instant::now())).fuse();
+            pin_mut!(recv, timeout);
+            select! {
+                msg = recv => Some(msg),
+                // the window is emitted by the next `tick`
+                _ = timeout => None,
+            }
+        } else {
+            Some(self.inp.recv_any().await)
+        };
+        match msg {
+            Some(Ok(msg)) if msg.is_none() => {
+                self.flush(msg.info().from_addr).await;
+                self.out.send_any(msg).await.ok();
+            }
+            Some(Ok(msg)) => self.push(msg).await,
+            // the input is flushed or closed
+            Some(Err(_)) => {
+                let keys: Vec<_> = self.pending.keys().cloned().collect();
+                for key in keys {
+                    self.flush(key).await;
+                }
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    async fn push(&mut self, msg: SealedEnvelope) {
+        let now = Instant::now();
+        let pending = self.pending.entry(msg.info().from_addr).or_default();
+        pending.envelopes.push_back((now, msg));
+        pending.fresh += 1;
+        if let Some((time, _)) = self.time {
+            pending.end.get_or_insert(now + time);
+        } else if pending.envelopes.len() == self.size {
+            let window = pack(
+                pending
+                    .envelopes
+                    .iter()
+                    .map(|(_, msg)| msg.clone())
+                    .collect(),
+            );
+            pending.envelopes.drain(..self.slide);
+            pending.fresh = 0;
+            self.out.send_any(window).await.ok();
+        }
+    }
+
+    // emit time windows which are finished
+    async fn tick(&mut self) {
+        let (time, slide) = match self.time {
+            Some(time) => time,
+            None => return,
+        };
+        let now = Instant::now();
+        let mut windows = vec![];
+        for pending in self.pending.values_mut() {
+            while let Some(end) = pending.end.filter(|&end| end <= now) {
+                let window: Vec<_> = pending
+                    .envelopes
+                    .iter()
+                    .filter(|(t, _)| *t < end)
+                    .map(|(_, msg)| msg.clone())
+                    .collect();
+                if !window.is_empty() {
+                    windows.push(pack(window));
+                }
+                let next = end + slide;
+                while matches!(pending.envelopes.front(), Some((t, _)) if *t + time < next) {
+                    pending.envelopes.pop_front();
+                }
+                pending.fresh = pending.envelopes.iter().filter(|(t, _)| *t >= end).count();
+                pending.end = Some(next).filter(|_| !pending.envelopes.is_empty());
+            }
+        }
+        self.pending.retain(|_, pending| pending.end.is_some());
+        for window in windows {
+            self.out.send_any(window).await.ok();
+        }
+    }
+
+    // emit the unfinished window of `key`
+    async fn flush(&mut self, key: Option<u64>) {
+        if let Some(pending) = self.pending.remove(&key) {
+            if pending.fresh > 0 {
+                let window = pack(pending.envelopes.into_iter().map(|(_, msg)| msg).collect());
+                self.out.send_any(window).await.ok();
+            }
+        }
+    }
+}
+
+fn pack(window: Vec<SealedEnvelope>) -> SealedEnvelope {
+    let info = window.last().expect("empty window").info().clone();
+    pack_python(&window, &info).unwrap_or_else(|| Envelope::with_info(window, info).seal())
+}
+
+#[cfg(feature = "python")]
+fn pack_python(window: &[SealedEnvelope], info: &EnvelopeInfo) -> Option<SealedEnvelope> {
+    use pyo3::types::PyList;
+    use pyo3::{PyObject, Python, ToPyObject};
+
+    Python::with_gil(|py| {
+        let msgs = window
+            .iter()
+            .map(|msg| {
+                msg.downcast_ref::<Envelope<PyObject>>()
+                    .map(|envelope| envelope.get_ref().clone_ref(py))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let list = PyList::new(py, msgs).to_object(py);
+        Some(Envelope::with_info(list, info.clone()).seal())
+    })
+}
+
+#[cfg(not(feature = "python"))]
+fn pack_python(_: &[SealedEnvelope], _: &EnvelopeInfo) -> Option<SealedEnvelope> {
+    None
+}
+
+node_register!("Window", Window);
+
+#[cfg(test)]
+mod test {
+    use crate::envelope::{Envelope, SealedEnvelope};
+    use crate::rt;
+    use crate::sandbox::Sandbox;
+    use std::time::Duration;
+
+    fn envelope(i: usize, from_addr: u64) -> SealedEnvelope {
+        let mut envelope = Envelope::new(i).seal();
+        envelope.info_mut().from_addr = Some(from_addr);
+        envelope
+    }
+
+    fn unpack(mut window: Envelope<Vec<SealedEnvelope>>) -> Vec<usize> {
+        window
+            .unpack()
+            .iter()
+            .map(|msg| *msg.downcast_ref::<Envelope<usize>>().unwrap().get_ref())
+            .collect()
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_count() {
+        let args = toml::from_str("size=3\nslide=2").unwrap();
+        let window = Sandbox::with_args("Window", args).unwrap();
+        let input = window.input("inp").unwrap();
+        let output = window.output("out").unwrap();
+        let handle = window.start();
+        for i in 0..6 {
+            input.send_any(envelope(i, i as u64 % 2)).await.ok();
+        }
+        assert_eq!(unpack(output.recv().await.unwrap()), vec![0, 2, 4]);
+        assert_eq!(unpack(output.recv().await.unwrap()), vec![1, 3, 5]);
+        input.send_any(envelope(6, 0)).await.ok();
+        // the empty envelope flushes the unfinished window of its stream
+        let mut empty = Envelope::<usize>::empty().seal();
+        empty.info_mut().from_addr = Some(0);
+        input.send_any(empty).await.ok();
+        assert_eq!(unpack(output.recv().await.unwrap()), vec![4, 6]);
+        assert!(output.recv_any().await.unwrap().is_none());
+        // windows of other streams are all emitted
+        input.close();
+        handle.await.unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[flow_rs::rt::test]
+    async fn test_time() {
+        let args = toml::from_str("time_ms=100").unwrap();
+        let window = Sandbox::with_args("Window", args).unwrap();
+        let input = window.input("inp").unwrap();
+        let output = window.output("out").unwrap();
+        let handle = window.start();
+        input.send_any(envelope(0, 0)).await.ok();
+        input.send_any(envelope(1, 0)).await.ok();
+        rt::task::sleep(Duration::from_millis(150)).await;
+        input.send_any(envelope(2, 0)).await.ok();
+        assert_eq!(unpack(output.recv().await.unwrap()), vec![0, 1]);
+        assert_eq!(unpack(output.recv().await.unwrap()), vec![2]);
+        input.close();
+        handle.await.unwrap();
+    }
+}
@@ -0,0 +1,262 @@
+/**
+ * \file flow-rs/src/node/zip.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::rt;
+use anyhow::Result;
+use flow_rs::prelude::*;
+use futures_util::future::{pending, select_all};
+use futures_util::{select, FutureExt};
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+use toml::value::Table;
+
+struct Group {
+    envelopes: Vec<Option<SealedEnvelope>>,
+    created: Instant,
+}
+
+/// Align envelopes with the same `partial_id`(and `from_addr` if `by_addr` is true) from inputs `inp:0`,
+/// `inp:1`, ..., and emit them as an envelope of `Vec<Option<SealedEnvelope>>` in the order of input tags,
+/// or of a python list of messages if all of them are python envelopes.
+///
+/// A group is emitted as soon as all inputs have arrived. Otherwise it is emitted with `None` for the missing
+/// inputs when it is `timeout_ms` old, when all open inputs send an empty envelope of its `from_addr`, which is
+/// forwarded after the group, or when the inputs are flushed or closed. Envelopes without `partial_id` are
+/// dropped.
+#[inputs(inp:{})]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+struct Zip {
+    by_addr: bool,
+    timeout: Option<Duration>,
+    groups: HashMap<(u64, Option<u64>), Group>,
+    // the inputs which sent an empty envelope of each `from_addr`, and the last one of them
+    ends: HashMap<Option<u64>, (HashSet<usize>, SealedEnvelope)>,
+    flushed: usize,
+}
+
+impl Zip {
+    fn new(name: String, args: &Table) -> Zip {
+        let by_addr = args
+            .get("by_addr")
+            .map(|by_addr| {
+                by_addr
+                    .as_bool()
+                    .unwrap_or_else(|| panic!("by_addr of node {} must be a boolean", name))
+            })
+            .unwrap_or(false);
+        let timeout = args.get("timeout_ms").map(|timeout| {
+            let timeout = timeout
+                .as_integer()
+                .filter(|&timeout| timeout > 0)
+                .unwrap_or_else(|| {
+                    panic!("timeout_ms of node {} must be a positive integer", name)
+                });
+            Duration::from_millis(timeout as u64)
+        });
+        Zip {
+            by_addr,
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        self.expire().await;
+        let mut tags: Vec<_> = self.inp.keys().cloned().collect();
+        tags.sort_unstable();
+        let n = tags.len();
+        let ret = {
+            let recvs: Vec<_> = tags
+                .iter()
+                .map(|tag| &self.inp[tag])
+                .enumerate()
+                .filter(|(_, inp)| !inp.is_closed())
+                .map(|(i, inp)| Box::pin(async move { (i, inp.recv_any().await) }))
+                .collect();
+            if recvs.is_empty() {
+                None
+            } else {
+                let deadline = self.timeout.and_then(|timeout| {
+                    self.groups
+                        .values()
+                        .map(|group| group.created + timeout)
+                        .min()
+                });
+                let timeout = async move {
+                    match deadline {
+                        Some(deadline) => {
+                            rt::task::sleep(deadline.saturating_duration_since(Instant::now()))
+                                .await
+                        }
+                        None => pending::<()>().await,
+                    }
+                };
+                let mut recv = select_all(recvs).fuse();
+                let mut timeout = Box::pin(timeout.fuse());
+                select! {
+                    (ret, _, _) = recv => Some(ret),
+                    // expired groups are emitted by the next `expire`
+                    _ = timeout => return Ok(()),
+                }
+            }
+        };
+        match ret {
+            Some((i, Ok(msg))) if msg.is_none() => {
+                let from_addr = msg.info().from_addr;
+                let mut inputs = self
+                    .ends
+                    .remove(&from_addr)
+                    .map(|(inputs, _)| inputs)
+                    .unwrap_or_default();
+                inputs.insert(i);
+                self.ends.insert(from_addr, (inputs, msg));
+                self.end(&tags).await;
+            }
+            Some((i, Ok(msg))) => self.push(i, n, msg).await,
+            // an input is flushed or closed
+            _ => {
+                let flushed = self
+                    .inp
+                    .values()
+                    .map(|inp| inp.empty_n())
+                    .min()
+                    .unwrap_or(0);
+                if flushed > self.flushed || self.inp.values().all(|inp| inp.is_closed()) {
+                    self.flushed = flushed;
+                    self.emit(|_, _| true).await;
+                }
+                // the closed input may be the last one which has not sent an empty envelope
+                self.end(&tags).await;
+            }
+        }
+        Ok(())
+    }
+
+    async fn push(&mut self, i: usize, n: usize, msg: SealedEnvelope) {
+        let info = msg.info();
+        let partial_id = match info.partial_id {
+            Some(partial_id) => partial_id,
+            None => {
+                log::warn!("envelope without partial_id from input {} is dropped", i);
+                return;
+            }
+        };
+        let key = (partial_id, info.from_addr.filter(|_| self.by_addr));
+        let group = self.groups.entry(key).or_insert_with(|| Group {
+            envelopes: vec![None; n],
+            created: Instant::now(),
+        });
+        if group.envelopes[i].is_some() {
+            log::warn!(
+                "duplicated envelope {} from input {} is dropped",
+                partial_id,
+                i
+            );
+            return;
+        }
+        group.envelopes[i] = Some(msg);
+        if group.envelopes.iter().all(Option::is_some) {
+            let group = self.groups.remove(&key).unwrap();
+            self.out.send_any(pack(group.envelopes)).await.ok();
+        }
+    }
+
+    // emit the groups of each `from_addr` whose empty envelopes are received from all open inputs, and
+    // forward the empty envelope after them
+    async fn end(&mut self, tags: &[u64]) {
+        let open: Vec<_> = tags
+            .iter()
+            .enumerate()
+            .filter(|(_, tag)| !self.inp[*tag].is_closed())
+            .map(|(i, _)| i)
+            .collect();
+        let finished: Vec<_> = self
+            .ends
+            .iter()
+            .filter(|(_, (inputs, _))| open.iter().all(|i| inputs.contains(i)))
+            .map(|(from_addr, _)| *from_addr)
+            .collect();
+        for from_addr in finished {
+            let (_, msg) = self.ends.remove(&from_addr).unwrap();
+            let by_addr = self.by_addr;
+            self.emit(move |(_, addr), _| !by_addr || *addr == from_addr)
+                .await;
+            self.out.send_any(msg).await.ok();
+        }
+    }
+
+    // emit groups which are `timeout` old
+    async fn expire(&mut self) {
+        if let Some(timeout) = self.timeout {
+            let now = Instant::now();
+            self.emit(|_, group| group.created + timeout <= now).await;
+        }
+    }
+
+    // emit groups satisfying `f` in the order of creation
+    async fn emit(&mut self, f: impl Fn(&(u64, Option<u64>), &Group) -> bool) {
+        let keys: Vec<_> = self
+            .groups
+            .iter()
+            .filter(|(key, group)| f(*key, *group))
+            .map(|(key, _)| *key)
+            .collect();
+        let mut groups: Vec<_> = keys
+            .into_iter()
+            .map(|key| self.groups.remove(&key).unwrap())
+            .collect();
+        groups.sort_by_key(|group| group.created);
+        for group in groups {
+            self.out.send_any(pack(group.envelopes)).await.ok();
+        }
+    }
+}
+
+fn pack(group: Vec<Option<SealedEnvelope>>) -> SealedEnvelope {
+    let info = group
+        .iter()
+        .flatten()
+        .next()
+        .expect("empty group")
+        .info()
+        .clone();
+    pack_python(&group, &info).unwrap_or_else(|| Envelope::with_info(group, info).seal())
+}
+
+#[cfg(feature = "python")]
+fn pack_python(group: &[Option<SealedEnvelope>], info: &EnvelopeInfo) -> Option<SealedEnvelope> {
+    use pyo3::types::PyList;
+    use pyo3::{PyObject, Python, ToPyObject};
+
+    Python::with_gil(|py| {
+        let msgs = group
+            .iter()
+            .map(|msg| match msg {
+                Some(msg) => msg
+                    .downcast_ref::<Envelope<PyObject>>()
+                    .map(|envelope| envelope.get_ref().clone_ref(py)),
+                None => Some(py.None()),
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let list = PyList::new(py, msgs).to_object(py);
+        Some(Envelope::with_info(list, info.clone()).seal())
+    })
+}
+
+#[cfg(not(feature = "python"))]
+fn pack_python(_: &[Option<SealedEnvelope>], _: &EnvelopeInfo) -> Option<SealedEnvelope> {
+    None
+}
+
+node_register!("Zip", Zip);
@@ -0,0 +1,355 @@
+/**
+ * \file flow-rs/src/record.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use crate::channel::Observer;
+use crate::envelope::{AnyEnvelope, DummyEnvelope, Envelope, EnvelopeInfo, SealedEnvelope};
+use crate::graph::MainGraph;
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Convert the messages of a port from and to json, which is required to record or replay the port
+#[derive(Clone, Copy)]
+pub struct Codec {
+    encode: fn(&SealedEnvelope) -> Result<Value>,
+    decode: fn(Value, EnvelopeInfo) -> Result<SealedEnvelope>,
+    empty: fn(EnvelopeInfo) -> SealedEnvelope,
+}
+
+impl Codec {
+    /// The codec of ports whose messages are `T`
+    pub fn new<T>() -> Codec
+    where
+        T: 'static + Clone + Send + Serialize + DeserializeOwned,
+    {
+        Codec {
+            encode: |envelope| {
+                let envelope = envelope
+                    .downcast_ref::<Envelope<T>>()
+                    .ok_or_else(|| anyhow!("unexpected message type"))?;
+                Ok(serde_json::to_value(envelope.get_ref())?)
+            },
+            decode: |msg, info| {
+                Ok(Envelope::with_info(serde_json::from_value::<T>(msg)?, info).seal())
+            },
+            empty: |info| {
+                let mut envelope = Envelope::<T>::empty();
+                *envelope.info_mut() = info;
+                envelope.seal()
+            },
+        }
+    }
+}
+
+/// The serializable part of `EnvelopeInfo`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct RecordedInfo {
+    pub partial_id: Option<u64>,
+    pub from_addr: Option<u64>,
+    pub to_addr: Option<u64>,
+    pub transfer_addr: Option<u64>,
+    pub tag: Option<String>,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+impl From<&EnvelopeInfo> for RecordedInfo {
+    fn from(info: &EnvelopeInfo) -> Self {
+        RecordedInfo {
+            partial_id: info.partial_id,
+            from_addr: info.from_addr,
+            to_addr: info.to_addr,
+            transfer_addr: info.transfer_addr,
+            tag: info.tag.clone(),
+            priority: info.priority,
+        }
+    }
+}
+
+impl From<RecordedInfo> for EnvelopeInfo {
+    fn from(info: RecordedInfo) -> Self {
+        EnvelopeInfo {
+            partial_id: info.partial_id,
+            from_addr: info.from_addr,
+            to_addr: info.to_addr,
+            transfer_addr: info.transfer_addr,
+            tag: info.tag,
+            priority: info.priority,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// An envelope, whose message is none if the envelope is empty, or the port has no codec
+    Envelope {
+        info: RecordedInfo,
+        msg: Option<Value>,
+    },
+    /// A flush event
+    Flush,
+    /// The port is closed
+    Close,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Record {
+    /// The name of an input or output of the graph
+    pub port: String,
+    /// Time elapsed since the recorder is created in microseconds
+    pub offset_us: u64,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Write envelopes going through the inputs and outputs of a graph into a file, see `MainGraph::record`
+pub struct Recorder {
+    writer: Mutex<LineWriter<File>>,
+    start: Instant,
+    codecs: HashMap<String, Codec>,
+}
+
+impl Recorder {
+    /// Create a recording file, which is truncated if it exists
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Recorder> {
+        Ok(Recorder {
+            writer: Mutex::new(LineWriter::new(File::create(path)?)),
+            start: Instant::now(),
+            codecs: Default::default(),
+        })
+    }
+
+    /// Set the codec of a port, envelopes of ports without codecs are recorded without messages
+    pub fn codec(mut self, port: &str, codec: Codec) -> Recorder {
+        self.codecs.insert(port.to_owned(), codec);
+        self
+    }
+
+    fn write(&self, port: &str, event: Event) {
+        let record = Record {
+            port: port.to_owned(),
+            offset_us: self.start.elapsed().as_micros() as u64,
+            event,
+        };
+        let mut writer = self.writer.lock().unwrap();
+        let ret = serde_json::to_writer(&mut *writer, &record)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| Ok(writer.write_all(b"\n")?));
+        if let Err(err) = ret {
+            log::error!("failed to record port {}: {}", port, err);
+        }
+    }
+
+    fn encode(&self, port: &str, envelope: &SealedEnvelope) -> Option<Value> {
+        if envelope.is_none() {
+            return None;
+        }
+        let codec = self.codecs.get(port)?;
+        match (codec.encode)(envelope) {
+            Ok(msg) => Some(msg),
+            Err(err) => {
+                log::error!("failed to encode the message of port {}: {}", port, err);
+                None
+            }
+        }
+    }
+
+    /// The observer recording a port
+    pub(crate) fn observer(self: &Arc<Self>, port: &str) -> Arc<dyn Observer> {
+        Arc::new(PortObserver {
+            recorder: self.clone(),
+            port: port.to_owned(),
+            is_closed: AtomicBool::new(false),
+        })
+    }
+}
+
+struct PortObserver {
+    recorder: Arc<Recorder>,
+    port: String,
+    is_closed: AtomicBool,
+}
+
+impl Observer for PortObserver {
+    fn on_envelope(&self, envelope: &SealedEnvelope) {
+        let event = Event::Envelope {
+            info: envelope.info().into(),
+            msg: self.recorder.encode(&self.port, envelope),
+        };
+        self.recorder.write(&self.port, event);
+    }
+
+    fn on_flush(&self) {
+        self.recorder.write(&self.port, Event::Flush);
+    }
+
+    fn on_close(&self) {
+        // receivers find the channel closed on every recv after it is closed
+        if !self.is_closed.swap(true, Ordering::Relaxed) {
+            self.recorder.write(&self.port, Event::Close);
+        }
+    }
+}
+
+/// Records loaded from a recording file, which is a json lines file, each line of which is a `Record`
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    pub records: Vec<Record>,
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Recording> {
+        let mut records = vec![];
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                records.push(serde_json::from_str(&line)?);
+            }
+        }
+        Ok(Recording { records })
+    }
+
+    /// Events of a port in order
+    pub fn events<'a>(&'a self, port: &'a str) -> impl Iterator<Item = &'a Event> + 'a {
+        self.records
+            .iter()
+            .filter(move |record| record.port == port)
+            .map(|record| &record.event)
+    }
+
+    /// Compare events of `ports` with a golden recording regardless of timing, and return the differences
+    pub fn diff(&self, golden: &Recording, ports: &[&str]) -> Vec<String> {
+        let mut diffs = vec![];
+        for port in ports {
+            let events: Vec<_> = self.events(port).collect();
+            let expected: Vec<_> = golden.events(port).collect();
+            for (i, (event, expected)) in events.iter().zip(expected.iter()).enumerate() {
+                if event != expected {
+                    diffs.push(format!(
+                        "port {} event {}: expected {:?}, found {:?}",
+                        port, i, expected, event
+                    ));
+                }
+            }
+            if events.len() != expected.len() {
+                diffs.push(format!(
+                    "port {}: expected {} events, found {}",
+                    port,
+                    expected.len(),
+                    events.len()
+                ));
+            }
+        }
+        diffs
+    }
+}
+
+/// Send the recorded inputs into a graph
+#[derive(Default)]
+pub struct Replayer {
+    codecs: HashMap<String, Codec>,
+    speed: Option<f64>,
+}
+
+impl Replayer {
+    /// Set the codec of a port, which is required to replay envelopes into the port
+    pub fn codec(mut self, port: &str, codec: Codec) -> Replayer {
+        self.codecs.insert(port.to_owned(), codec);
+        self
+    }
+
+    /// Replay at `speed` times the original timing, inputs are sent as soon as possible by default
+    pub fn speed(mut self, speed: f64) -> Replayer {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Send events of inputs of the graph in the recording, return after the last one is sent
+    pub async fn replay(&self, recording: &Recording, graph: &MainGraph) -> Result<()> {
+        let start = Instant::now();
+        let mut inputs = HashMap::new();
+        for name in graph.input_names() {
+            inputs.insert(name.to_owned(), graph.input(name).unwrap());
+        }
+        for record in &recording.records {
+            let input = match inputs.get(&record.port) {
+                Some(input) => input,
+                None => continue,
+            };
+            if let Some(speed) = self.speed {
+                let offset = Duration::from_micros(record.offset_us).div_f64(speed);
+                if let Some(delay) = offset.checked_sub(start.elapsed()) {
+                    crate::rt::task::sleep(delay).await;
+                }
+            }
+            match &record.event {
+                Event::Envelope { info, msg } => {
+                    let codec = self
+                        .codecs
+                        .get(&record.port)
+                        .ok_or_else(|| anyhow!("codec of port {} is not found", record.port))?;
+                    let info = info.clone().into();
+                    let envelope = match msg {
+                        Some(msg) => (codec.decode)(msg.clone(), info)?,
+                        None => (codec.empty)(info),
+                    };
+                    input
+                        .send_any(envelope)
+                        .await
+                        .map_err(|_| anyhow!("port {} is closed", record.port))?;
+                }
+                Event::Flush => {
+                    input.send_any(DummyEnvelope {}.seal()).await.ok();
+                }
+                Event::Close => input.close(),
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_record_format() {
+        let record = Record {
+            port: "inp".to_owned(),
+            offset_us: 10,
+            event: Event::Envelope {
+                info: RecordedInfo {
+                    partial_id: Some(1),
+                    ..Default::default()
+                },
+                msg: Some(Value::from(3)),
+            },
+        };
+        let line = serde_json::to_string(&record).unwrap();
+        assert!(line.contains("\"event\":\"envelope\""));
+        assert_eq!(serde_json::from_str::<Record>(&line).unwrap(), record);
+
+        let line = r#"{"port":"inp","offset_us":20,"event":"close"}"#;
+        assert_eq!(
+            serde_json::from_str::<Record>(line).unwrap().event,
+            Event::Close
+        );
+    }
+}
@@ -63,6 +63,18 @@ impl<ID: Eq + Hash, T> Registry<ID, T> {
         registry.elems.insert(id.into(), Arc::new(elem));
     }
 
+    pub(crate) fn get_or_insert_with<F>(&self, id: ID, f: F) -> Arc<T>
+    where
+        F: FnOnce() -> T,
+    {
+        let mut registry = self.inner.write().unwrap();
+        registry
+            .elems
+            .entry(id)
+            .or_insert_with(|| Arc::new(f()))
+            .clone()
+    }
+
     pub(crate) fn for_each<F>(&self, f: F)
     where
         F: FnMut(&T),
@@ -137,6 +149,12 @@ where
         map.get(&id).cloned().unwrap()
     }
 
+    /// Keys of all graph loads
+    pub(crate) fn local_keys(&self) -> Vec<u64> {
+        let map = self.inner.read().unwrap();
+        map.keys().cloned().collect()
+    }
+
     pub(crate) fn remove(&self, id: u64) {
         let mut map = self.inner.write().unwrap();
         map.remove(&id);
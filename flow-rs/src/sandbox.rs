@@ -55,10 +55,12 @@ impl Sandbox {
             },
             res,
             cloned: None,
+            balance: Default::default(),
             inputs: Default::default(),
             outputs: Default::default(),
             is_dyn: false,
             is_shared: false,
+            restart: Default::default(),
         };
         let inputs_name: Vec<String> = inputs(local_key, ty)?.into_iter().collect();
         let outputs_name: Vec<String> = outputs(local_key, ty)?.into_iter().collect();
@@ -96,6 +98,11 @@ impl Sandbox {
                         local_key,
                         target: "out".to_owned(),
                         cap: 16,
+                        policy: Default::default(),
+                        priority: false,
+                        idle_timeout: None,
+                        admission: None,
+                        pool: None,
                         brokers: vec![client],
                         args: Default::default(),
                     },
@@ -119,6 +126,11 @@ impl Sandbox {
                         local_key,
                         target: "inp".to_owned(),
                         cap: 16,
+                        policy: Default::default(),
+                        priority: false,
+                        idle_timeout: None,
+                        admission: None,
+                        pool: None,
                         brokers: vec![client],
                         args: Default::default(),
                     },
@@ -179,11 +191,13 @@ fn graph_register(local_key: u64, ty: &str, port_name: &str) {
             args: Default::default(),
         },
         cloned: None,
+        balance: Default::default(),
         inputs: inputs.iter().cloned().collect(),
         outputs: outputs.iter().cloned().collect(),
         is_dyn: false,
         is_shared: false,
         res: vec![],
+        restart: Default::default(),
     };
     let port = interlayer::Port {
         node_type: vec![ty.to_owned()],
@@ -196,6 +210,14 @@ fn graph_register(local_key: u64, ty: &str, port_name: &str) {
         cap: 16,
         tx: inputs.iter().map(|_| port.clone()).collect(),
         rx: outputs.iter().map(|_| port.clone()).collect(),
+        ttl: None,
+        expired: None,
+        idle_timeout: None,
+        max_instances: None,
+        admission: Default::default(),
+        pool_size: None,
+        policy: Default::default(),
+        priority: false,
     };
     let cfg = interlayer::Graph {
         name: format!("_{}_", ty),
@@ -0,0 +1,226 @@
+/**
+ * \file flow-rs/src/trace/export.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+use super::TraceContext;
+use crate::config::presentation::Trace;
+use crate::rt::channel::{unbounded, Sender};
+use crate::rt::net::TcpStream;
+use crate::rt::prelude::*;
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAX_BATCH: usize = 512;
+
+lazy_static::lazy_static! {
+    static ref EXPORTER: Mutex<Option<Sender<SpanData>>> = Default::default();
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// values of the otlp enum `SpanKind`
+#[derive(Clone, Copy)]
+pub(super) enum SpanKind {
+    Internal = 1,
+    Server = 2,
+}
+
+pub(super) struct SpanData {
+    pub ctx: TraceContext,
+    pub parent: Option<u64>,
+    pub name: String,
+    pub kind: SpanKind,
+    pub start: SystemTime,
+    pub end: SystemTime,
+    pub attributes: Vec<(&'static str, String)>,
+}
+
+impl SpanData {
+    pub fn start(ctx: TraceContext, parent: Option<u64>, name: &str, kind: SpanKind) -> SpanData {
+        let now = SystemTime::now();
+        SpanData {
+            ctx,
+            parent,
+            name: name.to_owned(),
+            kind,
+            start: now,
+            end: now,
+            attributes: vec![],
+        }
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({"key": key, "value": {"stringValue": value}})
+}
+
+/// Encode spans as an OTLP-JSON `ExportTraceServiceRequest`
+fn encode(service_name: &str, spans: &[SpanData]) -> Value {
+    let spans: Vec<_> = spans
+        .iter()
+        .map(|span| {
+            let mut value = json!({
+                "traceId": format!("{:032x}", span.ctx.trace_id),
+                "spanId": format!("{:016x}", span.ctx.span_id),
+                "name": span.name,
+                "kind": span.kind as u8,
+                "startTimeUnixNano": unix_nanos(span.start),
+                "endTimeUnixNano": unix_nanos(span.end),
+                "attributes": span
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| attribute(key, value))
+                    .collect::<Vec<_>>(),
+            });
+            if let Some(parent) = span.parent {
+                value["parentSpanId"] = Value::from(format!("{:016x}", parent));
+            }
+            value
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": {"attributes": [attribute("service.name", service_name)]},
+            "scopeSpans": [{"scope": {"name": "megflow"}, "spans": spans}],
+        }]
+    })
+}
+
+enum Target {
+    File(File),
+    // (address, path)
+    Http(String, String),
+}
+
+impl Target {
+    fn new(cfg: &Trace) -> Result<Target> {
+        match (&cfg.path, &cfg.endpoint) {
+            (Some(path), None) => Ok(Target::File(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            (None, Some(endpoint)) => {
+                let endpoint = endpoint.trim_start_matches("http://");
+                let (addr, path) = match endpoint.find('/') {
+                    Some(i) => (&endpoint[..i], &endpoint[i..]),
+                    None => (endpoint, "/v1/traces"),
+                };
+                Ok(Target::Http(addr.to_owned(), path.to_owned()))
+            }
+            _ => Err(anyhow!("trace requires exactly one of path and endpoint")),
+        }
+    }
+
+    async fn write(&mut self, body: String) -> Result<()> {
+        match self {
+            Target::File(file) => {
+                file.write_all(body.as_bytes())?;
+                file.write_all(b"\n")?;
+                Ok(())
+            }
+            Target::Http(addr, path) => {
+                let mut stream = TcpStream::connect(addr.as_str()).await?;
+                let request = format!(
+                    "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    path,
+                    addr,
+                    body.len(),
+                    body
+                );
+                stream.write_all(request.as_bytes()).await?;
+                let mut response = vec![];
+                stream.read_to_end(&mut response).await?;
+                let response = String::from_utf8_lossy(&response);
+                let status = response.split_whitespace().nth(1).unwrap_or_default();
+                if !status.starts_with('2') {
+                    bail!("collector {} responded with status {}", addr, status);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Export spans of all graphs as configured by `cfg`, which replaces the previous configuration
+pub(crate) fn install(cfg: &Trace) -> Result<()> {
+    let mut target = Target::new(cfg)?;
+    let service_name = cfg.service_name.clone();
+    let (s, r) = unbounded::<SpanData>();
+    crate::rt::task::spawn(async move {
+        while let Ok(span) = r.recv().await {
+            let mut batch = vec![span];
+            while let Ok(span) = r.try_recv() {
+                batch.push(span);
+                if batch.len() >= MAX_BATCH {
+                    break;
+                }
+            }
+            let body = encode(&service_name, &batch).to_string();
+            if let Err(err) = target.write(body).await {
+                log::error!("failed to export {} spans: {}", batch.len(), err);
+            }
+        }
+    });
+    *EXPORTER.lock().unwrap() = Some(s);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+pub(super) fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub(super) fn export(span: SpanData) {
+    if let Some(exporter) = EXPORTER.lock().unwrap().as_ref() {
+        exporter.try_send(span).ok();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let ctx = TraceContext {
+            trace_id: 0xab,
+            span_id: 0xcd,
+        };
+        let mut span = SpanData::start(ctx, Some(1), "a", SpanKind::Internal);
+        span.attributes.push(("megflow.node", "a".to_owned()));
+        let value = encode("test", &[span]);
+        let resource = &value["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "test"
+        );
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "000000000000000000000000000000ab");
+        assert_eq!(span["spanId"], "00000000000000cd");
+        assert_eq!(span["parentSpanId"], "0000000000000001");
+        assert_eq!(span["kind"], 1);
+        assert_eq!(span["attributes"][0]["key"], "megflow.node");
+        assert!(span["startTimeUnixNano"]
+            .as_str()
+            .unwrap()
+            .parse::<u64>()
+            .is_ok());
+    }
+}
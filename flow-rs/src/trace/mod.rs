@@ -0,0 +1,315 @@
+/**
+ * \file flow-rs/src/trace/mod.rs
+ * MegFlow is Licensed under the Apache License, Version 2.0 (the "License")
+ *
+ * Copyright (c) 2019-2021 Megvii Inc. All rights reserved.
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT ARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ */
+mod export;
+
+use crate::envelope::{AnyEnvelope, EnvelopeInfo, SealedEnvelope};
+use export::{SpanData, SpanKind};
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+pub(crate) use export::install;
+
+// an exec receiving more traces than this, e.g. a server looping in one exec, stops opening spans
+const MAX_SPANS_PER_EXEC: usize = 1024;
+
+/// The trace an envelope belongs to, and the span which sent the envelope
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+}
+
+impl TraceContext {
+    /// The trace id in hex, as it is shown by tracing backends
+    pub fn trace_id_hex(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+}
+
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish().max(1)
+}
+
+/// Return true if tracing is configured by `[trace]`
+pub fn is_enabled() -> bool {
+    export::is_enabled()
+}
+
+/// A span exported when it is dropped
+pub struct Span {
+    data: Option<SpanData>,
+}
+
+impl Span {
+    /// Start a span of a new trace, e.g. for a request received by a server node.
+    ///
+    /// The span does nothing if tracing is not enabled.
+    pub fn root(name: &str) -> Span {
+        if !is_enabled() {
+            return Span { data: None };
+        }
+        let ctx = TraceContext {
+            trace_id: ((random_id() as u128) << 64) | random_id() as u128,
+            span_id: random_id(),
+        };
+        Span {
+            data: Some(SpanData::start(ctx, None, name, SpanKind::Server)),
+        }
+    }
+
+    /// The context to put into `EnvelopeInfo::trace`, so that spans of nodes handling the envelope join the trace
+    pub fn context(&self) -> Option<TraceContext> {
+        self.data.as_ref().map(|data| data.ctx)
+    }
+
+    pub fn set_attribute(&mut self, key: &'static str, value: impl ToString) {
+        if let Some(data) = &mut self.data {
+            data.attributes.push((key, value.to_string()));
+        }
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(mut data) = self.data.take() {
+            data.end = SystemTime::now();
+            export::export(data);
+        }
+    }
+}
+
+struct NodeInfo {
+    graph: String,
+    node: String,
+}
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<NodeInfo>>> = RefCell::new(None);
+}
+
+crate::rt::task_local! {
+    static SCOPE: RefCell<Option<Scope>> = RefCell::new(None);
+}
+
+struct ScopeSpan {
+    // the context of the first envelope of the trace received in the exec
+    parent: TraceContext,
+    span_id: u64,
+    start: SystemTime,
+}
+
+// spans of the exec running in the current task, one for each trace received
+struct Scope {
+    node: Arc<NodeInfo>,
+    spans: Vec<ScopeSpan>,
+}
+
+impl Scope {
+    // spans start when the first envelope of the trace is received instead of when the exec starts, which
+    // usually waits for inputs first
+    fn enter(&mut self, ctx: TraceContext) {
+        let is_entered = self
+            .spans
+            .iter()
+            .any(|span| span.parent.trace_id == ctx.trace_id);
+        if !is_entered && self.spans.len() < MAX_SPANS_PER_EXEC {
+            self.spans.push(ScopeSpan {
+                parent: ctx,
+                span_id: random_id(),
+                start: SystemTime::now(),
+            });
+        }
+    }
+
+    // envelopes sent without a trace join the trace if only one is received in the exec
+    fn context_for(&self, trace: Option<TraceContext>) -> Option<TraceContext> {
+        let span = match trace {
+            Some(ctx) => self
+                .spans
+                .iter()
+                .find(|span| span.parent.trace_id == ctx.trace_id)?,
+            None if self.spans.len() == 1 => &self.spans[0],
+            None => return None,
+        };
+        Some(TraceContext {
+            trace_id: span.parent.trace_id,
+            span_id: span.span_id,
+        })
+    }
+}
+
+/// Emits spans of the exec of the node being started in the current thread
+#[derive(Clone, Default)]
+pub struct ExecSpan(Option<Arc<NodeInfo>>);
+
+impl ExecSpan {
+    /// Must be called in `Actor::start` before the actor is spawned
+    pub fn current() -> ExecSpan {
+        ExecSpan(CURRENT.with(|current| current.borrow().clone()))
+    }
+
+    /// Must be called in the task of the actor right before `exec`, spans of traces received during the exec
+    /// are ended when the returned guard is dropped
+    pub fn enter(&self) -> ExecGuard {
+        let node = match &self.0 {
+            Some(node) if is_enabled() => node,
+            _ => return ExecGuard { is_entered: false },
+        };
+        let scope = Scope {
+            node: node.clone(),
+            spans: vec![],
+        };
+        let is_entered = SCOPE
+            .try_with(|current| current.replace(Some(scope)))
+            .is_ok();
+        ExecGuard { is_entered }
+    }
+}
+
+pub struct ExecGuard {
+    is_entered: bool,
+}
+
+impl Drop for ExecGuard {
+    fn drop(&mut self) {
+        if !self.is_entered {
+            return;
+        }
+        let scope = match SCOPE.try_with(|current| current.take()) {
+            Ok(Some(scope)) => scope,
+            _ => return,
+        };
+        let end = SystemTime::now();
+        for span in scope.spans {
+            let ctx = TraceContext {
+                trace_id: span.parent.trace_id,
+                span_id: span.span_id,
+            };
+            let mut data = SpanData::start(
+                ctx,
+                Some(span.parent.span_id),
+                &scope.node.node,
+                SpanKind::Internal,
+            );
+            data.start = span.start;
+            data.end = end;
+            data.attributes
+                .push(("megflow.graph", scope.node.graph.clone()));
+            data.attributes
+                .push(("megflow.node", scope.node.node.clone()));
+            export::export(data);
+        }
+    }
+}
+
+/// Stop emitting spans of the exec running in the current task.
+///
+/// It is for nodes serving in one endless exec, whose spans are emitted by `Span::root` instead.
+pub fn detach_exec() {
+    SCOPE.try_with(|current| current.take()).ok();
+}
+
+/// Call `f`, which starts the node `node` of the graph `graph`
+pub(crate) fn with_node<F, R>(graph: &str, node: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let info = Arc::new(NodeInfo {
+        graph: graph.to_owned(),
+        node: node.to_owned(),
+    });
+    let prev = CURRENT.with(|current| current.replace(Some(info)));
+    let ret = f();
+    CURRENT.with(|current| current.replace(prev));
+    ret
+}
+
+/// Called by receivers, the exec running in the current task joins the trace of the envelope
+pub(crate) fn on_recv(info: &EnvelopeInfo) {
+    if let Some(ctx) = info.trace {
+        SCOPE
+            .try_with(|current| {
+                if let Some(scope) = current.borrow_mut().as_mut() {
+                    scope.enter(ctx);
+                }
+            })
+            .ok();
+    }
+}
+
+/// Called by senders, the envelope is marked as sent by the span of the exec running in the current task
+pub(crate) fn on_send(envelope: &mut SealedEnvelope) {
+    if !is_enabled() {
+        return;
+    }
+    let trace = envelope.info().trace;
+    let ctx = SCOPE
+        .try_with(|current| {
+            current
+                .borrow()
+                .as_ref()
+                .and_then(|scope| scope.context_for(trace))
+        })
+        .ok()
+        .flatten();
+    if ctx.is_some() && ctx != trace {
+        envelope.info_mut().trace = ctx;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_scope() {
+        let mut scope = Scope {
+            node: Arc::new(NodeInfo {
+                graph: "g".to_owned(),
+                node: "a".to_owned(),
+            }),
+            spans: vec![],
+        };
+        let a = TraceContext {
+            trace_id: 1,
+            span_id: 10,
+        };
+        let b = TraceContext {
+            trace_id: 2,
+            span_id: 20,
+        };
+        scope.enter(a);
+        scope.enter(a);
+        let ctx = scope.context_for(None).unwrap();
+        assert_eq!(ctx.trace_id, 1);
+        assert_ne!(ctx.span_id, 10);
+        assert_eq!(scope.context_for(Some(a)), Some(ctx));
+
+        scope.enter(b);
+        assert_eq!(scope.spans.len(), 2);
+        assert_eq!(scope.context_for(None), None);
+        assert_eq!(scope.context_for(Some(b)).unwrap().trace_id, 2);
+        assert_eq!(
+            scope.context_for(Some(TraceContext {
+                trace_id: 3,
+                span_id: 30
+            })),
+            None
+        );
+    }
+}
@@ -0,0 +1,120 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+
+fn build(node: &str) -> Result<MainGraph> {
+    Builder::default()
+        .template(format!(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{}]
+inputs=[{{name="inp",cap=4,ports=["a:inp"]}}]
+outputs=[{{name="out",cap=4,ports=["a:out"]}}]
+        "#,
+            node
+        ))
+        .build()
+}
+
+#[rt::test]
+async fn test_on_failure() -> Result<()> {
+    let mut graph = build(r#"{name="a", ty="FlakyOpr", restart="on-failure"}"#)?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    inp.send(Envelope::new(1i32)).await.ok();
+    inp.send(Envelope::new(-1i32)).await.ok();
+    inp.send(Envelope::new(2i32)).await.ok();
+    inp.close();
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 1);
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 2);
+    assert!(out.recv::<i32>().await.is_err());
+
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_always() -> Result<()> {
+    let mut graph = build(r#"{name="a", ty="OnceOpr", restart="always"}"#)?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    // the node is restarted after it exits with its input open
+    for i in 0..3 {
+        inp.send(Envelope::new(i)).await.ok();
+        assert_eq!(*out.recv::<i32>().await?.get_ref(), i);
+    }
+    inp.close();
+    assert!(out.recv::<i32>().await.is_err());
+
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_exit_on_failure() -> Result<()> {
+    let mut graph = build(r#"{name="a", ty="OnceOpr", restart="on-failure"}"#)?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    // the node exiting without an error is not restarted
+    inp.send(Envelope::new(1i32)).await.ok();
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 1);
+    inp.send(Envelope::new(2i32)).await.ok();
+    assert!(out.recv::<i32>().await.is_err());
+    inp.close();
+
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_max_retries() -> Result<()> {
+    let mut graph =
+        build(r#"{name="a", ty="FlakyOpr", restart="on-failure", max_retries=1, backoff=1}"#)?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+
+    inp.send(Envelope::new(-1i32)).await.ok();
+    inp.send(Envelope::new(-2i32)).await.ok();
+    inp.close();
+    assert!(handle.await.is_err());
+    Ok(())
+}
+
+#[rt::test]
+async fn test_never() -> Result<()> {
+    let mut graph = build(r#"{name="a", ty="FlakyOpr"}"#)?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+
+    inp.send(Envelope::new(-1i32)).await.ok();
+    assert!(handle.await.is_err());
+    Ok(())
+}
+
+#[rt::test]
+async fn test_shared() -> Result<()> {
+    assert!(Builder::default()
+        .template(
+            r#"
+main="test"
+nodes=[{name="a", ty="FlakyOpr", restart="always"}]
+[[graphs]]
+name="test"
+inputs=[{name="inp",cap=1,ports=["a:inp"]}]
+outputs=[{name="out",cap=1,ports=["a:out"]}]
+        "#
+            .to_owned(),
+        )
+        .build()
+        .is_err());
+    Ok(())
+}
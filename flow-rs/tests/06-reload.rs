@@ -0,0 +1,100 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+
+fn config(scale: i32, cap: usize) -> String {
+    format!(
+        r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{{name="a", ty="ScaleOpr", scale={}}}]
+inputs=[{{name="inp",cap={},ports=["a:inp"]}}]
+outputs=[{{name="out",cap=4,ports=["a:out"]}}]
+        "#,
+        scale, cap
+    )
+}
+
+#[rt::test]
+async fn test_reload() -> Result<()> {
+    let mut graph = Builder::default().template(config(1, 4)).build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    inp.send(Envelope::new(1i32)).await.ok();
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 1);
+
+    let report = graph.reload(&config(1, 4), "")?;
+    assert!(report.restarted.is_empty());
+    let report = graph.reload(&config(10, 4), "")?;
+    assert_eq!(report.restarted, vec!["a".to_owned()]);
+
+    // the old instance is stopped after its current exec, so at most one message is not scaled
+    let mut scaled = 0;
+    for i in 2..5i32 {
+        inp.send(Envelope::new(i)).await.ok();
+        if *out.recv::<i32>().await?.get_ref() == i * 10 {
+            scaled += 1;
+        }
+    }
+    assert!(scaled >= 2);
+
+    inp.close();
+    assert!(out.recv::<i32>().await.is_err());
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_refused() -> Result<()> {
+    let mut graph = Builder::default().template(config(1, 4)).build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    assert!(graph.reload(&config(10, 8), "").is_err());
+
+    inp.send(Envelope::new(1i32)).await.ok();
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 1);
+    inp.close();
+    handle.await?;
+    Ok(())
+}
+
+fn json_config(scale: i32) -> String {
+    format!(
+        r#"{{
+    "include": ["./sub.toml"],
+    "main": "test",
+    "graphs": [{{
+        "name": "test",
+        "nodes": [{{"name": "a", "ty": "ScaleOpr", "scale": {}}}],
+        "inputs": [{{"name": "inp", "cap": 4, "ports": ["a:inp"]}}],
+        "outputs": [{{"name": "out", "cap": 4, "ports": ["a:out"]}}]
+    }}]
+}}"#,
+        scale
+    )
+}
+
+#[rt::test]
+async fn test_template_file() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let path = temp_dir.path().join("test.json");
+    std::fs::write(&path, json_config(1))?;
+    std::fs::write(temp_dir.path().join("sub.toml"), "main=\"test\"\ngraphs=[]")?;
+    let mut graph = Builder::default().template_file(&path)?.build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+
+    // parsed as json, and the include is resolved against the template file
+    let report = graph.reload(&json_config(10), "")?;
+    assert_eq!(report.restarted, vec!["a".to_owned()]);
+
+    inp.close();
+    handle.await?;
+    Ok(())
+}
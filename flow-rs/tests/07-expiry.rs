@@ -0,0 +1,107 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+#[rt::test]
+async fn test_ttl() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="a", ty="Transform"}]
+inputs=[{name="inp",cap=4,ports=["a:inp"],ttl_ms=20}]
+outputs=[{name="out",cap=4,ports=["a:out"]}]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+
+    inp.send(Envelope::new(1i32)).await.ok();
+    rt::task::sleep(Duration::from_millis(30)).await;
+    let handle = graph.start();
+    let mut envelope = Envelope::new(2i32);
+    envelope.info_mut().set_timeout(Duration::from_millis(0));
+    inp.send(envelope).await.ok();
+    inp.send(Envelope::new(3i32)).await.ok();
+    inp.close();
+
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 3);
+    assert!(out.recv::<i32>().await.is_err());
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_divert() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[
+    {name="a", ty="Transform"},
+    {name="b", ty="Transform"},
+    {name="c", ty="Transform"},
+]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[
+    {name="out",cap=4,ports=["b:out"]},
+    {name="expired",cap=4,ports=["c:out"]},
+]
+connections=[
+    {cap=4,ports=["a:out","b:inp"],expired="c:inp"},
+    {cap=4,ports=["c:inp"]},
+]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let expired = graph.output("expired").unwrap();
+    let handle = graph.start();
+
+    let mut envelope = Envelope::new(1i32);
+    envelope.info_mut().set_timeout(Duration::from_millis(0));
+    inp.send(envelope).await.ok();
+    inp.send(Envelope::new(2i32)).await.ok();
+    inp.close();
+
+    assert_eq!(*out.recv::<i32>().await?.get_ref(), 2);
+    assert_eq!(*expired.recv::<i32>().await?.get_ref(), 1);
+    assert!(out.recv::<i32>().await.is_err());
+    assert!(expired.recv::<i32>().await.is_err());
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_invalid_divert() -> Result<()> {
+    let build = |expired: &str| {
+        Builder::default()
+            .template(format!(
+                r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{{name="a", ty="Transform"}}, {{name="b", ty="Transform"}}]
+inputs=[{{name="inp",cap=4,ports=["a:inp"]}}]
+outputs=[{{name="out",cap=4,ports=["b:out"]}}]
+connections=[{{cap=4,ports=["a:out","b:inp"],expired="{}"}}]
+        "#,
+                expired
+            ))
+            .build()
+    };
+    assert!(build("b:inp").is_err());
+    assert!(build("a:inp").is_err());
+    assert!(build("c:inp").is_err());
+    Ok(())
+}
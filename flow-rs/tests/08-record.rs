@@ -0,0 +1,73 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use flow_rs::record::{Codec, Event, Recorder, Recording, Replayer};
+
+fn build() -> Result<MainGraph> {
+    Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="a", ty="ScaleOpr", scale=2}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[{name="out",cap=4,ports=["a:out"]}]
+        "#
+            .to_owned(),
+        )
+        .build()
+}
+
+async fn drain(out: Receiver) -> Vec<i32> {
+    let mut msgs = vec![];
+    while let Ok(msg) = out.recv::<i32>().await {
+        msgs.push(*msg.get_ref());
+    }
+    msgs
+}
+
+#[rt::test]
+async fn test_record_replay() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let golden_path = dir.path().join("golden.jsonl");
+    let replayed_path = dir.path().join("replayed.jsonl");
+    let recorder = |path: &std::path::Path| -> Result<Recorder> {
+        Ok(Recorder::create(path)?
+            .codec("inp", Codec::new::<i32>())
+            .codec("out", Codec::new::<i32>()))
+    };
+
+    let mut graph = build()?;
+    graph.record(recorder(&golden_path)?);
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    for i in 1..4i32 {
+        inp.send(Envelope::new(i)).await.ok();
+    }
+    inp.close();
+    assert_eq!(drain(out).await, vec![2, 4, 6]);
+    handle.await?;
+
+    let golden = Recording::load(&golden_path)?;
+    assert_eq!(golden.events("inp").count(), 4);
+    assert_eq!(golden.events("inp").last(), Some(&Event::Close));
+
+    let mut graph = build()?;
+    graph.record(recorder(&replayed_path)?);
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    Replayer::default()
+        .codec("inp", Codec::new::<i32>())
+        .speed(10.0)
+        .replay(&golden, &graph)
+        .await?;
+    assert_eq!(drain(out).await, vec![2, 4, 6]);
+    handle.await?;
+
+    let replayed = Recording::load(&replayed_path)?;
+    assert!(replayed.diff(&golden, &["inp", "out"]).is_empty());
+    Ok(())
+}
@@ -0,0 +1,95 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use flow_rs::trace::Span;
+use serde_json::Value;
+use std::path::Path;
+use std::time::Duration;
+
+// (name, span id, parent span id) of spans of the trace
+fn load_spans(path: &Path, trace_id: &str) -> Result<Vec<(String, String, Option<String>)>> {
+    let mut spans = vec![];
+    if !path.exists() {
+        return Ok(spans);
+    }
+    for line in std::fs::read_to_string(path)?.lines() {
+        let request: Value = serde_json::from_str(line)?;
+        for span in request["resourceSpans"][0]["scopeSpans"][0]["spans"]
+            .as_array()
+            .unwrap()
+        {
+            if span["traceId"] == trace_id {
+                spans.push((
+                    span["name"].as_str().unwrap().to_owned(),
+                    span["spanId"].as_str().unwrap().to_owned(),
+                    span["parentSpanId"].as_str().map(|s| s.to_owned()),
+                ));
+            }
+        }
+    }
+    Ok(spans)
+}
+
+#[rt::test]
+async fn test_trace() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("spans.jsonl");
+    let mut graph = Builder::default()
+        .template(format!(
+            r#"
+main="test"
+trace={{path="{}"}}
+[[graphs]]
+name="test"
+nodes=[{{name="a", ty="ScaleOpr", scale=2}}, {{name="b", ty="ScaleOpr", scale=3}}]
+inputs=[{{name="inp",cap=4,ports=["a:inp"]}}]
+outputs=[{{name="out",cap=4,ports=["b:out"]}}]
+connections=[{{cap=4,ports=["a:out","b:inp"]}}]
+        "#,
+            path.display()
+        ))
+        .build()?;
+    assert!(flow_rs::trace::is_enabled());
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    let root = Span::root("request");
+    let ctx = root.context().unwrap();
+    let envelope = Envelope::with_info(
+        1i32,
+        EnvelopeInfo {
+            trace: Some(ctx),
+            ..Default::default()
+        },
+    );
+    inp.send(envelope).await.ok();
+    let msg = out.recv::<i32>().await.unwrap();
+    assert_eq!(*msg.get_ref(), 6);
+    let received = msg.info().trace.unwrap();
+    assert_eq!(received.trace_id, ctx.trace_id);
+    assert_ne!(received.span_id, ctx.span_id);
+    drop(root);
+    inp.close();
+    handle.await?;
+
+    let trace_id = ctx.trace_id_hex();
+    let mut spans = vec![];
+    for _ in 0..100 {
+        spans = load_spans(&path, &trace_id)?;
+        if spans.len() >= 3 {
+            break;
+        }
+        rt::task::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(spans.len(), 3);
+    let find = |name: &str| spans.iter().find(|span| span.0 == name).unwrap();
+    let (root, a, b) = (find("request"), find("a"), find("b"));
+    assert_eq!(root.1, format!("{:016x}", ctx.span_id));
+    assert_eq!(root.2, None);
+    assert_eq!(a.2.as_ref(), Some(&root.1));
+    assert_eq!(b.2.as_ref(), Some(&a.1));
+    assert_eq!(b.1, format!("{:016x}", received.span_id));
+    Ok(())
+}
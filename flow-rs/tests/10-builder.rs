@@ -0,0 +1,78 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use nodes_ext::ScaleOpr;
+use toml::value::Table;
+
+fn scale(scale: i64) -> Table {
+    let mut args = Table::new();
+    args.insert("scale".to_owned(), scale.into());
+    args
+}
+
+#[rt::test]
+async fn test_graph_builder() -> Result<()> {
+    let a = ScaleOpr::ports("a");
+    let b = ScaleOpr::ports("b");
+    let mut graph = GraphBuilder::new("test")
+        .node("a", "ScaleOpr", scale(2))
+        .node("b", "ScaleOpr", scale(3))
+        .input("inp", a.inp, 4)
+        .connect(a.out, b.inp, 4)
+        .output("out", b.out, 4)
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    for i in 1..4i32 {
+        inp.send(Envelope::new(i)).await.ok();
+    }
+    inp.close();
+    let mut msgs = vec![];
+    while let Ok(msg) = out.recv::<i32>().await {
+        msgs.push(*msg.get_ref());
+    }
+    assert_eq!(msgs, vec![6, 12, 18]);
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_graph_builder_with_template() -> Result<()> {
+    let sub = GraphBuilder::new("sub")
+        .node("a", "ScaleOpr", scale(2))
+        .input("inp", "a:inp", 4)
+        .output("out", "a:out", 4);
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="s", ty="sub"}]
+inputs=[{name="inp",cap=4,ports=["s:inp"]}]
+outputs=[{name="out",cap=4,ports=["s:out"]}]
+        "#
+            .to_owned(),
+        )
+        .graph(sub)
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    inp.send(Envelope::new(5i32)).await.ok();
+    inp.close();
+    assert_eq!(*out.recv::<i32>().await.unwrap().get_ref(), 10);
+    handle.await?;
+    Ok(())
+}
+
+#[test]
+fn test_invalid_port() {
+    let ret = GraphBuilder::new("test")
+        .node("a", "ScaleOpr", scale(2))
+        .input("inp", "a:unknown", 4)
+        .build();
+    assert!(ret.is_err());
+}
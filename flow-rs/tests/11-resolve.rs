@@ -0,0 +1,57 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use flow_rs::Format;
+
+const TEMPLATE: &str = r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[
+    {name="a", ty="ScaleOpr", scale=2},
+    {name="b", ty="ScaleOpr", scale=3},
+    {name="c", ty="ScaleOpr", scale=5},
+]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[{name="b_out",cap=4,ports=["b:out"]}, {name="c_out",cap=4,ports=["c:out"]}]
+connections=[{cap=4,ports=["a:out","b:inp","c:inp"]}]
+"#;
+
+async fn run(mut graph: MainGraph) -> Result<(i32, i32)> {
+    let inp = graph.input("inp").unwrap();
+    let b_out = graph.output("b_out").unwrap();
+    let c_out = graph.output("c_out").unwrap();
+    let handle = graph.start();
+    inp.send(Envelope::new(1i32)).await.ok();
+    inp.close();
+    let b = *b_out.recv::<i32>().await.unwrap().get_ref();
+    let c = *c_out.recv::<i32>().await.unwrap().get_ref();
+    handle.await?;
+    Ok((b, c))
+}
+
+#[rt::test]
+async fn test_resolve() -> Result<()> {
+    let resolved = Builder::default().template(TEMPLATE.to_owned()).resolve()?;
+    let json = resolved.to_json()?;
+    let nodes = json["graphs"][0]["nodes"].as_array().unwrap();
+    // the connection to two inputs is expanded into a broadcast node
+    assert_eq!(nodes.len(), 4);
+    assert!(nodes.iter().any(|node| node["ty"] == "Bcast"));
+
+    let dir = tempfile::tempdir()?;
+    for ext in ["json", "yaml", "toml"] {
+        let path = dir.path().join(format!("resolved.{}", ext));
+        resolved.write(&path)?;
+        let graph = Builder::default().template_file(&path)?.build()?;
+        assert_eq!(run(graph).await?, (6, 10));
+
+        let again = Builder::default().template_file(&path)?.resolve()?;
+        assert_eq!(again.to_json()?, json);
+    }
+    assert!(resolved.emit(Format::Json)?.starts_with('{'));
+    assert!(resolved.to_dot().starts_with("digraph"));
+    assert!(resolved.to_svg().starts_with("<svg"));
+    Ok(())
+}
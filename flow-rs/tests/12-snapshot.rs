@@ -0,0 +1,45 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+#[rt::test]
+async fn test_snapshot() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="a", ty="ScaleOpr", scale=2}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[{name="out",cap=2,ports=["a:out"]}]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    assert!(graph.snapshot_dot().contains("0/2 queued, 0.0/s"));
+
+    for i in 0..3i32 {
+        inp.send(Envelope::new(i)).await.ok();
+    }
+    for _ in 0..100 {
+        if out.len() == 2 {
+            break;
+        }
+        rt::task::sleep(Duration::from_millis(10)).await;
+    }
+    let dot = graph.snapshot_dot();
+    assert!(dot.contains("blocked, 2/2 queued"));
+    assert!(dot.contains("color=red"));
+    assert!(graph.snapshot_svg().contains(r#"stroke="red""#));
+
+    inp.close();
+    while out.recv::<i32>().await.is_ok() {}
+    handle.await?;
+    Ok(())
+}
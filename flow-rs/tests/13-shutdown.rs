@@ -0,0 +1,95 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::{Duration, Instant};
+
+fn build() -> Result<MainGraph> {
+    Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="a", ty="StuckOpr"}, {name="b", ty="ScaleOpr", scale=2}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[{name="out",cap=4,ports=["b:out"]}]
+connections=[{cap=4,ports=["a:out","b:inp"]}]
+        "#
+            .to_owned(),
+        )
+        .build()
+}
+
+#[rt::test]
+async fn test_shutdown_drained() -> Result<()> {
+    let mut graph = build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    for i in 1..3i32 {
+        inp.send(Envelope::new(i)).await.ok();
+    }
+    let report = graph.shutdown(Duration::from_secs(5)).await;
+    assert!(report.drained);
+    assert!(report.cancelled.is_empty());
+    // envelopes in flight are drained through all nodes
+    assert_eq!(*out.recv::<i32>().await.unwrap().get_ref(), 2);
+    assert_eq!(*out.recv::<i32>().await.unwrap().get_ref(), 4);
+    assert!(inp.send(Envelope::new(3i32)).await.is_err());
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_shutdown_timeout() -> Result<()> {
+    let mut graph = build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+    let shutdown = graph.shutdown_handle();
+    inp.send(Envelope::new(-1i32)).await.ok();
+
+    let start = Instant::now();
+    let report = shutdown.shutdown(Duration::from_millis(100)).await;
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!report.drained);
+    // b is waiting for a
+    assert_eq!(report.cancelled, vec!["a", "b"]);
+    // the cancelled nodes are still finalized
+    handle.await?;
+    assert!(nodes_ext::UNSTUCK.lock().unwrap().contains(&"a".to_owned()));
+    Ok(())
+}
+
+#[rt::test]
+async fn test_shutdown_shared() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+nodes=[{name="shared_a", ty="StuckOpr"}]
+[[graphs]]
+name="test"
+nodes=[{name="b", ty="ScaleOpr"}]
+inputs=[{name="inp",cap=4,ports=["shared_a:inp"]}]
+outputs=[{name="out",cap=4,ports=["b:out"]}]
+connections=[{cap=4,ports=["shared_a:out","b:inp"]}]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+    let shutdown = graph.shutdown_handle();
+    inp.send(Envelope::new(-1i32)).await.ok();
+
+    let report = shutdown.shutdown(Duration::from_millis(100)).await;
+    assert!(!report.drained);
+    assert!(report.cancelled.contains(&"shared_a".to_owned()));
+    handle.await?;
+    assert!(nodes_ext::UNSTUCK
+        .lock()
+        .unwrap()
+        .contains(&"shared_a".to_owned()));
+    Ok(())
+}
@@ -0,0 +1,50 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+#[rt::test]
+async fn test_pause() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="sub"
+nodes=[{name="a", ty="ScaleOpr", scale=2}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[{name="out",cap=4,ports=["a:out"]}]
+[[graphs]]
+name="test"
+nodes=[{name="s", ty="sub"}, {name="b", ty="ScaleOpr", scale=3}]
+inputs=[{name="inp",cap=4,ports=["s:inp"]}]
+outputs=[{name="out",cap=4,ports=["b:out"]}]
+connections=[{cap=4,ports=["s:out","b:inp"]}]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    assert!(graph.pause("unknown").is_err());
+    graph.pause("sub")?;
+    graph.pause("b")?;
+    assert!(graph.is_paused("b")?);
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    inp.send(Envelope::new(1i32)).await.ok();
+    rt::task::sleep(Duration::from_millis(50)).await;
+    assert!(out.is_empty());
+    // the subgraph is still paused
+    graph.resume("b")?;
+    rt::task::sleep(Duration::from_millis(50)).await;
+    assert!(out.is_empty());
+    graph.resume("sub")?;
+    assert_eq!(*out.recv::<i32>().await.unwrap().get_ref(), 6);
+    assert!(!graph.is_paused("sub")?);
+
+    inp.close();
+    handle.await?;
+    Ok(())
+}
@@ -0,0 +1,55 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+#[rt::test]
+async fn test_dyn_instances() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="sub"
+nodes=[{name="a", ty="StuckOpr"}, {name="b", ty="NoopConsumer"}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+connections=[{cap=4,ports=["a:out","b:inp"]}]
+[[graphs]]
+name="test"
+nodes=[{name="demux", ty="DynDemux"}, {name="s", ty="sub"}]
+inputs=[{name="inp",cap=4,ports=["demux:inp"]}]
+connections=[{cap=4,ports=["demux:out","s:inp"]}]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+    assert!(graph.dyn_instances()?.is_empty());
+
+    for (addr, n) in [(1, 1i32), (2, -1), (1, 2)] {
+        let mut envelope = Envelope::new(n).seal();
+        envelope.info_mut().to_addr = Some(addr);
+        inp.send_any(envelope).await.ok();
+    }
+    rt::task::sleep(Duration::from_millis(50)).await;
+
+    let instances = graph.dyn_instances()?;
+    let keys: Vec<_> = instances.iter().map(|x| (x.key, x.received)).collect();
+    assert_eq!(keys, vec![(1, 2), (2, 1)]);
+    assert!(instances.iter().all(|x| x.topic == "sub"));
+
+    assert!(graph.kill_dyn("unknown", 2).is_err());
+    assert_eq!(graph.kill_dyn("sub", 3)?, 0);
+    // the instance 2 is stuck, and only the kill can stop it
+    assert_eq!(graph.kill_dyn("sub", 2)?, 1);
+    rt::task::sleep(Duration::from_millis(50)).await;
+    let keys: Vec<_> = graph.dyn_instances()?.iter().map(|x| x.key).collect();
+    assert_eq!(keys, vec![1]);
+
+    inp.close();
+    handle.await?;
+    assert!(graph.dyn_instances().is_err());
+    Ok(())
+}
@@ -0,0 +1,80 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+fn template(idle_timeout: &str) -> String {
+    format!(
+        r#"
+main="test"
+[[graphs]]
+name="sub"
+nodes=[{{name="a", ty="ScaleOpr"}}, {{name="b", ty="NoopConsumer"}}]
+inputs=[{{name="inp",cap=4,ports=["a:inp"]}}]
+connections=[{{cap=4,ports=["a:out","b:inp"]}}]
+[[graphs]]
+name="test"
+nodes=[{{name="demux", ty="DynDemux"}}, {{name="s", ty="sub"}}]
+inputs=[{{name="inp",cap=4,ports=["demux:inp"]}}]
+connections=[{{cap=4,ports=["demux:out","s:inp"]{}}}]
+        "#,
+        idle_timeout
+    )
+}
+
+async fn send(inp: &Sender, addr: u64) {
+    let mut envelope = Envelope::new(1i32).seal();
+    envelope.info_mut().to_addr = Some(addr);
+    inp.send_any(envelope).await.ok();
+}
+
+#[rt::test]
+async fn test_idle_timeout() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(template(",idle_timeout_ms=50"))
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+
+    send(&inp, 1).await;
+    send(&inp, 2).await;
+    for _ in 0..4 {
+        rt::task::sleep(Duration::from_millis(20)).await;
+        send(&inp, 2).await;
+    }
+    // the instance 1 is idle and closed, while the instance 2 is kept alive
+    let keys: Vec<_> = graph.dyn_instances()?.iter().map(|x| x.key).collect();
+    assert_eq!(keys, vec![2]);
+
+    // a closed instance is created again for a returned client
+    send(&inp, 1).await;
+    rt::task::sleep(Duration::from_millis(20)).await;
+    let instances = graph.dyn_instances()?;
+    let instance = instances.iter().find(|x| x.key == 1).unwrap();
+    assert_eq!(instance.received, 1);
+
+    inp.close();
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_idle_timeout_static() -> Result<()> {
+    let ret = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{name="a", ty="ScaleOpr"}, {name="b", ty="ScaleOpr"}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+outputs=[{name="out",cap=4,ports=["b:out"]}]
+connections=[{cap=4,ports=["a:out","b:inp"],idle_timeout_ms=50}]
+        "#
+            .to_owned(),
+        )
+        .build();
+    assert!(ret.is_err());
+    Ok(())
+}
@@ -0,0 +1,85 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+fn template(admission: &str) -> String {
+    format!(
+        r#"
+main="test"
+[[graphs]]
+name="sub"
+nodes=[{{name="a", ty="ScaleOpr"}}, {{name="b", ty="NoopConsumer"}}]
+inputs=[{{name="inp",cap=4,ports=["a:inp"]}}]
+connections=[{{cap=4,ports=["a:out","b:inp"]}}]
+[[graphs]]
+name="test"
+nodes=[{{name="demux", ty="DynDemux"}}, {{name="s", ty="sub"}}]
+inputs=[{{name="inp",cap=4,ports=["demux:inp"]}}]
+connections=[{{cap=4,ports=["demux:out","s:inp"]{}}}]
+        "#,
+        admission
+    )
+}
+
+async fn send(inp: &Sender, addr: u64) {
+    let mut envelope = Envelope::new(1i32).seal();
+    envelope.info_mut().to_addr = Some(addr);
+    inp.send_any(envelope).await.ok();
+}
+
+async fn keys(graph: &MainGraph) -> Result<Vec<u64>> {
+    rt::task::sleep(Duration::from_millis(20)).await;
+    Ok(graph.dyn_instances()?.iter().map(|x| x.key).collect())
+}
+
+#[rt::test]
+async fn test_reject() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(template(r#",max_instances=1,admission="reject""#))
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+
+    send(&inp, 1).await;
+    send(&inp, 2).await;
+    assert_eq!(keys(&graph).await?, vec![1]);
+    assert_eq!(graph.dyn_instances()?[0].received, 1);
+
+    inp.close();
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_evict() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(template(r#",max_instances=2,admission="evict""#))
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+
+    send(&inp, 1).await;
+    send(&inp, 2).await;
+    send(&inp, 1).await;
+    // the instance 2 is the least recently used
+    send(&inp, 3).await;
+    assert_eq!(keys(&graph).await?, vec![1, 3]);
+    // an evicted instance is created again for a returned client
+    send(&inp, 2).await;
+    assert_eq!(keys(&graph).await?, vec![3, 2]);
+
+    inp.close();
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_zero() -> Result<()> {
+    let ret = Builder::default()
+        .template(template(",max_instances=0"))
+        .build();
+    assert!(ret.is_err());
+    Ok(())
+}
@@ -0,0 +1,52 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use nodes_ext::BUILT;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+async fn send(inp: &Sender, addr: u64) {
+    let mut envelope = Envelope::new(1i32).seal();
+    envelope.info_mut().to_addr = Some(addr);
+    inp.send_any(envelope).await.ok();
+}
+
+#[rt::test]
+async fn test_pool() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(
+            r#"
+main="test"
+[[graphs]]
+name="sub"
+nodes=[{name="a", ty="CountedOpr"}, {name="b", ty="NoopConsumer"}]
+inputs=[{name="inp",cap=4,ports=["a:inp"]}]
+connections=[{cap=4,ports=["a:out","b:inp"]}]
+[[graphs]]
+name="test"
+nodes=[{name="demux", ty="DynDemux"}, {name="s", ty="sub"}]
+inputs=[{name="inp",cap=4,ports=["demux:inp"]}]
+connections=[{cap=4,ports=["demux:out","s:inp"],pool_size=2}]
+        "#
+            .to_owned(),
+        )
+        .build()?;
+    let inp = graph.input("inp").unwrap();
+    let handle = graph.start();
+    // subgraphs are built before any stream starts
+    rt::task::sleep(Duration::from_millis(50)).await;
+    assert_eq!(BUILT.load(Ordering::SeqCst), 2);
+
+    send(&inp, 1).await;
+    send(&inp, 2).await;
+    rt::task::sleep(Duration::from_millis(50)).await;
+    let keys: Vec<_> = graph.dyn_instances()?.iter().map(|x| x.key).collect();
+    assert_eq!(keys, vec![1, 2]);
+    // the pool is refilled after instances are taken
+    assert_eq!(BUILT.load(Ordering::SeqCst), 4);
+
+    inp.close();
+    handle.await?;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+
+fn template(mode: &str) -> String {
+    format!(
+        r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{{name="router", ty="Router", mode="{}", routes=[{{tag=["a","b"]}}, {{partial_id=[0,2]}}]}}]
+inputs=[{{name="inp",cap=4,ports=["router:inp"]}}]
+outputs=[{{name="ab",cap=4,ports=["router:out:0"]}}, {{name="head",cap=4,ports=["router:out:1"]}}]
+        "#,
+        mode
+    )
+}
+
+async fn send(inp: &Sender, i: usize, tag: &str) {
+    let mut envelope = Envelope::new(i).seal();
+    envelope.info_mut().tag = Some(tag.to_owned());
+    envelope.info_mut().partial_id = Some(i as u64);
+    inp.send_any(envelope).await.ok();
+}
+
+async fn collect(out: &Receiver) -> Vec<usize> {
+    let mut ret = vec![];
+    while let Ok(envelope) = out.recv::<usize>().await {
+        ret.push(*envelope.get_ref());
+    }
+    ret
+}
+
+#[rt::test]
+async fn test_first() -> Result<()> {
+    let mut graph = Builder::default().template(template("first")).build()?;
+    let inp = graph.input("inp").unwrap();
+    let ab = graph.output("ab").unwrap();
+    let head = graph.output("head").unwrap();
+    let handle = graph.start();
+
+    send(&inp, 0, "a").await;
+    send(&inp, 1, "c").await;
+    send(&inp, 2, "b").await;
+    // matches no route
+    send(&inp, 3, "c").await;
+    inp.close();
+
+    assert_eq!(collect(&ab).await, vec![0, 2]);
+    assert_eq!(collect(&head).await, vec![1]);
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_all() -> Result<()> {
+    let mut graph = Builder::default().template(template("all")).build()?;
+    let inp = graph.input("inp").unwrap();
+    let ab = graph.output("ab").unwrap();
+    let head = graph.output("head").unwrap();
+    let handle = graph.start();
+
+    send(&inp, 0, "a").await;
+    send(&inp, 1, "c").await;
+    send(&inp, 2, "b").await;
+    inp.close();
+
+    assert_eq!(collect(&ab).await, vec![0, 2]);
+    assert_eq!(collect(&head).await, vec![0, 1]);
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_empty() -> Result<()> {
+    let mut graph = Builder::default().template(template("first")).build()?;
+    let inp = graph.input("inp").unwrap();
+    let ab = graph.output("ab").unwrap();
+    let head = graph.output("head").unwrap();
+    let handle = graph.start();
+
+    // empty envelopes match no route, but are sent to all outputs
+    inp.send(Envelope::<usize>::empty()).await.ok();
+    assert!(ab.recv_any().await.unwrap().is_none());
+    assert!(head.recv_any().await.unwrap().is_none());
+    inp.close();
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_order() -> Result<()> {
+    // outputs are placed by their tags, not by the order of connections
+    let template = template("first").replace(
+        r#"outputs=[{name="ab",cap=4,ports=["router:out:0"]}, {name="head",cap=4,ports=["router:out:1"]}]"#,
+        r#"outputs=[{name="head",cap=4,ports=["router:out:1"]}, {name="ab",cap=4,ports=["router:out:0"]}]"#,
+    );
+    let mut graph = Builder::default().template(template).build()?;
+    let inp = graph.input("inp").unwrap();
+    let ab = graph.output("ab").unwrap();
+    let head = graph.output("head").unwrap();
+    let handle = graph.start();
+
+    send(&inp, 0, "a").await;
+    send(&inp, 1, "c").await;
+    inp.close();
+
+    assert_eq!(collect(&ab).await, vec![0]);
+    assert_eq!(collect(&head).await, vec![1]);
+    handle.await?;
+    Ok(())
+}
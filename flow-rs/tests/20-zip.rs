@@ -0,0 +1,125 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::time::Duration;
+
+fn template(args: &str) -> String {
+    format!(
+        r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{{name="zip", ty="Zip"{}}}]
+inputs=[{{name="a",cap=4,ports=["zip:inp:0"]}}, {{name="b",cap=4,ports=["zip:inp:1"]}}]
+outputs=[{{name="out",cap=4,ports=["zip:out"]}}]
+        "#,
+        args
+    )
+}
+
+async fn send(inp: &Sender, msg: usize, partial_id: u64) {
+    let mut envelope = Envelope::new(msg).seal();
+    envelope.info_mut().partial_id = Some(partial_id);
+    inp.send_any(envelope).await.ok();
+}
+
+async fn recv(out: &Receiver) -> Vec<Option<usize>> {
+    let mut envelope = out.recv::<Vec<Option<SealedEnvelope>>>().await.unwrap();
+    envelope
+        .unpack()
+        .iter()
+        .map(|msg| {
+            msg.as_ref()
+                .map(|msg| *msg.downcast_ref::<Envelope<usize>>().unwrap().get_ref())
+        })
+        .collect()
+}
+
+#[rt::test]
+async fn test_zip() -> Result<()> {
+    let mut graph = Builder::default().template(template("")).build()?;
+    let a = graph.input("a").unwrap();
+    let b = graph.input("b").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    send(&b, 10, 0).await;
+    send(&a, 1, 1).await;
+    send(&a, 0, 0).await;
+    assert_eq!(recv(&out).await, vec![Some(0), Some(10)]);
+    send(&b, 11, 1).await;
+    assert_eq!(recv(&out).await, vec![Some(1), Some(11)]);
+
+    // unfinished groups are emitted when the inputs are closed
+    send(&b, 12, 2).await;
+    a.close();
+    b.close();
+    assert_eq!(recv(&out).await, vec![None, Some(12)]);
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_no_partial_id() -> Result<()> {
+    let mut graph = Builder::default().template(template("")).build()?;
+    let a = graph.input("a").unwrap();
+    let b = graph.input("b").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    // envelopes without partial_id are dropped
+    a.send(Envelope::new(0usize)).await.ok();
+    send(&a, 1, 0).await;
+    send(&b, 10, 0).await;
+    assert_eq!(recv(&out).await, vec![Some(1), Some(10)]);
+
+    a.close();
+    b.close();
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_end_closed() -> Result<()> {
+    let mut graph = Builder::default().template(template("")).build()?;
+    let a = graph.input("a").unwrap();
+    let b = graph.input("b").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    // the end of the stream only waits for inputs which are still open
+    send(&a, 0, 0).await;
+    b.close();
+    a.send(Envelope::<usize>::empty()).await.ok();
+    assert_eq!(recv(&out).await, vec![Some(0), None]);
+    assert!(out.recv_any().await?.is_none());
+
+    a.close();
+    handle.await?;
+    Ok(())
+}
+
+#[rt::test]
+async fn test_timeout() -> Result<()> {
+    let mut graph = Builder::default()
+        .template(template(",timeout_ms=50"))
+        .build()?;
+    let a = graph.input("a").unwrap();
+    let b = graph.input("b").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+
+    send(&a, 0, 0).await;
+    rt::task::sleep(Duration::from_millis(100)).await;
+    assert_eq!(recv(&out).await, vec![Some(0), None]);
+    // the late envelope starts a new group
+    send(&b, 10, 0).await;
+    send(&a, 1, 0).await;
+    assert_eq!(recv(&out).await, vec![Some(1), Some(10)]);
+
+    a.close();
+    b.close();
+    handle.await?;
+    Ok(())
+}
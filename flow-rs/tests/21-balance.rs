@@ -0,0 +1,66 @@
+mod nodes_ext;
+
+use anyhow::Result;
+use flow_rs::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+fn template(balance: &str) -> String {
+    format!(
+        r#"
+main="test"
+[[graphs]]
+name="test"
+nodes=[{{name="a", ty="InstanceOpr", cloned=3, balance="{}"}}]
+inputs=[{{name="inp",cap=16,ports=["a:inp"]}}]
+outputs=[{{name="out",cap=16,ports=["a:out"]}}]
+        "#,
+        balance
+    )
+}
+
+// the ids of instances which envelopes of each `from_addr` are dispatched to
+async fn dispatch(
+    balance: &str,
+    from_addr: impl Fn(u64) -> Option<u64>,
+) -> Result<Vec<(Option<u64>, usize)>> {
+    let mut graph = Builder::default().template(template(balance)).build()?;
+    let inp = graph.input("inp").unwrap();
+    let out = graph.output("out").unwrap();
+    let handle = graph.start();
+    for i in 0..12 {
+        let mut envelope = Envelope::new(0usize).seal();
+        envelope.info_mut().from_addr = from_addr(i);
+        inp.send_any(envelope).await.ok();
+    }
+    inp.close();
+    let mut ret = vec![];
+    while let Ok(envelope) = out.recv::<usize>().await {
+        ret.push((envelope.info().from_addr, *envelope.get_ref()));
+    }
+    handle.await?;
+    assert_eq!(ret.len(), 12);
+    Ok(ret)
+}
+
+#[rt::test]
+async fn test_consistent_hash() -> Result<()> {
+    let mut instances: HashMap<_, HashSet<_>> = HashMap::new();
+    for (from_addr, id) in dispatch("consistent_hash", |i| Some(i % 4)).await? {
+        instances.entry(from_addr).or_default().insert(id);
+    }
+    // all envelopes of a stream are processed by the same instance
+    assert_eq!(instances.len(), 4);
+    assert!(instances.values().all(|ids| ids.len() == 1));
+    Ok(())
+}
+
+#[rt::test]
+async fn test_round_robin() -> Result<()> {
+    let mut counts: HashMap<_, usize> = HashMap::new();
+    for (_, id) in dispatch("round_robin", |_| None).await? {
+        *counts.entry(id).or_default() += 1;
+    }
+    assert_eq!(counts.len(), 3);
+    assert!(counts.values().all(|&n| n == 4));
+    Ok(())
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use flow_rs::prelude::*;
 use futures_util::{select, stream::FuturesUnordered, StreamExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use toml::value::Table;
 
 #[inputs(a, b)]
@@ -120,3 +121,186 @@ impl IsolatedNever {
 }
 
 node_register!("IsolatedNever", IsolatedNever);
+
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+struct FlakyOpr {}
+
+impl FlakyOpr {
+    fn new(_name: String, _: &Table) -> Self {
+        Default::default()
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(msg) = self.inp.recv::<i32>().await {
+            if *msg.get_ref() < 0 {
+                return Err(anyhow::anyhow!("negative message"));
+            }
+            self.out.send(msg).await.ok();
+        }
+        Ok(())
+    }
+}
+
+node_register!("FlakyOpr", FlakyOpr);
+
+lazy_static::lazy_static! {
+    /// Names of `StuckOpr` finalized after being stuck
+    #[allow(dead_code)]
+    pub static ref UNSTUCK: std::sync::Mutex<Vec<String>> = Default::default();
+}
+
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+struct StuckOpr {
+    name: String,
+    stuck: bool,
+}
+
+impl StuckOpr {
+    fn new(name: String, _: &Table) -> Self {
+        StuckOpr {
+            name,
+            ..Default::default()
+        }
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {
+        if self.stuck {
+            UNSTUCK.lock().unwrap().push(self.name.clone());
+        }
+    }
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(msg) = self.inp.recv::<i32>().await {
+            if *msg.get_ref() < 0 {
+                self.stuck = true;
+                std::future::pending::<()>().await;
+            }
+            self.out.send(msg).await.ok();
+        }
+        Ok(())
+    }
+}
+
+node_register!("StuckOpr", StuckOpr);
+
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+pub struct ScaleOpr {
+    scale: i32,
+}
+
+impl ScaleOpr {
+    fn new(_name: String, args: &Table) -> Self {
+        ScaleOpr {
+            scale: args.get("scale").and_then(|x| x.as_integer()).unwrap_or(1) as i32,
+            ..Default::default()
+        }
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(msg) = self.inp.recv::<i32>().await {
+            self.out
+                .send(Envelope::new(*msg.get_ref() * self.scale))
+                .await
+                .ok();
+        }
+        Ok(())
+    }
+}
+
+node_register!("ScaleOpr", ScaleOpr);
+
+/// The number of `CountedOpr` built
+#[allow(dead_code)]
+pub static BUILT: AtomicUsize = AtomicUsize::new(0);
+
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+struct CountedOpr {}
+
+impl CountedOpr {
+    fn new(_name: String, _: &Table) -> Self {
+        BUILT.fetch_add(1, Ordering::SeqCst);
+        Default::default()
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(msg) = self.inp.recv_any().await {
+            self.out.send_any(msg).await.ok();
+        }
+        Ok(())
+    }
+}
+
+node_register!("CountedOpr", CountedOpr);
+
+static INSTANCES: AtomicUsize = AtomicUsize::new(0);
+
+/// Replace messages with the id of the instance
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Actor, Default)]
+struct InstanceOpr {
+    id: usize,
+}
+
+impl InstanceOpr {
+    fn new(_name: String, _: &Table) -> Self {
+        InstanceOpr {
+            id: INSTANCES.fetch_add(1, Ordering::SeqCst),
+            ..Default::default()
+        }
+    }
+
+    async fn initialize(&mut self, _: ResourceCollection) {}
+    async fn finalize(&mut self) {}
+    async fn exec(&mut self, _: &Context) -> Result<()> {
+        if let Ok(msg) = self.inp.recv::<usize>().await {
+            self.out.send(msg.repack(self.id)).await.ok();
+        }
+        Ok(())
+    }
+}
+
+node_register!("InstanceOpr", InstanceOpr);
+
+/// Forward one message and exit, while the input is still open
+#[inputs(inp)]
+#[outputs(out)]
+#[derive(Node, Default)]
+struct OnceOpr {}
+
+impl OnceOpr {
+    fn new(_name: String, _: &Table) -> Self {
+        Default::default()
+    }
+}
+
+impl Actor for OnceOpr {
+    fn start(
+        self: Box<Self>,
+        _: Context,
+        _: ResourceCollection,
+    ) -> rt::task::JoinHandle<Result<()>> {
+        rt::task::spawn(async move {
+            if let Ok(msg) = self.inp.recv_any().await {
+                self.out.send_any(msg).await.ok();
+            }
+            Ok(())
+        })
+    }
+}
+
+node_register!("OnceOpr", OnceOpr);